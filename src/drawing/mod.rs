@@ -30,8 +30,35 @@ pub use self::rect::{
 };
 
 mod text;
+#[cfg(feature = "color-glyphs")]
+pub use self::text::draw_text_with_color_glyphs_mut;
 pub use self::text::{
-    draw_text, draw_text_mut, text_size, EdgePosition, GlyphString, GlyphStrings, Position,
+    advances, break_opportunities, caret_position, checked_text_size, draw_block_cursor_mut,
+    draw_caret_mut, draw_fitted_positioned_mut, draw_justified_text_mut, draw_label_value_mut,
+    draw_labels_mut, draw_paragraphs_mut, draw_ruby_mut, draw_selection_mut, draw_shaped_text_mut,
+    draw_text, draw_text_aligned_mut, draw_text_append_mut, draw_text_around_mut,
+    draw_text_at_origin_mut, draw_text_auto_contrast_mut, draw_text_blended_mut,
+    draw_text_bounded_mut, draw_text_cached_mut, draw_text_checked_mut, draw_text_clipped_mut,
+    draw_text_contrast_mut, draw_text_dashed_outline_mut, draw_text_decorated_mut,
+    draw_text_directed_mut, draw_text_embossed_mut, draw_text_fitted_mut, draw_text_flipped_mut,
+    draw_text_gray_mut, draw_text_hollow_mut, draw_text_in_quad_mut, draw_text_linear_mut,
+    draw_text_masked_mut, draw_text_mut, draw_text_on_path_mut, draw_text_opacity_mut,
+    draw_text_opacity_ramp_mut, draw_text_outlined_mut, draw_text_positioned_mut,
+    draw_text_report_mut, draw_text_rotated_mut, draw_text_scaled_ramp_mut, draw_text_spaced_mut,
+    draw_text_stamped_mut, draw_text_subpixel_mut, draw_text_supersampled_mut,
+    draw_text_textured_mut, draw_text_truncated_mut, draw_text_with_background_mut,
+    draw_text_with_fallback_mut, draw_text_with_progress_mut, draw_text_with_reflection_mut,
+    draw_text_with_shadow_mut, draw_vertical_text_mut, draw_wrapped_positioned_mut,
+    draw_wrapped_text_mut, fit_scale, glyph_rects, ink_bounds, logical_bounds, missing_glyphs,
+    multiline_text_size, rasterize_text, rotated_text_bounds, scale_from_pt, scaled_ramp_text_size,
+    selection_rect, spaced_text_size, substring_x_range, text_metrics, text_size,
+    text_size_with_fallback, trimmed_text_size, truncate_with_ellipsis, variable_font,
+    vertical_text_size, wrap_text, wrap_text_around, wrap_text_indented, Aliasing,
+    ArrangedGlyphStrings, BlendMode, DefaultShaper, EdgePosition, Flip, FontStack, FontVariation,
+    GlyphCache, GlyphString, GlyphStrings, HorizontalAlign, InvalidEdgePosition, Label, Layout,
+    LineSpacing, Orientation, Origin, Overflow, ParagraphStyle, Position, RichLine, ShapedGlyph,
+    Shaper, StrokeJoin, TabWidth, TextDecoration, TextDirection, TextDrawReport, TextMeasurer,
+    TextMetrics, TextNotDrawn, TextStyle, VerticalMetric,
 };
 
 // Set pixel at (x, y) to color if this point lies within image bounds,
@@ -1,44 +1,449 @@
 use crate::definitions::{Clamp, Image};
 use crate::drawing::Canvas;
+use crate::filter::gaussian_blur_f32;
+use crate::geometric_transformations::{warp_into, Interpolation, Projection};
+use crate::math::cast;
 use conv::ValueInto;
-use image::{GenericImage, ImageBuffer, Pixel};
+#[cfg(feature = "color-glyphs")]
+use image::imageops::{resize, FilterType};
+use image::{GenericImage, GrayImage, ImageBuffer, Luma, Pixel};
+use itertools::Either;
+use num::Bounded;
 use std::f32;
 use std::i32;
 
 use crate::pixelops::weighted_sum;
-use rusttype::{point, Font, PositionedGlyph, Rect, Scale, VMetrics};
-use std::cmp::max;
+use rusttype::{
+    point, Font, Glyph, GlyphId, OutlineBuilder, Point, PositionedGlyph, Rect, Scale, VMetrics,
+};
+use std::cmp::{max, Ordering};
+use std::collections::HashMap;
+use std::ops::ControlFlow;
 
-use crate::rect::Rect as IpRect;
+use crate::rect::{Rect as IpRect, Region};
+
+/// The vertical distance between the baselines of two consecutive lines of text,
+/// i.e. `ascent - descent + line_gap`. Note that `descent` is negative.
+fn line_advance(v_metrics: &VMetrics) -> f32 {
+    v_metrics.ascent - v_metrics.descent + v_metrics.line_gap
+}
+
+/// The vertical distance between the baselines of consecutive lines of text.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum LineSpacing {
+    /// A multiple of the font's line advance `ascent - descent + line_gap`.
+    /// `Multiplier(1.0)` is the spacing intended by the font.
+    Multiplier(f32),
+    /// A fixed number of pixels, independent of the font and scale.
+    Pixels(f32),
+}
+
+impl Default for LineSpacing {
+    fn default() -> Self {
+        LineSpacing::Multiplier(1.0)
+    }
+}
+
+impl LineSpacing {
+    fn line_advance(&self, v_metrics: &VMetrics) -> f32 {
+        match *self {
+            LineSpacing::Multiplier(factor) => factor * line_advance(v_metrics),
+            LineSpacing::Pixels(pixels) => pixels,
+        }
+    }
+}
+
+/// Horizontal alignment of the lines of a multi-line text.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum HorizontalAlign {
+    /// Every line starts at the left edge of the text box.
+    #[default]
+    Left,
+    /// Every line is centered within the text box.
+    Center,
+    /// Every line ends at the right edge of the text box.
+    Right,
+}
+
+/// The order in which the characters of a line of text are placed.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum TextDirection {
+    /// Characters are placed from left to right.
+    #[default]
+    Ltr,
+    /// Characters are placed from right to left, as in Arabic or Hebrew.
+    ///
+    /// Every character of the text is treated as right-to-left, i.e. no bidirectional
+    /// reordering is performed for embedded left-to-right runs such as numbers.
+    Rtl,
+}
+
+/// The distance between consecutive tab stops.
+///
+/// A tab character moves the pen to the next tab stop, measured from the start of the line.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum TabWidth {
+    /// A fixed number of pixels.
+    Pixels(f32),
+    /// A multiple of the advance width of a space in the font being drawn.
+    Spaces(f32),
+}
+
+impl Default for TabWidth {
+    fn default() -> Self {
+        TabWidth::Spaces(4.0)
+    }
+}
+
+impl TabWidth {
+    fn pixels(&self, scale: Scale, font: &Font) -> f32 {
+        match *self {
+            TabWidth::Pixels(pixels) => pixels,
+            TabWidth::Spaces(spaces) => {
+                spaces * font.glyph(' ').scaled(scale).h_metrics().advance_width
+            }
+        }
+    }
+}
+
+/// Settings which control how glyphs are arranged by `layout_glyphs`.
+#[derive(Copy, Clone, Debug)]
+struct LayoutOptions {
+    align: HorizontalAlign,
+    line_spacing: LineSpacing,
+    /// Extra horizontal space in pixels between consecutive glyphs of a line.
+    tracking: f32,
+    direction: TextDirection,
+    tab_width: TabWidth,
+    /// Width in pixels by which glyphs are horizontally smeared to fake a bold weight.
+    synthetic_bold: f32,
+    /// Horizontal displacement per pixel of height above the baseline, to fake an italic style.
+    shear: f32,
+    /// Whether to adjust the space between pairs of glyphs using the font's kerning table.
+    kerning: bool,
+    /// Whether every digit takes up the advance of the widest digit, so that columns of numbers line up.
+    tabular_figures: bool,
+}
+
+impl Default for LayoutOptions {
+    fn default() -> Self {
+        LayoutOptions {
+            align: HorizontalAlign::default(),
+            line_spacing: LineSpacing::default(),
+            tracking: 0.0,
+            direction: TextDirection::default(),
+            tab_width: TabWidth::default(),
+            synthetic_bold: 0.0,
+            shear: 0.0,
+            kerning: true,
+            tabular_figures: false,
+        }
+    }
+}
+
+impl LayoutOptions {
+    /// Whether glyph coverage must be modified by `synthetic_bold` or `shear` before drawing.
+    fn synthesizes(&self) -> bool {
+        self.synthetic_bold > 0.0 || self.shear != 0.0
+    }
+
+    /// The number of columns added to the right of each glyph's pixel bounding box by `synthetic_bold`.
+    fn bold_columns(&self) -> i32 {
+        if self.synthetic_bold > 0.0 {
+            self.synthetic_bold.ceil() as i32
+        } else {
+            0
+        }
+    }
+
+    /// The horizontal offset which `shear` applies to points at height `y` of a glyph with
+    /// baseline `baseline`.
+    fn shear_offset(&self, y: f32, baseline: f32) -> f32 {
+        self.shear * (baseline - y)
+    }
+
+    /// The pixel bounding box of a glyph with bounding box `bb` and baseline `baseline`, after
+    /// applying `synthetic_bold` and `shear`.
+    fn synthesized_bounds(&self, bb: Rect<i32>, baseline: f32) -> Rect<i32> {
+        let top = self.shear_offset(bb.min.y as f32, baseline);
+        let bottom = self.shear_offset(bb.max.y as f32, baseline);
+        let left = (-top.min(bottom)).max(0.0).ceil() as i32;
+        let right = top.max(bottom).max(0.0).ceil() as i32;
+        Rect {
+            min: point(bb.min.x - left, bb.min.y),
+            max: point(bb.max.x + self.bold_columns() + right, bb.max.y),
+        }
+    }
+}
 
 fn layout_glyphs(
     scale: Scale,
     font: &Font,
     text: &str,
+    options: &LayoutOptions,
     mut f: impl FnMut(PositionedGlyph, Rect<i32>),
 ) -> (i32, i32) {
-    let v_metrics = font.v_metrics(scale);
+    layout_chars(scale, font, text, options, |_, g, bb| f(g, bb))
+}
+
+/// Lays out text as `layout_glyphs` does, additionally passing `f` the character each glyph represents.
+fn layout_chars<'f>(
+    scale: Scale,
+    font: &'f Font<'f>,
+    text: &str,
+    options: &LayoutOptions,
+    f: impl FnMut(char, PositionedGlyph<'f>, Rect<i32>),
+) -> (i32, i32) {
+    let scale = magnitude(scale);
+    layout_chars_with_metrics(scale, font, &font.v_metrics(scale), text, options, f)
+}
+
+/// `scale` with both components made non-negative. Text is laid out at this scale, and mirrored
+/// when drawn in each direction whose scale component is negative.
+fn magnitude(scale: Scale) -> Scale {
+    Scale {
+        x: scale.x.abs(),
+        y: scale.y.abs(),
+    }
+}
+
+/// Lays out text as `layout_chars` does, using the given vertical metrics of `font` at `scale`.
+fn layout_chars_with_metrics<'f>(
+    scale: Scale,
+    font: &'f Font<'f>,
+    v_metrics: &VMetrics,
+    text: &str,
+    options: &LayoutOptions,
+    mut f: impl FnMut(char, PositionedGlyph<'f>, Rect<i32>),
+) -> (i32, i32) {
+    let v_metrics = *v_metrics;
+
+    // The text box is as wide as the widest line, so aligning anything other than
+    // left requires measuring every line up front.
+    let line_widths: Vec<i32> = match options.align {
+        HorizontalAlign::Left => vec![],
+        _ => text
+            .lines()
+            .map(|line| line_width(scale, font, line, options))
+            .collect(),
+    };
+    let box_width = line_widths.iter().copied().max().unwrap_or(0);
 
     let (mut w, mut h) = (0, 0);
 
-    for g in font.layout(text, scale, point(0.0, v_metrics.ascent)) {
-        if let Some(bb) = g.pixel_bounding_box() {
-            w = max(w, bb.max.x);
-            h = max(h, bb.max.y);
-            f(g, bb);
-        }
+    // Each line starts at its aligned x offset and lies one line advance below the previous one.
+    // A single-line string is laid out exactly once at y = ascent.
+    for (i, line) in text.lines().enumerate() {
+        let x = match options.align {
+            HorizontalAlign::Left => 0.0,
+            HorizontalAlign::Center => (box_width - line_widths[i]) as f32 / 2.0,
+            HorizontalAlign::Right => (box_width - line_widths[i]) as f32,
+        };
+        let y = v_metrics.ascent + i as f32 * options.line_spacing.line_advance(&v_metrics);
+        layout_line(scale, font, line, point(x, y), options, |c, g| {
+            if let Some(bb) = g.pixel_bounding_box() {
+                let bb = options.synthesized_bounds(bb, g.position().y);
+                w = max(w, bb.max.x);
+                h = max(h, bb.max.y);
+                f(c, g, bb);
+            }
+        });
     }
 
     (w, h)
 }
 
-/// Get the width and height of the given text, rendered with the given font and scale. Note that this function *does not* support newlines, you must do this manually.
-pub fn text_size(scale: Scale, font: &Font, text: &str) -> (i32, i32) {
-    layout_glyphs(scale, font, text, |_, _| {})
+/// Positions the glyphs of a single line of text, with the baseline of the first glyph at `origin`,
+/// calling `f` with each glyph and the character it represents.
+///
+/// This matches `Font::layout`, except that kerning can be disabled, `options.tracking` is added between consecutive glyphs,
+/// right-to-left text is placed in reverse order, tab characters advance the pen to the next tab stop
+/// and every advance is widened by `options.synthetic_bold`. With `options.tabular_figures`, each ASCII digit is
+/// centered in the advance of the widest digit and is not kerned. Returns the x coordinate of the pen after
+/// the last character.
+fn layout_line<'f>(
+    scale: Scale,
+    font: &'f Font,
+    line: &str,
+    origin: Point<f32>,
+    options: &LayoutOptions,
+    mut f: impl FnMut(char, PositionedGlyph<'f>),
+) -> f32 {
+    let mut caret = origin;
+    let mut last_glyph = None;
+    let mut last_tabular = false;
+    let digit_advance = match options.tabular_figures {
+        true => ('0'..='9')
+            .map(|d| font.glyph(d).scaled(scale).h_metrics().advance_width)
+            .fold(0.0, f32::max),
+        false => 0.0,
+    };
+
+    // Glyphs are always positioned from left to right, so right-to-left text is laid out in reverse
+    let chars = match options.direction {
+        TextDirection::Ltr => Either::Left(line.chars()),
+        TextDirection::Rtl => Either::Right(line.chars().rev()),
+    };
+
+    for c in chars {
+        if c == '\t' {
+            let tab_width = options.tab_width.pixels(scale, font);
+            if tab_width > 0.0 {
+                let tabs = ((caret.x - origin.x) / tab_width).floor() + 1.0;
+                caret.x = origin.x + tabs * tab_width;
+            }
+            last_glyph = None;
+            continue;
+        }
+
+        let g = font.glyph(c).scaled(scale);
+        let tabular = options.tabular_figures && c.is_ascii_digit();
+        if let Some(last) = last_glyph {
+            if options.kerning && !tabular && !last_tabular {
+                caret.x += font.pair_kerning(scale, last, g.id());
+            }
+            caret.x += options.tracking;
+        }
+        last_glyph = Some(g.id());
+        last_tabular = tabular;
+        let natural = g.h_metrics().advance_width;
+        let (offset, advance) = match tabular {
+            true => ((digit_advance - natural) / 2.0, digit_advance),
+            false => (0.0, natural),
+        };
+        f(c, g.positioned(point(caret.x + offset, caret.y)));
+        caret.x += advance + options.synthetic_bold.max(0.0);
+    }
+
+    caret.x
 }
 
-/// Draws colored text on an image in place. `scale` is augmented font scaling on both the x and y axis (in pixels). Note that this function *does not* support newlines, you must do this manually.
-pub fn draw_text_mut<'a, C>(
+/// The width of the glyphs of a single line of text, which is unaffected by trailing whitespace.
+fn line_width(scale: Scale, font: &Font, line: &str, options: &LayoutOptions) -> i32 {
+    let options = LayoutOptions {
+        align: HorizontalAlign::Left,
+        ..*options
+    };
+    layout_glyphs(scale, font, line, &options, |_, _| {}).0
+}
+
+/// The position of the caret after the first `char_index` characters of `text`, as the pen x coordinate
+/// and the baseline y coordinate relative to the top-left corner of the text box.
+///
+/// Left-to-right layout is assumed, whatever `options.direction` is. Text at a negative scale is mirrored
+/// within its text box, and the caret with it.
+fn caret_offset(
+    scale: Scale,
+    font: &Font,
+    text: &str,
+    char_index: usize,
+    options: &LayoutOptions,
+) -> (f32, f32) {
+    let (x, baseline) = unmirrored_caret_offset(magnitude(scale), font, text, char_index, options);
+    if scale.x >= 0.0 && scale.y >= 0.0 {
+        return (x, baseline);
+    }
+    let (w, h) = measure(scale, font, text, options);
+    let x = if scale.x < 0.0 { w as f32 - x } else { x };
+    let baseline = if scale.y < 0.0 {
+        h as f32 - baseline
+    } else {
+        baseline
+    };
+    (x, baseline)
+}
+
+/// The position of the caret as given by `caret_offset`, for text laid out at a non-negative `scale`.
+fn unmirrored_caret_offset(
+    scale: Scale,
+    font: &Font,
+    text: &str,
+    char_index: usize,
+    options: &LayoutOptions,
+) -> (f32, f32) {
+    let v_metrics = font.v_metrics(scale);
+    let options = LayoutOptions {
+        direction: TextDirection::Ltr,
+        ..*options
+    };
+    let box_width = match options.align {
+        HorizontalAlign::Left => 0,
+        _ => text
+            .lines()
+            .map(|line| line_width(scale, font, line, &options))
+            .max()
+            .unwrap_or(0),
+    };
+
+    // Each line is followed by the newline which ends it, so the caret can also be placed after that
+    let mut remaining = char_index;
+    let mut lines = text.split('\n').enumerate().peekable();
+    while let Some((i, line)) = lines.next() {
+        let count = line.chars().count();
+        if remaining > count && lines.peek().is_some() {
+            remaining -= count + 1;
+            continue;
+        }
+
+        let origin = point(
+            match options.align {
+                HorizontalAlign::Left => 0.0,
+                HorizontalAlign::Center => {
+                    (box_width - line_width(scale, font, line, &options)) as f32 / 2.0
+                }
+                HorizontalAlign::Right => {
+                    (box_width - line_width(scale, font, line, &options)) as f32
+                }
+            },
+            v_metrics.ascent + i as f32 * options.line_spacing.line_advance(&v_metrics),
+        );
+        let prefix_end = line
+            .char_indices()
+            .nth(remaining)
+            .map_or(line.len(), |(end, _)| end);
+        let mut x = layout_line(
+            scale,
+            font,
+            &line[..prefix_end],
+            origin,
+            &options,
+            |_, _| {},
+        );
+
+        // Between two glyphs, the caret is placed at the start of the second, after any kerning and tracking
+        if let Some(next) = line[prefix_end..].chars().next() {
+            if next != '\t' && remaining > 0 {
+                let next_end = prefix_end + next.len_utf8();
+                layout_line(scale, font, &line[..next_end], origin, &options, |_, g| {
+                    x = g.position().x;
+                });
+            }
+        }
+        return (x, origin.y);
+    }
+
+    (0.0, v_metrics.ascent)
+}
+
+/// Get the position of the caret, or insertion point, after the first `char_index` characters of `text`.
+///
+/// Returns the x coordinate of the pen and the y coordinate of the baseline of the line containing the caret,
+/// relative to the top-left corner of the text box as drawn by [`draw_text_mut`](fn.draw_text_mut.html).
+/// Between two characters the caret lies at the start of the second, after kerning. A newline counts as a
+/// character, so the caret after it lies at the start of the next line. Indices past the end of `text` give
+/// the position at the end of `text`. For each negative component of `scale` the text is mirrored within its
+/// text box, as by `draw_text_mut`, and so is the caret position.
+pub fn caret_position(scale: Scale, font: &Font, text: &str, char_index: usize) -> (i32, i32) {
+    let (x, y) = caret_offset(scale, font, text, char_index, &LayoutOptions::default());
+    (x.round() as i32, y.round() as i32)
+}
+
+/// Draws a one pixel wide vertical caret after the first `char_index` characters of `text`, when `text` is drawn
+/// at `(x, y)` by [`draw_text_mut`](fn.draw_text_mut.html). The text itself is not drawn.
+///
+/// The caret is placed as described by [`caret_position`](fn.caret_position.html), and reaches from the font's
+/// ascent above the baseline to its descent below it.
+pub fn draw_caret_mut<'a, C>(
     canvas: &'a mut C,
     color: C::Pixel,
     x: i32,
@@ -46,182 +451,5511 @@ pub fn draw_text_mut<'a, C>(
     scale: Scale,
     font: &'a Font<'a>,
     text: &'a str,
+    char_index: usize,
 ) where
     C: Canvas,
     <C::Pixel as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
 {
-    let image_width = canvas.width() as i32;
-    let image_height = canvas.height() as i32;
+    let (cx, baseline) = caret_offset(scale, font, text, char_index, &LayoutOptions::default());
+    draw_caret_line(canvas, color, x, y, scale, font, cx, baseline);
+}
 
-    layout_glyphs(scale, font, text, |g, bb| {
-        g.draw(|gx, gy, gv| {
-            let gx = gx as i32 + bb.min.x;
-            let gy = gy as i32 + bb.min.y;
+/// Draws a block cursor over a character cell, e.g. for a terminal renderer: `cell` is filled with the
+/// color of `style`, and `ch` is then drawn on top of it in `style` but in the `background` color, so the
+/// character under the cursor appears inverted.
+///
+/// The text box of `ch` has its top-left corner at the top-left corner of `cell`, as each character of a
+/// terminal line is placed at the origin of its cell. Parts of the glyph outside `cell` are also drawn.
+pub fn draw_block_cursor_mut<C>(
+    canvas: &mut C,
+    cell: IpRect,
+    ch: char,
+    style: &TextStyle<C::Pixel>,
+    background: C::Pixel,
+) where
+    C: Canvas,
+    <C::Pixel as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    for cy in cell.top()..=cell.bottom() {
+        for cx in cell.left()..=cell.right() {
+            blend_coverage(canvas, cx, cy, style.color, 1.0);
+        }
+    }
 
-            let image_x = gx + x;
-            let image_y = gy + y;
+    let mut buffer = [0; 4];
+    style
+        .color(background)
+        .draw_mut(canvas, cell.left(), cell.top(), ch.encode_utf8(&mut buffer));
+}
 
-            if (0..image_width).contains(&image_x) && (0..image_height).contains(&image_y) {
-                let pixel = canvas.get_pixel(image_x as u32, image_y as u32);
-                let weighted_color = weighted_sum(pixel, color, 1.0 - gv, gv);
-                canvas.draw_pixel(image_x as u32, image_y as u32, weighted_color);
-            }
-        })
-    });
+/// Draws a one pixel wide vertical line at `x + cx`, spanning the font's ascent and descent around
+/// the baseline at `y + baseline`. For a negative `scale.y` lines are upside down, so the ascent lies
+/// below the baseline.
+fn draw_caret_line<C>(
+    canvas: &mut C,
+    color: C::Pixel,
+    x: i32,
+    y: i32,
+    scale: Scale,
+    font: &Font,
+    cx: f32,
+    baseline: f32,
+) where
+    C: Canvas,
+    <C::Pixel as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    let v_metrics = font.v_metrics(magnitude(scale));
+    let (above, below) = match scale.y < 0.0 {
+        true => (-v_metrics.descent, v_metrics.ascent),
+        false => (v_metrics.ascent, -v_metrics.descent),
+    };
+    let top = (baseline - above).round() as i32;
+    let bottom = (baseline + below).round() as i32;
+    let cx = x + cx.round() as i32;
+    for cy in top..bottom {
+        blend_coverage(canvas, cx, y + cy, color, 1.0);
+    }
 }
 
-/// Draws colored text on an image in place. `scale` is augmented font scaling on both the x and y axis (in pixels). Note that this function *does not* support newlines, you must do this manually.
-pub fn draw_text<'a, I>(
-    image: &'a mut I,
-    color: I::Pixel,
+/// Get the horizontal extent of the characters of `text` with char indices in `start..end`, e.g. to highlight a
+/// search match. Indices count characters rather than bytes, as for [`caret_position`](fn.caret_position.html).
+///
+/// Returns the unrounded x coordinates of the caret positions at `start` and `end`, relative to the left edge of
+/// the text box as drawn by [`draw_text_mut`](fn.draw_text_mut.html). Unlike measuring `text[start..end]` on its
+/// own, this accounts for kerning with the neighbouring characters on both sides. Indices past the end of `text`
+/// are clamped to it, and an empty range gives the same coordinate twice. Each end is measured on its own line
+/// if `text` contains newlines, so this is intended for single lines of text.
+///
+/// For a negative `scale.x` the text is mirrored within its text box, so the caret position at `start` is
+/// the right end of the returned range rather than the left.
+pub fn substring_x_range(
+    scale: Scale,
+    font: &Font,
+    text: &str,
+    start: usize,
+    end: usize,
+) -> (f32, f32) {
+    let options = LayoutOptions::default();
+    let (start_x, _) = caret_offset(scale, font, text, start.min(end), &options);
+    let (end_x, _) = caret_offset(scale, font, text, end, &options);
+    if scale.x < 0.0 {
+        (end_x.min(start_x), start_x)
+    } else {
+        (start_x, end_x.max(start_x))
+    }
+}
+
+/// Get the rectangle covering the characters of `text` with char indices in `start..end`, e.g. to highlight
+/// a selection. Indices count characters rather than bytes, and a newline counts as one character, as for
+/// [`caret_position`](fn.caret_position.html).
+///
+/// The rectangle is relative to the top-left corner of the text box as drawn by [`draw_text_mut`](fn.draw_text_mut.html).
+/// It reaches horizontally between the caret positions at `start` and `end`, and vertically from the font's
+/// ascent above the baseline to its descent below it. If the range spans several lines the rectangle covers
+/// all of them, from the left edge of the text box to the end of the longest of these lines.
+/// Returns `None` if the range is empty. For each negative component of `scale` the text is mirrored within
+/// its text box, as by `draw_text_mut`, and so is the rectangle.
+pub fn selection_rect(
+    scale: Scale,
+    font: &Font,
+    text: &str,
+    start: usize,
+    end: usize,
+) -> Option<IpRect> {
+    if scale.x < 0.0 || scale.y < 0.0 {
+        let rect = selection_rect(magnitude(scale), font, text, start, end)?;
+        let (w, h) = text_size(scale, font, text);
+        let left = match scale.x < 0.0 {
+            true => w - 1 - rect.right(),
+            false => rect.left(),
+        };
+        let top = match scale.y < 0.0 {
+            true => h - 1 - rect.bottom(),
+            false => rect.top(),
+        };
+        return Some(IpRect::at(left, top).of_size(rect.width(), rect.height()));
+    }
+
+    let options = LayoutOptions::default();
+    let char_count = text.chars().count();
+    let (start, end) = (start.min(char_count), end.min(char_count));
+    if start >= end {
+        return None;
+    }
+
+    let v_metrics = font.v_metrics(scale);
+    let (start_x, start_baseline) = caret_offset(scale, font, text, start, &options);
+    let (end_x, end_baseline) = caret_offset(scale, font, text, end, &options);
+    let (left, right) = if start_baseline == end_baseline {
+        (start_x, end_x)
+    } else {
+        let first_line = text.chars().take(start).filter(|&c| c == '\n').count();
+        let last_line = text.chars().take(end).filter(|&c| c == '\n').count();
+        let right = text
+            .split('\n')
+            .skip(first_line)
+            .take(last_line - first_line + 1)
+            .map(|line| advance_width(scale, font, line, &options))
+            .fold(end_x, f32::max);
+        (0.0, right)
+    };
+
+    let (left, right) = (left.round() as i32, right.round() as i32);
+    let top = (start_baseline - v_metrics.ascent).round() as i32;
+    let bottom = (end_baseline - v_metrics.descent).round() as i32;
+    if right <= left || bottom <= top {
+        return None;
+    }
+    Some(IpRect::at(left, top).of_size((right - left) as u32, (bottom - top) as u32))
+}
+
+/// Fills the [`selection_rect`](fn.selection_rect.html) of the characters of `text` with char indices in
+/// `start..end`, when `text` is drawn at `(x, y)`. The text itself is not drawn, so this should be called
+/// before drawing the text on top.
+pub fn draw_selection_mut<'a, C>(
+    canvas: &'a mut C,
+    color: C::Pixel,
     x: i32,
     y: i32,
     scale: Scale,
     font: &'a Font<'a>,
     text: &'a str,
-) -> Image<I::Pixel>
-where
-    I: GenericImage,
-    <I::Pixel as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
-    I::Pixel: 'static,
+    start: usize,
+    end: usize,
+) where
+    C: Canvas,
+    <C::Pixel as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
 {
-    let mut out = ImageBuffer::new(image.width(), image.height());
-    out.copy_from(image, 0, 0).unwrap();
-    draw_text_mut(&mut out, color, x, y, scale, font, text);
-    out
+    if let Some(rect) = selection_rect(scale, font, text, start, end) {
+        for sy in rect.top()..=rect.bottom() {
+            for sx in rect.left()..=rect.right() {
+                blend_coverage(canvas, x + sx, y + sy, color, 1.0);
+            }
+        }
+    }
 }
 
-/// This helper function is used to find the top (or) left corner of a text.
-/// It takes handles only one dimension per call to make it more reusable.
-/// It takes a `rectangle_size` which is the length (width or height) of the surrounding rectangle
-/// and a `content_size` which is the length (width or height) of the text inside.
-/// The `relative_position` is then used to divide the space
-/// which is not used up by the content (=`content_size`) to determine a padding
-/// inside the rectangle `rectangle_size`.
-fn calculate_center(
-    rectangle_size: u32,
-    content_size: u32,
-    relative_position: &EdgePosition,
-) -> u32 {
-    ((rectangle_size - content_size) as f32 * relative_position.0 / 100.0) as u32
+/// Get the width and height of the given text, rendered with the given font and scale.
+///
+/// The text may contain newlines, in which case the returned size is the bounding box
+/// of all lines. See [`draw_text_mut`](fn.draw_text_mut.html) for how lines are spaced.
+///
+/// The width is measured to the furthest pixel drawn, so depends on the shapes of the glyphs. The height
+/// reaches at least to the font's descent below the baseline of the last line, so boxes sized using it
+/// leave room for descenders whether or not the text contains any, e.g. `"Tops"` and `"gjpqy"` have the
+/// same height. Text which draws nothing, whether because it is empty, only contains whitespace or is drawn
+/// at a scale of zero, has a size of `(0, 0)`; use [`checked_text_size`](fn.checked_text_size.html) to tell
+/// these cases apart. See [`logical_bounds`](fn.logical_bounds.html) for a measurement based on advances
+/// and line metrics.
+pub fn text_size(scale: Scale, font: &Font, text: &str) -> (i32, i32) {
+    measure(scale, font, text, &LayoutOptions::default())
 }
 
-fn find_text_area_coordinates(
-    position: &Position,
-    rectangle: &IpRect,
-    width: u32,
-    height: u32,
-) -> (u32, u32) {
-    match position {
-        Position::HorizontalCenter(edge_position) => (
-            rectangle.left() as u32 + calculate_center(rectangle.width(), width, edge_position),
-            rectangle.top() as u32 + ((rectangle.height() - height) / 2) as u32,
-        ),
-        Position::HorizontalBottom(edge_position) => (
-            rectangle.left() as u32 + calculate_center(rectangle.width(), width, edge_position),
-            rectangle.bottom() as u32 - height,
-        ),
-        Position::HorizontalTop(edge_position) => (
-            rectangle.left() as u32 + calculate_center(rectangle.width(), width, edge_position),
-            rectangle.top() as u32,
-        ),
-        Position::VerticalCenter(edge_position) => (
-            rectangle.left() as u32 + ((rectangle.width() - width) / 2) as u32,
-            rectangle.top() as u32 + calculate_center(rectangle.height(), height, edge_position),
-        ),
-        Position::VerticalRight(edge_position) => (
-            rectangle.right() as u32 - width,
-            rectangle.top() as u32 + calculate_center(rectangle.height(), height, edge_position),
-        ),
-        Position::VerticalLeft(edge_position) => (
-            rectangle.left() as u32,
-            rectangle.top() as u32 + calculate_center(rectangle.height(), height, edge_position),
-        ),
-        Position::Any(horizontal_edge, vertical_edge) => (
-            rectangle.left() as u32 + calculate_center(rectangle.width(), width, horizontal_edge),
-            rectangle.top() as u32 + calculate_center(rectangle.height(), height, vertical_edge),
-        ),
+/// Get the size of the given text as [`text_size`](fn.text_size.html) does, distinguishing empty text
+/// from text which draws nothing.
+///
+/// Returns `None` if `text` is the empty string. Otherwise returns the size given by `text_size`, which
+/// is `(0, 0)` if the text contains characters but none of them draw anything, e.g. if it is only
+/// whitespace or line breaks or `scale` is zero. Measurement never fails for any other reason: characters
+/// missing from the font are measured using the font's glyph for missing characters.
+pub fn checked_text_size(scale: Scale, font: &Font, text: &str) -> Option<(i32, i32)> {
+    if text.is_empty() {
+        None
+    } else {
+        Some(text_size(scale, font, text))
     }
 }
 
-/// An arrangement of glyphs which can be drawn onto an image.
-/// This string also knows about its size, according to scaling and font properties.
-pub struct GlyphString<'a> {
-    glyphs: Vec<PositionedGlyph<'a>>,
+/// Get the width and height of the given text as [`text_size`](fn.text_size.html) does, but ignoring
+/// any space before the first inked pixel, e.g. leading whitespace or the left side bearing of the first
+/// glyph, together with the x offset of that pixel from the left of the text box.
+///
+/// The width spans from the leftmost to the rightmost inked pixel of every line, so trailing whitespace
+/// is ignored too, whereas the height is that of `text_size`. Drawing the text at `x - offset` places
+/// its ink exactly at `x`, which allows text with asymmetric whitespace to be centered visually. Text
+/// which draws nothing has a size of `(0, 0)` and an offset of zero.
+pub fn trimmed_text_size(scale: Scale, font: &Font, text: &str) -> ((i32, i32), i32) {
+    let (mut left, mut right) = (i32::MAX, 0);
+    layout_glyphs(scale, font, text, &LayoutOptions::default(), |_, bb| {
+        left = left.min(bb.min.x);
+        right = right.max(bb.max.x);
+    });
+    if left > right {
+        return ((0, 0), 0);
+    }
+    let (_, height) = text_size(scale, font, text);
+    ((right - left, height), left)
 }
 
-impl<'a> GlyphString<'a> {
-    /// Construct a `GlyphString` from `text` scaled by `scale` using the Font `font`.
-    pub fn new(scale: Scale, font: &'a Font<'a>, text: &'a str) -> Self {
-        let v_metrics = font.v_metrics(scale);
-        let offset = point(0.0, v_metrics.ascent);
-
-        let glyphs = font.layout(text, scale, offset).collect();
+/// The size of the text box of the laid out text, with room for the descent of the last line.
+fn measure(scale: Scale, font: &Font, text: &str, options: &LayoutOptions) -> (i32, i32) {
+    let size = layout_glyphs(scale, font, text, options, |_, _| {});
+    reserve_descent(
+        size,
+        &font.v_metrics(magnitude(scale)),
+        text,
+        options.line_spacing,
+    )
+}
 
-        Self { glyphs }
+/// Extends the height of the ink box `size` of `text` to reach at least the descent below the
+/// baseline of its last line. Empty boxes are unchanged.
+fn reserve_descent(
+    size: (i32, i32),
+    v_metrics: &VMetrics,
+    text: &str,
+    line_spacing: LineSpacing,
+) -> (i32, i32) {
+    let (w, h) = size;
+    if w <= 0 || h <= 0 {
+        return size;
     }
 
-    /// Find out how much horizontal space this `GlyphString` needs when drawn.
-    // https://docs.rs/artano/0.2.8/src/artano/annotation.rs.html#270-277
-    pub fn width(&self) -> u32 {
-        2 + self
-            .glyphs
-            .iter()
-            .map(|glyph| glyph.unpositioned().h_metrics().advance_width)
-            .sum::<f32>() as u32
-    }
+    let lines = text.lines().count().max(1);
+    let last_baseline =
+        v_metrics.ascent + (lines - 1) as f32 * line_spacing.line_advance(v_metrics);
+    let bottom = (last_baseline - v_metrics.descent).ceil() as i32;
+    (w, max(h, bottom))
+}
 
-    /// Find out how much vertical space this `GlyphString` needs when drawn.
-    pub fn height(&self) -> u32 {
-        self.glyphs
-            .first()
-            .map(|glyph| {
-                let scale = glyph.scale();
-                let font = glyph.font();
+/// Measures text drawn with a single font, remembering the font's vertical metrics at each scale used.
+///
+/// Functions such as [`text_size`](fn.text_size.html) look up the font's vertical metrics on every call.
+/// When measuring many short strings, e.g. to lay out a table, a `TextMeasurer` avoids repeating this work.
+/// Results are identical to those of the corresponding free functions.
+#[derive(Debug)]
+pub struct TextMeasurer<'a> {
+    font: &'a Font<'a>,
+    /// Vertical metrics keyed by the bit patterns of the horizontal and vertical scale.
+    v_metrics: HashMap<(u32, u32), VMetrics>,
+}
 
-                let VMetrics {
-                    ascent, descent, ..
-                } = font.v_metrics(scale);
-                ((ascent - descent) as f32 * 1.1) as u32
-            })
-            .unwrap_or(0)
+impl<'a> TextMeasurer<'a> {
+    /// Creates a measurer for text drawn with `font`.
+    pub fn new(font: &'a Font<'a>) -> Self {
+        TextMeasurer {
+            font,
+            v_metrics: HashMap::new(),
+        }
     }
 
-    /// Draws this `GlyphString` onto the `image` at the given coordinates `x` and `y`.
-    /// For an out-of-place version use [`GlyphString::draw`](#method.draw).
-    /// Behaves identical to [`draw_text_mut`](fn.draw_text_mut)
-    pub fn draw_mut<I>(&self, image: &mut I, color: I::Pixel, x: u32, y: u32)
-    where
-        I: GenericImage,
-        <I::Pixel as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
-    {
-        for g in self.glyphs.iter() {
-            if let Some(bb) = g.pixel_bounding_box() {
-                g.draw(|gx, gy, gv| {
-                    let gx = gx as i32 + bb.min.x;
-                    let gy = gy as i32 + bb.min.y;
-
-                    let image_x = gx + x as i32;
-                    let image_y = gy + y as i32;
-
-                    let image_width = image.width() as i32;
-                    let image_height = image.height() as i32;
-
-                    if image_x >= 0
-                        && image_x < image_width
-                        && image_y >= 0
-                        && image_y < image_height
-                    {
-                        let pixel = image.get_pixel(image_x as u32, image_y as u32);
-                        let weighted_color = weighted_sum(pixel, color, 1.0 - gv, gv);
-                        image.put_pixel(image_x as u32, image_y as u32, weighted_color);
-                    }
-                })
-            }
-        }
+    /// The font's vertical metrics at `scale`.
+    pub fn v_metrics(&mut self, scale: Scale) -> VMetrics {
+        let font = self.font;
+        *self
+            .v_metrics
+            .entry((scale.x.to_bits(), scale.y.to_bits()))
+            .or_insert_with(|| font.v_metrics(scale))
     }
 
-    /// Draws this `GlyphString` onto a copy of `image` at the given coordinates `x` and `y` and return the copy.
+    /// The width and height of `text`, as returned by [`text_size`](fn.text_size.html).
+    pub fn text_size(&mut self, scale: Scale, text: &str) -> (i32, i32) {
+        let scale = magnitude(scale);
+        let v_metrics = self.v_metrics(scale);
+        let options = LayoutOptions::default();
+        let size =
+            layout_chars_with_metrics(scale, self.font, &v_metrics, text, &options, |_, _, _| {});
+        reserve_descent(size, &v_metrics, text, options.line_spacing)
+    }
+}
+
+/// Measurements of a piece of text, as returned by [`text_metrics`](fn.text_metrics.html).
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TextMetrics {
+    /// Width of the text box, as returned by [`text_size`](fn.text_size.html).
+    pub width: i32,
+    /// Height of the text box, as returned by [`text_size`](fn.text_size.html).
+    pub height: i32,
+    /// Distance from the baseline to the highest point of any glyph in the font. Positive.
+    pub ascent: f32,
+    /// Distance from the baseline to the lowest point of any glyph in the font. Negative.
+    pub descent: f32,
+    /// Extra space the font recommends between the descent of one line and the ascent of the next.
+    pub line_gap: f32,
+    /// Distance from the top of the text box to the baseline of the first line.
+    pub baseline: f32,
+}
+
+/// Get the size of the given text together with the font's vertical metrics at `scale`.
+///
+/// This is useful for aligning text with other elements, e.g. to draw text so that its baseline
+/// lies on a given line, draw it at `y = line - metrics.baseline`.
+///
+/// The font's metrics are given at the magnitude of `scale`. For a negative `scale.y` the text is drawn
+/// upside down and mirrored within its text box, so the baseline of the first line lies `ascent` above
+/// the bottom of the text box rather than below its top.
+pub fn text_metrics(scale: Scale, font: &Font, text: &str) -> TextMetrics {
+    let (width, height) = text_size(scale, font, text);
+    let v_metrics = font.v_metrics(magnitude(scale));
+    let baseline = match scale.y < 0.0 {
+        true => height as f32 - v_metrics.ascent,
+        false => v_metrics.ascent,
+    };
+    TextMetrics {
+        width,
+        height,
+        ascent: v_metrics.ascent,
+        descent: v_metrics.descent,
+        line_gap: v_metrics.line_gap,
+        baseline,
+    }
+}
+
+/// Get the pixel bounding box of each character of the given text, in the order the characters appear.
+///
+/// Rectangles are relative to the top-left corner of the text box, as drawn by
+/// [`draw_text_mut`](fn.draw_text_mut.html), so they can be offset by the drawing position and used for
+/// hit-testing. Characters which draw nothing, such as whitespace and newlines, are omitted.
+pub fn glyph_rects(scale: Scale, font: &Font, text: &str) -> Vec<(char, IpRect)> {
+    let mut rects = vec![];
+    layout_chars(scale, font, text, &LayoutOptions::default(), |c, _, bb| {
+        let rect = IpRect::at(bb.min.x, bb.min.y).of_size(bb.width() as u32, bb.height() as u32);
+        rects.push((c, rect));
+    });
+    rects
+}
+
+/// Get the advance of each glyph of the given text, in the order the characters appear, when drawn by
+/// [`draw_text_mut`](fn.draw_text_mut.html).
+///
+/// The advance of a glyph is the horizontal distance from its position to that of the next glyph on the
+/// same line, so includes the kerning between the two, and the advance of the last glyph of a line is its
+/// advance width. So the advances of a line sum to its width as measured by
+/// [`logical_bounds`](fn.logical_bounds.html), before rounding. Every character other than line breaks and
+/// tabs has a glyph, including whitespace, and the space a tab moves the pen by is part of the advance of
+/// the glyph before it.
+pub fn advances(scale: Scale, font: &Font, text: &str) -> Vec<f32> {
+    let mut advances = vec![];
+    for line in text.lines() {
+        let mut positions = vec![];
+        let end = layout_line(
+            scale,
+            font,
+            line,
+            point(0.0, 0.0),
+            &LayoutOptions::default(),
+            |_, g| positions.push(g.position().x),
+        );
+        positions.push(end);
+        advances.extend(positions.windows(2).map(|pair| pair[1] - pair[0]));
+    }
+    advances
+}
+
+/// Get the tight pixel bounding box of the given text, when drawn by [`draw_text_mut`](fn.draw_text_mut.html).
+///
+/// This is the union of the pixel bounding boxes of all glyphs, relative to the top-left corner of the
+/// text box, so it may start to the right of or below `(0, 0)`. It depends on the shapes of the glyphs
+/// drawn, e.g. `"ace"` has a lower top than `"ACE"`. The right edge agrees with the width returned by
+/// [`text_size`](fn.text_size.html), whose height also leaves room for descenders. Returns `None` if
+/// nothing would be drawn.
+pub fn ink_bounds(scale: Scale, font: &Font, text: &str) -> Option<IpRect> {
+    let mut bounds = Bounds(None);
+    layout_glyphs(scale, font, text, &LayoutOptions::default(), |_, bb| {
+        bounds.add(bb.min.x, bb.min.y);
+        bounds.add(bb.max.x - 1, bb.max.y - 1);
+    });
+    bounds.rect()
+}
+
+/// Get the box the given text occupies according to glyph advances and the font's line metrics,
+/// when drawn by [`draw_text_mut`](fn.draw_text_mut.html).
+///
+/// The box starts at the top-left corner of the text box. It is as wide as the advance of the longest
+/// line, including kerning, and reaches from the ascent of the first line to the descent of the last,
+/// both rounded up to whole pixels. Unlike [`ink_bounds`](fn.ink_bounds.html) it depends only on
+/// which characters are drawn and not on their shapes, so is the better choice for placing text
+/// consistently, e.g. `"ace"` and `"ACE"` have the same height. Returns `None` for empty text.
+pub fn logical_bounds(scale: Scale, font: &Font, text: &str) -> Option<IpRect> {
+    let options = LayoutOptions::default();
+    let v_metrics = font.v_metrics(scale);
+    let lines = text.lines().count();
+    let width = text
+        .lines()
+        .map(|line| advance_width(scale, font, line, &options))
+        .fold(0.0, f32::max)
+        .ceil();
+    let height = v_metrics.ascent - v_metrics.descent
+        + lines.saturating_sub(1) as f32 * line_advance(&v_metrics);
+
+    if lines == 0 || width <= 0.0 {
+        return None;
+    }
+    Some(IpRect::at(0, 0).of_size(width as u32, height.ceil() as u32))
+}
+
+/// Get the width and height of the given multi-line text, with consecutive baselines
+/// separated according to `line_spacing`.
+///
+/// `text_size(scale, font, text)` is equivalent to
+/// `multiline_text_size(scale, font, text, LineSpacing::default())`.
+pub fn multiline_text_size(
+    scale: Scale,
+    font: &Font,
+    text: &str,
+    line_spacing: LineSpacing,
+) -> (i32, i32) {
+    let options = LayoutOptions {
+        line_spacing,
+        ..LayoutOptions::default()
+    };
+    measure(scale, font, text, &options)
+}
+
+/// Draws colored text on an image in place. `scale` is augmented font scaling on both the x and y axis (in pixels).
+///
+/// Text is split into lines at each `\n`. Every line starts at `x`, and the baseline of each line
+/// lies `ascent - descent + line_gap` pixels below the previous one, using the font's vertical metrics
+/// at `scale`. A trailing newline does not add an extra line.
+///
+/// On images with an alpha channel, text is composited over the existing pixels using its own alpha
+/// scaled by glyph coverage, so text drawn on a transparent layer keeps its color at anti-aliased edges.
+///
+/// A negative component of `scale` mirrors the text in that direction, as for
+/// [`draw_text_flipped_mut`](fn.draw_text_flipped_mut.html). The text is laid out at the absolute value
+/// of `scale` and then mirrored within its text box as reported by [`text_size`](fn.text_size.html), which
+/// ignores the signs of `scale`. So with a negative `scale.y` the first baseline, normally `ascent` pixels
+/// below the top of this box, lies `ascent` pixels above its bottom, and mirrored text covers the same box
+/// as unmirrored text.
+pub fn draw_text_mut<'a, C>(
+    canvas: &'a mut C,
+    color: C::Pixel,
+    x: i32,
+    y: i32,
+    scale: Scale,
+    font: &'a Font<'a>,
+    text: &'a str,
+) where
+    C: Canvas,
+    <C::Pixel as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    draw_text_bounded_mut(canvas, color, x, y, scale, font, text);
+}
+
+/// Draws text on a grayscale image in place, producing the same output as
+/// [`draw_text_mut`](fn.draw_text_mut.html) up to rounding, but faster.
+///
+/// `draw_text_mut` blends each coverage sample into the canvas via the generic, floating point
+/// `weighted_sum`. Here each sample is instead blended directly as
+/// `(prev * (255 - c) + color * c) / 255` in integer arithmetic, where `c` is the coverage scaled to `[0, 255]`.
+pub fn draw_text_gray_mut(
+    image: &mut GrayImage,
+    color: Luma<u8>,
+    x: i32,
+    y: i32,
+    scale: Scale,
+    font: &Font,
+    text: &str,
+) {
+    let (width, height) = image.dimensions();
+    let color = color[0] as u32;
+    let options = LayoutOptions::default();
+    for_each_sample(scale, font, text, &options, |gx, gy, gv| {
+        let (ix, iy) = (gx + x, gy + y);
+        if ix < 0 || iy < 0 || ix >= width as i32 || iy >= height as i32 {
+            return;
+        }
+        let coverage = (gv.clamp(0.0, 1.0) * 255.0).round() as u32;
+        let pixel = image.get_pixel_mut(ix as u32, iy as u32);
+        let prev = pixel[0] as u32;
+        pixel[0] = ((prev * (255 - coverage) + color * coverage + 127) / 255) as u8;
+    });
+}
+
+/// Draws a single line of colored text on an image in place, starting with the pen at `x` on the
+/// baseline `baseline`, and returns the x coordinate of the pen after the last glyph's advance.
+///
+/// This allows a line to be built up from several fragments, e.g. with different colors, fonts or scales,
+/// by passing the returned pen position as `x` to the next call. Positions are not rounded between calls,
+/// so the result matches drawing the text in a single call. If the previous fragment used the same font
+/// and scale then passing its last character as `previous` also applies the kerning between the two
+/// fragments; otherwise pass `None`.
+pub fn draw_text_append_mut<'a, C>(
+    canvas: &'a mut C,
+    color: C::Pixel,
+    x: f32,
+    baseline: f32,
+    previous: Option<char>,
+    scale: Scale,
+    font: &'a Font<'a>,
+    text: &'a str,
+) -> f32
+where
+    C: Canvas,
+    <C::Pixel as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    let options = LayoutOptions::default();
+    let kerning = match (previous, text.chars().next()) {
+        (Some(previous), Some(first)) => font.pair_kerning(scale, previous, first),
+        _ => 0.0,
+    };
+    layout_line(
+        scale,
+        font,
+        text,
+        point(x + kerning, baseline),
+        &options,
+        |_, g| {
+            if let Some(bb) = g.pixel_bounding_box() {
+                for_each_glyph_sample(&g, bb, &options, |gx, gy, gv| {
+                    blend_coverage(canvas, gx, gy, color, gv);
+                });
+            }
+        },
+    )
+}
+
+/// Which point of some text is placed at the coordinates passed to
+/// [`draw_text_at_origin_mut`](fn.draw_text_at_origin_mut.html).
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum Origin {
+    /// `(x, y)` is the top-left corner of the text box, as for [`draw_text_mut`](fn.draw_text_mut.html).
+    /// The top row of the box lies the font's ascent, rounded to the nearest pixel, above the first baseline.
+    #[default]
+    TopLeft,
+    /// `(x, y)` is the start of the baseline of the first line: the pen starts at column `x`, and
+    /// the baseline lies within half a pixel of the top edge of row `y`. So glyphs without descenders,
+    /// such as 'H', end in row `y - 1` or partially cover row `y`.
+    Baseline,
+    /// `(x, y)` is the center of the ink box, as given by [`ink_bounds`](fn.ink_bounds.html). For a box of
+    /// width `w` whose leftmost column is `left`, column `x` is `left + w / 2` (rounding down), and
+    /// similarly for rows.
+    Center,
+}
+
+/// Draws colored text on an image in place, with `origin` determining which point of the text lies at `(x, y)`.
+///
+/// `draw_text_at_origin_mut(canvas, color, x, y, Origin::TopLeft, scale, font, text)` is equivalent to
+/// `draw_text_mut(canvas, color, x, y, scale, font, text)`. Nothing is drawn for text with no ink when using
+/// `Origin::Center`.
+pub fn draw_text_at_origin_mut<'a, C>(
+    canvas: &'a mut C,
+    color: C::Pixel,
+    x: i32,
+    y: i32,
+    origin: Origin,
+    scale: Scale,
+    font: &'a Font<'a>,
+    text: &'a str,
+) where
+    C: Canvas,
+    <C::Pixel as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    let (x, y) = match origin {
+        Origin::TopLeft => (x, y),
+        Origin::Baseline => (x, y - font.v_metrics(scale).ascent.round() as i32),
+        Origin::Center => match ink_bounds(scale, font, text) {
+            Some(ink) => (
+                x - ink.left() - ink.width() as i32 / 2,
+                y - ink.top() - ink.height() as i32 / 2,
+            ),
+            None => return,
+        },
+    };
+    draw_text_mut(canvas, color, x, y, scale, font, text);
+}
+
+/// Draws colored text on an image in place, as [`draw_text_mut`](fn.draw_text_mut.html) does, and returns
+/// the smallest rectangle containing every pixel which was drawn to.
+///
+/// The returned rectangle is clamped to the canvas bounds, so it can be passed directly to other drawing
+/// functions, e.g. to draw a border around the text. Returns `None` if no pixel was drawn, either
+/// because the text is empty or because it lies entirely outside the canvas.
+pub fn draw_text_bounded_mut<'a, C>(
+    canvas: &'a mut C,
+    color: C::Pixel,
+    x: i32,
+    y: i32,
+    scale: Scale,
+    font: &'a Font<'a>,
+    text: &'a str,
+) -> Option<IpRect>
+where
+    C: Canvas,
+    <C::Pixel as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    let options = LayoutOptions::default();
+    draw_text_with_options_mut(canvas, color, x, y, scale, font, text, &options)
+}
+
+/// How much of some text was visible when drawn by [`draw_text_report_mut`](fn.draw_text_report_mut.html).
+///
+/// Only glyphs which have any ink are counted, so e.g. spaces are neither drawn nor clipped.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct TextDrawReport {
+    /// The number of glyphs of which at least one pixel lies on the canvas.
+    pub glyphs_drawn: usize,
+    /// The number of glyphs of which at least one pixel lies outside the canvas. A glyph which is only
+    /// partly visible counts as both drawn and clipped.
+    pub glyphs_clipped: usize,
+    /// Whether every pixel of the text lies on the canvas, i.e. no glyph was clipped.
+    pub contained: bool,
+}
+
+/// Draws colored text on an image in place, as [`draw_text_mut`](fn.draw_text_mut.html) does, and reports
+/// how many glyphs were drawn and how many were cut off by the edges of the canvas.
+///
+/// This is useful for detecting text which does not fit, e.g. to reposition or shrink it.
+pub fn draw_text_report_mut<'a, C>(
+    canvas: &'a mut C,
+    color: C::Pixel,
+    x: i32,
+    y: i32,
+    scale: Scale,
+    font: &'a Font<'a>,
+    text: &'a str,
+) -> TextDrawReport
+where
+    C: Canvas,
+    <C::Pixel as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    let options = LayoutOptions::default();
+    let mut report = TextDrawReport::default();
+    let mirror = sample_mirror(scale, font, text, &options);
+    layout_glyphs(scale, font, text, &options, |g, bb| {
+        let (mut drawn, mut clipped) = (false, false);
+        for_each_glyph_sample(&g, bb, &options, |gx, gy, gv| {
+            let (gx, gy) = mirror(gx, gy);
+            if gv > 0.0 {
+                if blend_coverage(canvas, gx + x, gy + y, color, gv) {
+                    drawn = true;
+                } else {
+                    clipped = true;
+                }
+            }
+        });
+        report.glyphs_drawn += drawn as usize;
+        report.glyphs_clipped += clipped as usize;
+    });
+    report.contained = report.glyphs_clipped == 0;
+    report
+}
+
+/// Why [`draw_text_checked_mut`](fn.draw_text_checked_mut.html) drew nothing.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TextNotDrawn {
+    /// The text has no ink, e.g. it is empty or only whitespace.
+    NoInk,
+    /// The ink box of the text, in canvas coordinates, lies entirely outside the canvas.
+    OffCanvas(IpRect),
+}
+
+impl std::fmt::Display for TextNotDrawn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TextNotDrawn::NoInk => write!(f, "text has no ink to draw"),
+            TextNotDrawn::OffCanvas(rect) => write!(
+                f,
+                "text ink box {}x{} at ({}, {}) lies outside the canvas",
+                rect.width(),
+                rect.height(),
+                rect.left(),
+                rect.top()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for TextNotDrawn {}
+
+/// Draws colored text on an image in place, as [`draw_text_mut`](fn.draw_text_mut.html) does, but fails
+/// instead of silently drawing nothing when no part of the text would be visible.
+///
+/// Returns the part of the text's [`ink_bounds`](fn.ink_bounds.html) which lies on the canvas, in canvas
+/// coordinates. Text which is only partly visible is drawn and succeeds; use
+/// [`draw_text_report_mut`](fn.draw_text_report_mut.html) to detect clipping as well.
+pub fn draw_text_checked_mut<'a, C>(
+    canvas: &'a mut C,
+    color: C::Pixel,
+    x: i32,
+    y: i32,
+    scale: Scale,
+    font: &'a Font<'a>,
+    text: &'a str,
+) -> Result<IpRect, TextNotDrawn>
+where
+    C: Canvas,
+    <C::Pixel as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    let ink = ink_bounds(scale, font, text).ok_or(TextNotDrawn::NoInk)?;
+    let ink = IpRect::at(ink.left() + x, ink.top() + y).of_size(ink.width(), ink.height());
+    let (width, height) = canvas.dimensions();
+    let visible = if width == 0 || height == 0 {
+        None
+    } else {
+        ink.intersect(IpRect::at(0, 0).of_size(width, height))
+    };
+    let visible = visible.ok_or(TextNotDrawn::OffCanvas(ink))?;
+    draw_text_mut(canvas, color, x, y, scale, font, text);
+    Ok(visible)
+}
+
+/// Draws colored text on an image in place, as [`draw_text_mut`](fn.draw_text_mut.html) does, but only
+/// partially covering the image, e.g. for a watermark.
+///
+/// `opacity` is clamped to lie between 0 (invisible) and 1 (the same as `draw_text_mut`). It composes
+/// multiplicatively with the anti-aliasing coverage of the glyphs, so a pixel half covered by a glyph
+/// and drawn at an opacity of 0.4 is blended with a weight of 0.2.
+pub fn draw_text_opacity_mut<'a, C>(
+    canvas: &'a mut C,
+    color: C::Pixel,
+    x: i32,
+    y: i32,
+    opacity: f32,
+    scale: Scale,
+    font: &'a Font<'a>,
+    text: &'a str,
+) where
+    C: Canvas,
+    <C::Pixel as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    let opacity = opacity.clamp(0.0, 1.0);
+    for_each_sample(
+        scale,
+        font,
+        text,
+        &LayoutOptions::default(),
+        |gx, gy, gv| {
+            blend_coverage(canvas, gx + x, gy + y, color, gv * opacity);
+        },
+    );
+}
+
+/// Draws text on an image in place, as [`draw_text_mut`](fn.draw_text_mut.html) does, but filled with
+/// a repeating texture instead of a solid color, e.g. for metallic or patterned titles.
+///
+/// The texture is tiled across the canvas starting with its top-left corner at the top-left corner of the
+/// text box `(x, y)`, and each pixel covered by the text is blended towards the texture pixel behind it
+/// according to the glyph coverage. Nothing is drawn if `texture` is empty.
+pub fn draw_text_textured_mut<'a, C>(
+    canvas: &'a mut C,
+    texture: &Image<C::Pixel>,
+    x: i32,
+    y: i32,
+    scale: Scale,
+    font: &'a Font<'a>,
+    text: &'a str,
+) where
+    C: Canvas,
+    C::Pixel: 'static,
+    <C::Pixel as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    let (width, height) = texture.dimensions();
+    if width == 0 || height == 0 {
+        return;
+    }
+    let options = LayoutOptions::default();
+    for_each_sample(scale, font, text, &options, |gx, gy, gv| {
+        let color = *texture.get_pixel(
+            gx.rem_euclid(width as i32) as u32,
+            gy.rem_euclid(height as i32) as u32,
+        );
+        blend_coverage(canvas, gx + x, gy + y, color, gv);
+    });
+}
+
+/// Draws colored text on an image in place, as [`draw_text_mut`](fn.draw_text_mut.html) does, one line at
+/// a time, calling `progress(lines_done, total_lines)` after drawing each line, e.g. to report the progress
+/// of rendering a long document and allow the user to cancel it.
+///
+/// If `progress` returns `ControlFlow::Break` then no further lines are drawn and `Break` is returned, so
+/// the canvas contains exactly the first `lines_done` lines of the text. Otherwise `Continue` is returned
+/// once every line has been drawn. Empty text has no lines, so `progress` is never called.
+pub fn draw_text_with_progress_mut<'a, C>(
+    canvas: &'a mut C,
+    color: C::Pixel,
+    x: i32,
+    y: i32,
+    scale: Scale,
+    font: &'a Font<'a>,
+    text: &'a str,
+    mut progress: impl FnMut(usize, usize) -> ControlFlow<()>,
+) -> ControlFlow<()>
+where
+    C: Canvas,
+    <C::Pixel as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    let options = LayoutOptions::default();
+    let mirror = sample_mirror(scale, font, text, &options);
+    let scale = magnitude(scale);
+    let v_metrics = font.v_metrics(scale);
+    let total = text.lines().count();
+    for (i, line) in text.lines().enumerate() {
+        let baseline = v_metrics.ascent + i as f32 * options.line_spacing.line_advance(&v_metrics);
+        layout_line(scale, font, line, point(0.0, baseline), &options, |_, g| {
+            if let Some(bb) = g.pixel_bounding_box() {
+                for_each_glyph_sample(&g, bb, &options, |gx, gy, gv| {
+                    let (gx, gy) = mirror(gx, gy);
+                    blend_coverage(canvas, gx + x, gy + y, color, gv);
+                });
+            }
+        });
+        progress(i + 1, total)?;
+    }
+    ControlFlow::Continue(())
+}
+
+/// A glyph placed by a [`Shaper`](trait.Shaper.html).
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ShapedGlyph {
+    /// The id of the glyph in the font.
+    pub id: GlyphId,
+    /// The position of the glyph's origin relative to the start of the line on its baseline, in pixels.
+    /// Positive y is down.
+    pub position: Point<f32>,
+}
+
+/// Converts a line of text into positioned glyphs, for use by
+/// [`draw_shaped_text_mut`](fn.draw_shaped_text_mut.html).
+///
+/// Scripts such as Arabic and the Indic scripts need glyphs to be substituted, combined and reordered
+/// depending on their context, which rusttype does not do. Implementing this trait with a shaping library,
+/// e.g. a binding to HarfBuzz, allows such text to be drawn.
+pub trait Shaper {
+    /// Returns the glyphs to draw for a single line of `text`, which contains no line breaks, in `font`
+    /// at `scale`.
+    fn shape(&self, font: &Font, scale: Scale, text: &str) -> Vec<ShapedGlyph>;
+}
+
+/// The [`Shaper`](trait.Shaper.html) used by default, which maps each character to a single glyph
+/// and places them left to right with kerning, as `Font::layout` does.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct DefaultShaper;
+
+impl Shaper for DefaultShaper {
+    fn shape(&self, font: &Font, scale: Scale, text: &str) -> Vec<ShapedGlyph> {
+        font.layout(text, scale, point(0.0, 0.0))
+            .map(|g| ShapedGlyph {
+                id: g.id(),
+                position: g.position(),
+            })
+            .collect()
+    }
+}
+
+/// Draws colored text on an image in place, with each line converted into glyphs by `shaper`.
+///
+/// Lines are positioned as by [`draw_text_mut`](fn.draw_text_mut.html), with the top-left corner of the
+/// text box at `(x, y)`, and with [`DefaultShaper`](struct.DefaultShaper.html) the result is the same as
+/// `draw_text_mut` for text without tabs. Lines are shaped at the absolute value of `scale`, and negative
+/// components of `scale` mirror the glyphs within the text box reported by [`text_size`](fn.text_size.html).
+pub fn draw_shaped_text_mut<'a, C>(
+    canvas: &'a mut C,
+    color: C::Pixel,
+    x: i32,
+    y: i32,
+    scale: Scale,
+    font: &'a Font<'a>,
+    text: &'a str,
+    shaper: &impl Shaper,
+) where
+    C: Canvas,
+    <C::Pixel as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    let options = LayoutOptions::default();
+    let mirror = sample_mirror(scale, font, text, &options);
+    let scale = magnitude(scale);
+    let v_metrics = font.v_metrics(scale);
+    for (i, line) in text.lines().enumerate() {
+        let baseline = v_metrics.ascent + i as f32 * options.line_spacing.line_advance(&v_metrics);
+        for shaped in shaper.shape(font, scale, line) {
+            let position = point(shaped.position.x, baseline + shaped.position.y);
+            let g = font.glyph(shaped.id).scaled(scale).positioned(position);
+            if let Some(bb) = g.pixel_bounding_box() {
+                for_each_glyph_sample(&g, bb, &options, |gx, gy, gv| {
+                    let (gx, gy) = mirror(gx, gy);
+                    blend_coverage(canvas, gx + x, gy + y, color, gv);
+                });
+            }
+        }
+    }
+}
+
+/// Draws colored text on an image in place, as [`draw_text_opacity_mut`](fn.draw_text_opacity_mut.html)
+/// does, but with a separate opacity for each glyph, e.g. to fade text in one letter at a time.
+///
+/// `opacity` is called with the index of each glyph, counting the characters of `text` other than line
+/// breaks and tabs from zero, and its result is clamped to lie between 0 and 1.
+pub fn draw_text_opacity_ramp_mut<'a, C>(
+    canvas: &'a mut C,
+    color: C::Pixel,
+    x: i32,
+    y: i32,
+    opacity: impl Fn(usize) -> f32,
+    scale: Scale,
+    font: &'a Font<'a>,
+    text: &'a str,
+) where
+    C: Canvas,
+    <C::Pixel as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    let options = LayoutOptions::default();
+    let mirror = sample_mirror(scale, font, text, &options);
+    let scale = magnitude(scale);
+    let v_metrics = font.v_metrics(scale);
+    let mut index = 0;
+    for (i, line) in text.lines().enumerate() {
+        let baseline = v_metrics.ascent + i as f32 * options.line_spacing.line_advance(&v_metrics);
+        layout_line(scale, font, line, point(0.0, baseline), &options, |_, g| {
+            let glyph_opacity = opacity(index).clamp(0.0, 1.0);
+            index += 1;
+            if let (true, Some(bb)) = (glyph_opacity > 0.0, g.pixel_bounding_box()) {
+                for_each_glyph_sample(&g, bb, &options, |gx, gy, gv| {
+                    let (gx, gy) = mirror(gx, gy);
+                    blend_coverage(canvas, gx + x, gy + y, color, gv * glyph_opacity);
+                });
+            }
+        });
+    }
+}
+
+/// Draws colored text on an image in place, as [`draw_text_mut`](fn.draw_text_mut.html) does, but only
+/// where it is let through by `mask`, e.g. to show text only inside the shape of a logo.
+///
+/// `mask` is aligned with the canvas, so the coverage of the text at each canvas pixel `(cx, cy)` is
+/// multiplied by the value of `mask` at `(cx, cy)`, scaled to lie between 0 and 1. Pixels outside the
+/// bounds of `mask` are masked out entirely.
+pub fn draw_text_masked_mut<'a, C>(
+    canvas: &'a mut C,
+    color: C::Pixel,
+    x: i32,
+    y: i32,
+    scale: Scale,
+    font: &'a Font<'a>,
+    text: &'a str,
+    mask: &GrayImage,
+) where
+    C: Canvas,
+    <C::Pixel as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    let (width, height) = mask.dimensions();
+    let options = LayoutOptions::default();
+    for_each_sample(scale, font, text, &options, |gx, gy, gv| {
+        let (cx, cy) = (gx + x, gy + y);
+        if cx < 0 || cy < 0 || cx >= width as i32 || cy >= height as i32 {
+            return;
+        }
+        let visible = mask.get_pixel(cx as u32, cy as u32)[0] as f32 / 255.0;
+        blend_coverage(canvas, cx, cy, color, gv * visible);
+    });
+}
+
+/// How text is mirrored by [`draw_text_flipped_mut`](fn.draw_text_flipped_mut.html).
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum Flip {
+    /// The text is drawn normally.
+    #[default]
+    None,
+    /// The text is mirrored left to right.
+    Horizontal,
+    /// The text is mirrored top to bottom.
+    Vertical,
+    /// The text is mirrored both left to right and top to bottom, i.e. rotated by half a turn.
+    Both,
+}
+
+/// Draws colored text on an image in place, mirrored within its text box as specified by `flip`.
+///
+/// The text box is the one reported by [`text_size`](fn.text_size.html), with its top-left corner at
+/// `(x, y)`, so flipped text covers the same area as the unflipped text would. This box reaches down to
+/// the descent of the last line, so a vertically flipped line lies where the line would be drawn whether
+/// or not it has descenders. Coverage samples are mirrored to whole pixels, so anti-aliased edges are
+/// preserved exactly.
+pub fn draw_text_flipped_mut<'a, C>(
+    canvas: &'a mut C,
+    color: C::Pixel,
+    x: i32,
+    y: i32,
+    flip: Flip,
+    scale: Scale,
+    font: &'a Font<'a>,
+    text: &'a str,
+) where
+    C: Canvas,
+    <C::Pixel as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    let options = LayoutOptions::default();
+    let (w, h) = measure(scale, font, text, &options);
+    let (flip_x, flip_y) = match flip {
+        Flip::None => (false, false),
+        Flip::Horizontal => (true, false),
+        Flip::Vertical => (false, true),
+        Flip::Both => (true, true),
+    };
+
+    for_each_sample(scale, font, text, &options, |gx, gy, gv| {
+        let gx = if flip_x { w - 1 - gx } else { gx };
+        let gy = if flip_y { h - 1 - gy } else { gy };
+        blend_coverage(canvas, gx + x, gy + y, color, gv);
+    });
+}
+
+/// Positions the glyphs of `text` with scales interpolated linearly from `start` for the first glyph
+/// to `end` for the last, calling `f` with each glyph that has a bounding box. Returns the size of
+/// the resulting text box.
+///
+/// Every line shares the vertical metrics of the taller of the two scales, so all glyphs on a line
+/// sit on the same baseline however large they are.
+fn layout_scaled_ramp<'f>(
+    start: Scale,
+    end: Scale,
+    font: &'f Font<'f>,
+    text: &str,
+    mut f: impl FnMut(PositionedGlyph<'f>, Rect<i32>),
+) -> (i32, i32) {
+    let v_metrics = font.v_metrics(if end.y > start.y { end } else { start });
+    let count = text.lines().map(|line| line.chars().count()).sum::<usize>();
+    let step = if count > 1 {
+        1.0 / (count - 1) as f32
+    } else {
+        0.0
+    };
+
+    let (mut w, mut h) = (0, 0);
+    let mut index = 0;
+    for (i, line) in text.lines().enumerate() {
+        let baseline = v_metrics.ascent + i as f32 * line_advance(&v_metrics);
+        let mut caret = 0.0;
+        let mut last_glyph = None;
+        for c in line.chars() {
+            let t = index as f32 * step;
+            index += 1;
+            let scale = Scale {
+                x: start.x + (end.x - start.x) * t,
+                y: start.y + (end.y - start.y) * t,
+            };
+
+            let g = font.glyph(c).scaled(scale);
+            if let Some(last) = last_glyph {
+                caret += font.pair_kerning(scale, last, g.id());
+            }
+            last_glyph = Some(g.id());
+            let advance_width = g.h_metrics().advance_width;
+            let g = g.positioned(point(caret, baseline));
+            caret += advance_width;
+
+            if let Some(bb) = g.pixel_bounding_box() {
+                w = max(w, bb.max.x);
+                h = max(h, bb.max.y);
+                f(g, bb);
+            }
+        }
+    }
+
+    (w, h)
+}
+
+/// Whether `start` and `end` scale components have opposite signs along either axis, in which case the
+/// scale would pass through zero part way along a ramp between them.
+fn ramp_changes_sign(start: Scale, end: Scale) -> bool {
+    start.x * end.x < 0.0 || start.y * end.y < 0.0
+}
+
+/// A scale whose components are negative wherever those of `start` or `end` are, for mirroring a ramp.
+fn ramp_sign(start: Scale, end: Scale) -> Scale {
+    Scale {
+        x: start.x.min(end.x),
+        y: start.y.min(end.y),
+    }
+}
+
+/// Get the width and height of the given text when drawn by
+/// [`draw_text_scaled_ramp_mut`](fn.draw_text_scaled_ramp_mut.html) with the same `start` and `end` scales.
+pub fn scaled_ramp_text_size(start: Scale, end: Scale, font: &Font, text: &str) -> (i32, i32) {
+    if ramp_changes_sign(start, end) {
+        return (0, 0);
+    }
+    layout_scaled_ramp(magnitude(start), magnitude(end), font, text, |_, _| {})
+}
+
+/// Draws colored text on an image in place, with each glyph at a different scale.
+///
+/// The scale is interpolated linearly from `start` for the first character to `end` for the last,
+/// counting characters across all lines. Each glyph advances the pen by its own scaled width, so
+/// glyphs of different sizes do not overlap. All glyphs on a line share a baseline, which lies at
+/// the ascent of the larger scale below the top of the line, so glyphs grow upwards from it.
+/// Lines are spaced as for [`draw_text_mut`](fn.draw_text_mut.html) at the larger scale.
+///
+/// The size of the text box is given by [`scaled_ramp_text_size`](fn.scaled_ramp_text_size.html). Along
+/// each axis for which `start` and `end` are negative the text is mirrored within this box, as by
+/// `draw_text_mut`. Nothing is drawn if `start` and `end` have opposite signs along either axis, since
+/// the glyphs in between would shrink to nothing, and the size of the text box is then `(0, 0)`.
+pub fn draw_text_scaled_ramp_mut<'a, C>(
+    canvas: &'a mut C,
+    color: C::Pixel,
+    x: i32,
+    y: i32,
+    start: Scale,
+    end: Scale,
+    font: &'a Font<'a>,
+    text: &'a str,
+) where
+    C: Canvas,
+    <C::Pixel as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    if ramp_changes_sign(start, end) {
+        return;
+    }
+    let size = || scaled_ramp_text_size(start, end, font, text);
+    draw_mirrored_mut(canvas, x, y, ramp_sign(start, end), size, |canvas, x, y| {
+        layout_scaled_ramp(magnitude(start), magnitude(end), font, text, |g, bb| {
+            g.draw(|gx, gy, gv| {
+                let (gx, gy) = (gx as i32 + bb.min.x, gy as i32 + bb.min.y);
+                blend_coverage(canvas, gx + x, gy + y, color, gv);
+            });
+        });
+    });
+}
+
+/// The vertical advance of the glyph for `c` in pixels, from the font's `vmtx` table if it has one.
+/// Fonts without vertical metrics advance every glyph by `ascent - descent`.
+fn vertical_advance(scale: Scale, font: &Font, c: char) -> f32 {
+    let face = font_face(font);
+    let id = owned_ttf_parser::GlyphId(font.glyph(c).id().0);
+    match face.glyph_ver_advance(id) {
+        Some(advance) => advance as f32 * font.scale_for_pixel_height(scale.y),
+        None => {
+            let v_metrics = font.v_metrics(scale);
+            v_metrics.ascent - v_metrics.descent
+        }
+    }
+}
+
+/// Positions the glyphs of `text` in vertical columns, calling `f` with each glyph that has a bounding box.
+/// Returns the size of the text box.
+fn layout_vertical<'f>(
+    scale: Scale,
+    font: &'f Font<'f>,
+    text: &str,
+    mut f: impl FnMut(PositionedGlyph<'f>, Rect<i32>),
+) -> (i32, i32) {
+    let v_metrics = font.v_metrics(scale);
+    let column_width = scale.x.ceil();
+    let columns = text.lines().count();
+    let mut height = 0.0f32;
+
+    for (i, column) in text.lines().enumerate() {
+        let left = (columns - 1 - i) as f32 * column_width;
+        let mut pen = 0.0;
+        for c in column.chars() {
+            let g = font.glyph(c).scaled(scale);
+            let offset = (column_width - g.h_metrics().advance_width) / 2.0;
+            let g = g.positioned(point(left + offset, pen + v_metrics.ascent));
+            if let Some(bb) = g.pixel_bounding_box() {
+                f(g, bb);
+            }
+            pen += vertical_advance(scale, font, c);
+        }
+        height = height.max(pen);
+    }
+
+    ((columns as f32 * column_width) as i32, height.ceil() as i32)
+}
+
+/// Get the width and height of the given text when drawn by
+/// [`draw_vertical_text_mut`](fn.draw_vertical_text_mut.html).
+///
+/// The width is the number of columns times the column width `scale.x`, and the height is the largest sum
+/// of the vertical advances of the characters in any column, rounded up to a whole number of pixels.
+pub fn vertical_text_size(scale: Scale, font: &Font, text: &str) -> (i32, i32) {
+    layout_vertical(magnitude(scale), font, text, |_, _| {})
+}
+
+/// Draws colored text on an image in place, with characters stacked from top to bottom, as in vertical
+/// Chinese or Japanese writing.
+///
+/// Each line of `text` forms a column `scale.x` pixels wide, with the first column rightmost and each
+/// following column to the left of the previous one. `(x, y)` is the top-left corner of the text box, whose
+/// size is given by [`vertical_text_size`](fn.vertical_text_size.html). Each glyph is centered horizontally in
+/// its column, and the pen moves down by the glyph's vertical advance from the font's `vmtx` table. For fonts
+/// without vertical metrics every glyph advances by `ascent - descent`.
+///
+/// Glyphs are drawn upright. Punctuation which should be rotated or repositioned in vertical text is not
+/// treated specially. For each negative component of `scale` the text is mirrored within its text box, as
+/// by [`draw_text_mut`](fn.draw_text_mut.html).
+pub fn draw_vertical_text_mut<'a, C>(
+    canvas: &'a mut C,
+    color: C::Pixel,
+    x: i32,
+    y: i32,
+    scale: Scale,
+    font: &'a Font<'a>,
+    text: &'a str,
+) where
+    C: Canvas,
+    <C::Pixel as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    let size = || vertical_text_size(scale, font, text);
+    draw_mirrored_mut(canvas, x, y, scale, size, |canvas, x, y| {
+        layout_vertical(magnitude(scale), font, text, |g, bb| {
+            g.draw(|gx, gy, gv| {
+                let (gx, gy) = (gx as i32 + bb.min.x, gy as i32 + bb.min.y);
+                blend_coverage(canvas, gx + x, gy + y, color, gv);
+            });
+        });
+    });
+}
+
+/// Draws colored text on an image in place, placing characters in the given `direction`.
+///
+/// Right-to-left text is drawn with the first character of each line rightmost, and each line is aligned
+/// to the right edge of the text box. As for [`draw_text_mut`](fn.draw_text_mut.html), `(x, y)` is the
+/// top-left corner of the text box, whose size is given by [`text_size`](fn.text_size.html).
+pub fn draw_text_directed_mut<'a, C>(
+    canvas: &'a mut C,
+    color: C::Pixel,
+    x: i32,
+    y: i32,
+    direction: TextDirection,
+    scale: Scale,
+    font: &'a Font<'a>,
+    text: &'a str,
+) where
+    C: Canvas,
+    <C::Pixel as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    let align = match direction {
+        TextDirection::Ltr => HorizontalAlign::Left,
+        TextDirection::Rtl => HorizontalAlign::Right,
+    };
+    let options = LayoutOptions {
+        align,
+        direction,
+        ..LayoutOptions::default()
+    };
+    draw_text_with_options_mut(canvas, color, x, y, scale, font, text, &options);
+}
+
+/// Get the width and height of the given text when drawn by
+/// [`draw_text_spaced_mut`](fn.draw_text_spaced_mut.html) with the same `tracking`.
+pub fn spaced_text_size(scale: Scale, font: &Font, text: &str, tracking: f32) -> (i32, i32) {
+    let options = LayoutOptions {
+        tracking,
+        ..LayoutOptions::default()
+    };
+    measure(scale, font, text, &options)
+}
+
+/// Draws colored text on an image in place, with `tracking` pixels of extra space between consecutive glyphs.
+///
+/// Tracking is applied between glyphs but not after the last glyph of each line. Negative values
+/// produce condensed text. Use [`spaced_text_size`](fn.spaced_text_size.html) to measure the result.
+pub fn draw_text_spaced_mut<'a, C>(
+    canvas: &'a mut C,
+    color: C::Pixel,
+    x: i32,
+    y: i32,
+    tracking: f32,
+    scale: Scale,
+    font: &'a Font<'a>,
+    text: &'a str,
+) where
+    C: Canvas,
+    <C::Pixel as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    TextStyle::new(font, scale, color)
+        .tracking(tracking)
+        .draw_mut(canvas, x, y, text);
+}
+
+/// Whether `c` is an ideographic or syllabic character from a CJK script, after which a line may be
+/// broken without any intervening space.
+fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{3000}'..='\u{303F}' // CJK symbols and punctuation
+        | '\u{3040}'..='\u{30FF}' // Hiragana and Katakana
+        | '\u{3400}'..='\u{4DBF}' // CJK unified ideographs extension A
+        | '\u{4E00}'..='\u{9FFF}' // CJK unified ideographs
+        | '\u{AC00}'..='\u{D7AF}' // Hangul syllables
+        | '\u{F900}'..='\u{FAFF}' // CJK compatibility ideographs
+        | '\u{FF00}'..='\u{FFEF}' // Halfwidth and fullwidth forms
+        | '\u{20000}'..='\u{2FA1F}' // Supplementary ideographic plane
+    )
+}
+
+/// Get the byte indices in `text` at which a line may be broken, in increasing order.
+///
+/// A break at index `i` splits `text` into `&text[..i]` and `&text[i..]`. Breaks are permitted after
+/// a run of whitespace (so trailing spaces stay on the earlier line), after a hyphen which follows a
+/// non-whitespace character, and after a CJK character. No break is reported before whitespace, or
+/// at the start or end of `text`.
+///
+/// This is a simplified form of the Unicode line breaking algorithm, intended to be combined with
+/// [`text_size`](fn.text_size.html) for custom paragraph layout.
+pub fn break_opportunities(text: &str) -> Vec<usize> {
+    let mut breaks = vec![];
+    let mut chars = text.char_indices().peekable();
+    let mut previous: Option<char> = None;
+
+    while let Some((i, c)) = chars.next() {
+        let next = match chars.peek() {
+            Some(&(_, next)) => next,
+            None => break,
+        };
+        let allowed = match c {
+            c if c.is_whitespace() => true,
+            '-' => matches!(previous, Some(p) if !p.is_whitespace()),
+            c => is_cjk(c),
+        };
+        if allowed && !next.is_whitespace() {
+            breaks.push(i + c.len_utf8());
+        }
+        previous = Some(c);
+    }
+
+    breaks
+}
+
+/// Splits `text` into lines which are at most `max_width` pixels wide when drawn.
+///
+/// Lines are broken greedily at whitespace, with widths measured by [`text_size`](fn.text_size.html).
+/// Each `\n` in `text` starts a new line, and runs of whitespace within a line are collapsed to a
+/// single space.
+///
+/// A word which does not fit on the current line may also be broken after a hyphen, or at a soft hyphen
+/// (U+00AD), with the break as late in the word as fits. Soft hyphens are invisible, and are rendered as
+/// `'-'` only where such a break is taken. A word without any break that fits is placed on a line by
+/// itself rather than being split.
+pub fn wrap_text(scale: Scale, font: &Font, text: &str, max_width: u32) -> Vec<String> {
+    wrap_text_indented(scale, font, text, max_width, 0)
+}
+
+/// Splits `text` into lines as [`wrap_text`](fn.wrap_text.html) does, except that the first line of each
+/// paragraph, i.e. of each line of `text`, is indented by `first_line_indent` pixels and so is at most
+/// `max_width - first_line_indent` pixels wide.
+///
+/// A negative indent gives a hanging indent, where the first line of each paragraph may be wider than
+/// `max_width`.
+pub fn wrap_text_indented(
+    scale: Scale,
+    font: &Font,
+    text: &str,
+    max_width: u32,
+    first_line_indent: i32,
+) -> Vec<String> {
+    text.lines()
+        .flat_map(|paragraph| {
+            wrap_paragraph(scale, font, paragraph, max_width as i32, first_line_indent)
+        })
+        .collect()
+}
+
+/// Wraps a single paragraph greedily, as described for `wrap_text_indented`. Always returns at least one line.
+fn wrap_paragraph(
+    scale: Scale,
+    font: &Font,
+    paragraph: &str,
+    max_width: i32,
+    first_line_indent: i32,
+) -> Vec<String> {
+    wrap_paragraph_with(scale, font, paragraph, |line| match line {
+        0 => (max_width - first_line_indent, false),
+        _ => (max_width, false),
+    })
+}
+
+/// Wraps a single paragraph greedily, where `slot(i)` gives the width available for the `i`th line of the
+/// paragraph and whether that line may be left empty, moving its text to later lines, when not even a
+/// single word fits in it. Always returns at least one line.
+///
+/// If lines may be left empty then a later line must eventually be wide enough for every word, or not
+/// allow this.
+fn wrap_paragraph_with(
+    scale: Scale,
+    font: &Font,
+    paragraph: &str,
+    slot: impl Fn(usize) -> (i32, bool),
+) -> Vec<String> {
+    let mut lines: Vec<String> = vec![];
+    let mut current = String::new();
+    for word in paragraph.split_whitespace() {
+        let mut rest = word;
+        loop {
+            let (available, skippable) = slot(lines.len());
+            let fits = |line: &str| text_size(scale, font, line).0 <= available;
+            let join = |piece: &str| match current.is_empty() {
+                true => piece.to_string(),
+                false => format!("{} {}", current, piece),
+            };
+
+            let candidate = join(&strip_soft_hyphens(rest));
+            if fits(&candidate) {
+                current = candidate;
+                break;
+            }
+            // Take as much of the word as fits on this line, breaking at the last hyphen possible
+            let split = hyphen_breaks(rest)
+                .rev()
+                .map(|(head, tail)| (join(&head), tail))
+                .find(|(line, _)| fits(line));
+            if let Some((line, tail)) = split {
+                lines.push(line);
+                current.clear();
+                rest = tail;
+            } else if current.is_empty() && skippable {
+                lines.push(String::new());
+            } else if current.is_empty() {
+                // Nothing fits, so the rest of the word goes on a line of its own
+                current = candidate;
+                break;
+            } else {
+                lines.push(std::mem::take(&mut current));
+            }
+        }
+    }
+    lines.push(current);
+    lines
+}
+
+const SOFT_HYPHEN: char = '\u{AD}';
+
+fn strip_soft_hyphens(word: &str) -> String {
+    word.chars().filter(|&c| c != SOFT_HYPHEN).collect()
+}
+
+/// The ways of breaking `word` at a hyphen or soft hyphen, in increasing order of position, as the text
+/// ending the first line and the remainder of `word`. Breaks at a soft hyphen render it as a visible hyphen.
+fn hyphen_breaks(word: &str) -> impl DoubleEndedIterator<Item = (String, &str)> {
+    word.char_indices()
+        .filter(move |&(i, c)| {
+            (c == '-' || c == SOFT_HYPHEN) && i > 0 && i + c.len_utf8() < word.len()
+        })
+        .map(move |(i, c)| {
+            let end = i + c.len_utf8();
+            let mut head = strip_soft_hyphens(&word[..i]);
+            head.push('-');
+            (head, &word[end..])
+        })
+        .collect::<Vec<_>>()
+        .into_iter()
+}
+
+/// Splits `text` into lines as [`wrap_text`](fn.wrap_text.html) does, but flowing around `exclusions`, e.g.
+/// to wrap text around an inset image. Returns each line along with its horizontal offset from the left
+/// edge of the text box.
+///
+/// `exclusions` are given relative to the top-left corner of the text box, and lines are spaced by the font's
+/// line advance as for [`draw_text_mut`](fn.draw_text_mut.html). The part of each line between 0 and `max_width`
+/// which is not covered by any exclusion overlapping the rows of that line is split into free intervals, and the
+/// line is placed at the start of the widest of these, leftmost first. When not even a single word fits in a line
+/// narrowed by an exclusion, that line is left empty and the text continues below it. Lines are measured at the
+/// magnitude of `scale`.
+pub fn wrap_text_around(
+    scale: Scale,
+    font: &Font,
+    text: &str,
+    max_width: u32,
+    exclusions: &[IpRect],
+) -> Vec<(i32, String)> {
+    let scale = magnitude(scale);
+    let advance = line_advance(&font.v_metrics(scale));
+    let max_width = max_width as i32;
+    let slot = |line: usize| {
+        let (top, bottom) = (line as f32 * advance, (line + 1) as f32 * advance);
+        let mut blocked: Vec<(i32, i32)> = exclusions
+            .iter()
+            .filter(|r| (r.top() as f32) < bottom && ((r.bottom() + 1) as f32) > top)
+            .map(|r| (r.left(), r.right() + 1))
+            .collect();
+        blocked.sort_unstable();
+
+        let (mut best, mut start) = ((0, 0), 0);
+        for (left, right) in blocked.iter().copied().chain(Some((max_width, max_width))) {
+            let end = left.min(max_width);
+            if end - start > best.1 {
+                best = (start, end - start);
+            }
+            start = start.max(right);
+        }
+        (best, best.1 < max_width)
+    };
+
+    let mut lines = vec![];
+    for paragraph in text.lines() {
+        let first = lines.len();
+        let wrapped = wrap_paragraph_with(scale, font, paragraph, |i| {
+            let ((_, width), narrowed) = slot(first + i);
+            (width, narrowed)
+        });
+        lines.extend(
+            wrapped
+                .into_iter()
+                .enumerate()
+                .map(|(i, line)| ((slot(first + i).0).0, line)),
+        );
+    }
+    lines
+}
+
+/// Draws colored text on an image in place, wrapped into lines of at most `max_width` pixels which flow
+/// around `exclusions`, as given by [`wrap_text_around`](fn.wrap_text_around.html).
+///
+/// `exclusions` are relative to `(x, y)`, the top-left corner of the text box. Each line is drawn as by
+/// [`draw_text_mut`](fn.draw_text_mut.html), with its top one line advance below the previous line rounded
+/// to a whole pixel.
+///
+/// Nothing is drawn if either component of `scale` is negative. Mirrored text would cover the mirror image
+/// of the area kept clear of `exclusions`, and vertically mirrored text can only be placed once its height,
+/// which depends on the exclusions, is known.
+pub fn draw_text_around_mut<'a, C>(
+    canvas: &'a mut C,
+    color: C::Pixel,
+    x: i32,
+    y: i32,
+    max_width: u32,
+    exclusions: &[IpRect],
+    scale: Scale,
+    font: &'a Font<'a>,
+    text: &'a str,
+) where
+    C: Canvas,
+    <C::Pixel as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    if scale.x < 0.0 || scale.y < 0.0 {
+        return;
+    }
+    let advance = line_advance(&font.v_metrics(scale));
+    for (i, (offset, line)) in wrap_text_around(scale, font, text, max_width, exclusions)
+        .iter()
+        .enumerate()
+    {
+        let line_y = y + (i as f32 * advance).round() as i32;
+        draw_text_mut(canvas, color, x + offset, line_y, scale, font, line);
+    }
+}
+
+/// How a paragraph is laid out by [`draw_paragraphs_mut`](fn.draw_paragraphs_mut.html).
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct ParagraphStyle {
+    /// How many pixels the first line of the paragraph is indented by, which may be negative for a
+    /// hanging indent. The default is zero.
+    pub first_line_indent: i32,
+    /// How each line of the paragraph is aligned within the width the text is wrapped to. The default is left aligned.
+    pub align: HorizontalAlign,
+}
+
+/// Draws colored text on an image in place, wrapped into lines of at most `max_width` pixels and
+/// formatted as paragraphs, e.g. for body text.
+///
+/// Each line of `text` is a paragraph, which is wrapped as by [`wrap_text_indented`](fn.wrap_text_indented.html)
+/// and laid out as given by `styles`. The `i`th paragraph uses `styles[i]`, and if `styles` is shorter than
+/// the number of paragraphs then the remaining paragraphs all use its last element, so passing a single style
+/// formats every paragraph the same way. If `styles` is empty the default style is used.
+///
+/// Lines are aligned within the box from `x` to `x + max_width`, with the first line of each paragraph
+/// aligned within the part of this box to the right of its indent. Line widths are measured as in
+/// [`text_size`](fn.text_size.html) and spaced as for [`draw_text_mut`](fn.draw_text_mut.html).
+///
+/// For each negative component of `scale` the whole text is mirrored within its text box, as by
+/// `draw_text_mut`. This box is `max_width` wide, and as tall as `text_size` gives for the wrapped lines.
+pub fn draw_paragraphs_mut<'a, C>(
+    canvas: &'a mut C,
+    color: C::Pixel,
+    x: i32,
+    y: i32,
+    max_width: u32,
+    styles: &[ParagraphStyle],
+    scale: Scale,
+    font: &'a Font<'a>,
+    text: &'a str,
+) where
+    C: Canvas,
+    <C::Pixel as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    let (flipped, scale) = (scale, magnitude(scale));
+    let line_advance = line_advance(&font.v_metrics(scale));
+    let mut lines = vec![];
+    for (i, paragraph) in text.lines().enumerate() {
+        let style = match styles {
+            [] => ParagraphStyle::default(),
+            _ => styles[i.min(styles.len() - 1)],
+        };
+        let wrapped = wrap_paragraph(
+            scale,
+            font,
+            paragraph,
+            max_width as i32,
+            style.first_line_indent,
+        );
+        for (j, wrapped_line) in wrapped.into_iter().enumerate() {
+            let indent = if j == 0 { style.first_line_indent } else { 0 };
+            let space = max_width as i32 - indent - text_size(scale, font, &wrapped_line).0;
+            let offset = match style.align {
+                HorizontalAlign::Left => 0,
+                HorizontalAlign::Center => space / 2,
+                HorizontalAlign::Right => space,
+            };
+            lines.push((indent + offset, wrapped_line));
+        }
+    }
+
+    let size = || {
+        let all: Vec<&str> = lines.iter().map(|(_, line)| line.as_str()).collect();
+        (max_width as i32, text_size(scale, font, &all.join("\n")).1)
+    };
+    draw_mirrored_mut(canvas, x, y, flipped, size, |canvas, x, y| {
+        for (i, (offset, line)) in lines.iter().enumerate() {
+            let line_y = y + (i as f32 * line_advance).round() as i32;
+            draw_text_mut(canvas, color, x + offset, line_y, scale, font, line);
+        }
+    });
+}
+
+/// Draws colored text on an image in place, wrapped into lines of at most `max_width` pixels.
+///
+/// See [`wrap_text`](fn.wrap_text.html) for how lines are broken, and
+/// [`draw_text_mut`](fn.draw_text_mut.html) for how the resulting lines are drawn.
+pub fn draw_wrapped_text_mut<'a, C>(
+    canvas: &'a mut C,
+    color: C::Pixel,
+    x: i32,
+    y: i32,
+    max_width: u32,
+    scale: Scale,
+    font: &'a Font<'a>,
+    text: &'a str,
+) where
+    C: Canvas,
+    <C::Pixel as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    let wrapped = wrap_text(scale, font, text, max_width).join("\n");
+    draw_text_mut(canvas, color, x, y, scale, font, &wrapped);
+}
+
+/// Draws colored text on an image in place, wrapped to the width of `rectangle` and placed inside
+/// it at `position`.
+///
+/// The text is wrapped as by [`wrap_text`](fn.wrap_text.html), and the resulting block is positioned
+/// as a [`GlyphString`](struct.GlyphString.html) by
+/// [`GlyphString::draw_positioned_mut`](struct.GlyphString.html#method.draw_positioned_mut), so its
+/// height is the number of wrapped lines times the line height.
+pub fn draw_wrapped_positioned_mut<'a, C>(
+    canvas: &'a mut C,
+    color: C::Pixel,
+    position: &Position,
+    rectangle: &IpRect,
+    scale: Scale,
+    font: &'a Font<'a>,
+    text: &'a str,
+) where
+    C: Canvas,
+    <C::Pixel as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    let wrapped = wrap_text(scale, font, text, rectangle.width()).join("\n");
+    GlyphString::new(scale, font, &wrapped).draw_positioned_mut(canvas, color, position, rectangle);
+}
+
+/// Draws colored text on an image in place, placed inside `rectangle` at `position` as by
+/// [`GlyphString::draw_positioned_mut`](struct.GlyphString.html#method.draw_positioned_mut), but first
+/// shrinking it until it fits, e.g. for labels which must not overflow their box.
+///
+/// The text is drawn at the largest uniform scale up to `max_scale` at which the width and height of its
+/// [`GlyphString`](struct.GlyphString.html) fit inside `rectangle`, found as by [`fit_scale`](fn.fit_scale.html),
+/// and this scale is returned. If the text does not fit at any scale then nothing is drawn and a scale of
+/// zero is returned.
+pub fn draw_fitted_positioned_mut<'a, C>(
+    canvas: &'a mut C,
+    color: C::Pixel,
+    position: &Position,
+    rectangle: &IpRect,
+    max_scale: f32,
+    font: &'a Font<'a>,
+    text: &'a str,
+) -> Scale
+where
+    C: Canvas,
+    <C::Pixel as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    let scale = largest_fitting_scale(max_scale, |scale| {
+        let glyphs = GlyphString::new(scale, font, text);
+        glyphs.width() <= rectangle.width() && glyphs.height() <= rectangle.height()
+    });
+    if scale.y > 0.0 {
+        GlyphString::new(scale, font, text).draw_positioned_mut(canvas, color, position, rectangle);
+    }
+    scale
+}
+
+/// What [`draw_text_positioned_mut`](fn.draw_text_positioned_mut.html) and
+/// [`GlyphString::with_overflow`](struct.GlyphString.html#method.with_overflow) do with text which is
+/// too big for its rectangle.
+///
+/// Text is placed inside the rectangle at a [`Position`](enum.Position.html) after applying the
+/// policy. Whenever the resulting text is still wider or taller than the rectangle, it is pinned to the
+/// left or top edge respectively for every `Position` variant, ignoring the `EdgePosition` along that
+/// axis, and pixels outside the rectangle are not drawn.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum Overflow {
+    /// Draw the text at its original scale and cut it off at the edges of the rectangle. Text which
+    /// fits is positioned exactly as by [`GlyphString::draw_positioned_mut`].
+    ///
+    /// [`GlyphString::draw_positioned_mut`]: struct.GlyphString.html#method.draw_positioned_mut
+    #[default]
+    Clip,
+    /// Shrink the text to the largest uniform scale, up to its original vertical scale, at which it fits
+    /// inside the rectangle, as [`draw_fitted_positioned_mut`](fn.draw_fitted_positioned_mut.html) does.
+    /// Text which already fits is drawn at its original scale, and the fitted text is positioned as
+    /// given along both axes.
+    Shrink,
+    /// Wrap the text to the width of the rectangle as [`wrap_text`](fn.wrap_text.html) does. The wrapped
+    /// block is positioned horizontally as given, unless it contains a single word wider than the
+    /// rectangle, and is pinned to the top if there are too many lines to fit.
+    Wrap,
+    /// Shorten each line of the text to the width of the rectangle, as
+    /// [`truncate_with_ellipsis`](fn.truncate_with_ellipsis.html) does. The shortened text is positioned
+    /// horizontally as given, and is pinned to the top if it has too many lines to fit.
+    Ellipsis,
+}
+
+/// Draws colored text on an image in place, placed inside `rectangle` at `position` and handling text
+/// which is too big for `rectangle` as given by `overflow`.
+///
+/// The text is drawn as a [`GlyphString`](struct.GlyphString.html) with the given overflow, see
+/// [`GlyphString::with_overflow`](struct.GlyphString.html#method.with_overflow), so it is placed and drawn
+/// using the same line heights. No pixels outside `rectangle` are drawn.
+pub fn draw_text_positioned_mut<'a, C>(
+    canvas: &'a mut C,
+    color: C::Pixel,
+    position: &Position,
+    rectangle: &IpRect,
+    overflow: Overflow,
+    scale: Scale,
+    font: &'a Font<'a>,
+    text: &'a str,
+) where
+    C: Canvas,
+    <C::Pixel as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    GlyphString::new(scale, font, text)
+        .with_overflow(overflow)
+        .draw_positioned_mut(canvas, color, position, rectangle);
+}
+
+/// The distance the pen moves when laying out a single line of text, including the advance of the last glyph.
+fn advance_width(scale: Scale, font: &Font, line: &str, options: &LayoutOptions) -> f32 {
+    let mut end = 0.0;
+    layout_line(scale, font, line, point(0.0, 0.0), options, |_, g| {
+        end = g.position().x + g.unpositioned().h_metrics().advance_width;
+    });
+    end
+}
+
+/// Draws colored lines of text on an image in place, stretching the spaces between words so that
+/// each line is exactly `target_width` pixels wide.
+///
+/// The last line of a paragraph is left-aligned instead. A line is the last of its paragraph if it
+/// is the last of `lines` or is followed by an empty line. Lines containing a single word, and lines
+/// which are already wider than `target_width`, are also left-aligned. Words are separated by any
+/// amount of whitespace, and lines are spaced as for [`draw_text_mut`](fn.draw_text_mut.html).
+/// The output of [`wrap_text`](fn.wrap_text.html) is suitable for `lines`.
+///
+/// For each negative component of `scale` the whole text is mirrored within its text box, as by
+/// `draw_text_mut`. This box is `target_width` wide, and as tall as `text_size` gives for `lines`.
+pub fn draw_justified_text_mut<'a, C, S>(
+    canvas: &'a mut C,
+    color: C::Pixel,
+    x: i32,
+    y: i32,
+    target_width: u32,
+    scale: Scale,
+    font: &'a Font<'a>,
+    lines: &[S],
+) where
+    C: Canvas,
+    <C::Pixel as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
+    S: AsRef<str>,
+{
+    let (flipped, scale) = (scale, magnitude(scale));
+    let size = || {
+        let all: Vec<&str> = lines.iter().map(|line| line.as_ref()).collect();
+        (
+            target_width as i32,
+            text_size(scale, font, &all.join("\n")).1,
+        )
+    };
+    draw_mirrored_mut(canvas, x, y, flipped, size, |canvas, x, y| {
+        draw_justified_lines_mut(canvas, color, x, y, target_width, scale, font, lines)
+    });
+}
+
+/// Draws justified lines as `draw_justified_text_mut` does, at a non-negative `scale`.
+fn draw_justified_lines_mut<C, S>(
+    canvas: &mut C,
+    color: C::Pixel,
+    x: i32,
+    y: i32,
+    target_width: u32,
+    scale: Scale,
+    font: &Font,
+    lines: &[S],
+) where
+    C: Canvas,
+    <C::Pixel as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
+    S: AsRef<str>,
+{
+    let options = LayoutOptions::default();
+    let v_metrics = font.v_metrics(scale);
+    let space = advance_width(scale, font, " ", &options);
+
+    for (i, line) in lines.iter().enumerate() {
+        let words: Vec<&str> = line.as_ref().split_whitespace().collect();
+        let last_in_paragraph = match lines.get(i + 1) {
+            Some(next) => next.as_ref().trim().is_empty(),
+            None => true,
+        };
+
+        // Stretch the gaps by however much the line is narrower than the target when words are
+        // separated by single spaces
+        let gap = if last_in_paragraph || words.len() < 2 {
+            space
+        } else {
+            let natural = line_width(scale, font, &words.join(" "), &options);
+            let extra = (target_width as i32 - natural).max(0) as f32;
+            space + extra / (words.len() - 1) as f32
+        };
+
+        let baseline = v_metrics.ascent + i as f32 * line_advance(&v_metrics);
+        let mut pen = 0.0;
+        for word in words {
+            layout_line(scale, font, word, point(pen, baseline), &options, |_, g| {
+                if let Some(bb) = g.pixel_bounding_box() {
+                    g.draw(|gx, gy, gv| {
+                        let (gx, gy) = (gx as i32 + bb.min.x, gy as i32 + bb.min.y);
+                        blend_coverage(canvas, gx + x, gy + y, color, gv);
+                    });
+                }
+            });
+            pen += advance_width(scale, font, word, &options) + gap;
+        }
+    }
+}
+
+/// Shortens `text` so that it is at most `max_width` pixels wide, replacing the removed suffix with an ellipsis ("…").
+///
+/// Text which already fits is returned unchanged. Otherwise the longest prefix of `text` (ignoring trailing
+/// whitespace) for which the prefix followed by "…" fits is used. If even "…" on its own does not fit then
+/// an empty string is returned. Widths are measured by [`text_size`](fn.text_size.html).
+pub fn truncate_with_ellipsis(scale: Scale, font: &Font, text: &str, max_width: u32) -> String {
+    let fits = |candidate: &str| text_size(scale, font, candidate).0 <= max_width as i32;
+    if fits(text) {
+        return text.to_string();
+    }
+
+    let prefix_ends = text.char_indices().map(|(i, _)| i).rev();
+    for end in prefix_ends {
+        let candidate = format!("{}…", text[..end].trim_end());
+        if fits(&candidate) {
+            return candidate;
+        }
+    }
+
+    String::new()
+}
+
+/// Draws colored text on an image in place, truncated with an ellipsis if it is wider than `max_width` pixels.
+///
+/// See [`truncate_with_ellipsis`](fn.truncate_with_ellipsis.html) for how text is shortened. Returns the text
+/// which was drawn.
+pub fn draw_text_truncated_mut<'a, C>(
+    canvas: &'a mut C,
+    color: C::Pixel,
+    x: i32,
+    y: i32,
+    max_width: u32,
+    scale: Scale,
+    font: &'a Font<'a>,
+    text: &'a str,
+) -> String
+where
+    C: Canvas,
+    <C::Pixel as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    let truncated = truncate_with_ellipsis(scale, font, text, max_width);
+    draw_text_mut(canvas, color, x, y, scale, font, &truncated);
+    truncated
+}
+
+/// Returns the uniform scale for text of `pt` points at a resolution of `dpi` dots per inch, i.e.
+/// `pt * dpi / 72` pixels on both axes, so that e.g. 12pt at 96 DPI gives `Scale::uniform(16.0)`.
+///
+/// As for any `Scale`, the size is the distance in pixels between the ascent and descent lines of the font.
+pub fn scale_from_pt(pt: f32, dpi: f32) -> Scale {
+    Scale::uniform(pt * dpi / 72.0)
+}
+
+/// Finds the largest uniform scale, up to `max_scale`, at which `text` fits inside `rect`
+/// with `padding` pixels of space on every side.
+///
+/// The size of the text is measured by [`text_size`](fn.text_size.html), and the scale is found by a
+/// binary search to within a tenth of a pixel. Empty text fits at any scale, so `max_scale` is returned.
+/// If the text does not fit at any scale, e.g. because the padding leaves no space, a scale of zero is returned.
+pub fn fit_scale(font: &Font, text: &str, rect: IpRect, padding: u32, max_scale: f32) -> Scale {
+    let available_width = rect.width() as i32 - 2 * padding as i32;
+    let available_height = rect.height() as i32 - 2 * padding as i32;
+    largest_fitting_scale(max_scale, |scale| {
+        let (w, h) = text_size(scale, font, text);
+        w <= available_width && h <= available_height
+    })
+}
+
+/// Finds the largest uniform scale up to `max_scale` for which `fits` holds, to within a tenth of a pixel,
+/// as described for [`fit_scale`](fn.fit_scale.html).
+fn largest_fitting_scale(max_scale: f32, fits: impl Fn(Scale) -> bool) -> Scale {
+    let fits = |scale: f32| fits(Scale::uniform(scale));
+
+    if fits(max_scale) {
+        return Scale::uniform(max_scale);
+    }
+
+    // Invariant: the text fits at `lo` (or `lo` is zero) and does not fit at `hi`
+    let (mut lo, mut hi) = (0.0f32, max_scale);
+    while hi - lo > 0.1 {
+        let mid = (lo + hi) / 2.0;
+        if fits(mid) {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+
+    if lo > 0.0 && fits(lo) {
+        Scale::uniform(lo)
+    } else {
+        Scale::uniform(0.0)
+    }
+}
+
+/// Draws colored text on an image in place, at the largest scale which fits inside `rect`.
+///
+/// The scale is found by [`fit_scale`](fn.fit_scale.html) and returned. The text is drawn with the top-left
+/// corner of its text box `padding` pixels below and to the right of the top-left corner of `rect`.
+pub fn draw_text_fitted_mut<'a, C>(
+    canvas: &'a mut C,
+    color: C::Pixel,
+    rect: IpRect,
+    padding: u32,
+    max_scale: f32,
+    font: &'a Font<'a>,
+    text: &'a str,
+) -> Scale
+where
+    C: Canvas,
+    <C::Pixel as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    let scale = fit_scale(font, text, rect, padding, max_scale);
+    let (x, y) = (rect.left() + padding as i32, rect.top() + padding as i32);
+    draw_text_mut(canvas, color, x, y, scale, font, text);
+    scale
+}
+
+/// Draws colored multi-line text on an image in place, aligning each line horizontally within
+/// the box formed by the widest line. The top-left corner of this box is at `(x, y)`.
+///
+/// Line widths are measured as in [`text_size`](fn.text_size.html), so trailing whitespace does not
+/// affect the alignment. Empty lines draw nothing but still take up vertical space.
+/// Baselines are `line_spacing` apart; use [`multiline_text_size`](fn.multiline_text_size.html)
+/// to measure the resulting text box.
+pub fn draw_text_aligned_mut<'a, C>(
+    canvas: &'a mut C,
+    color: C::Pixel,
+    x: i32,
+    y: i32,
+    align: HorizontalAlign,
+    line_spacing: LineSpacing,
+    scale: Scale,
+    font: &'a Font<'a>,
+    text: &'a str,
+) where
+    C: Canvas,
+    <C::Pixel as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    TextStyle::new(font, scale, color)
+        .align(align)
+        .line_spacing(line_spacing)
+        .draw_mut(canvas, x, y, text);
+}
+
+fn draw_text_with_options_mut<C>(
+    canvas: &mut C,
+    color: C::Pixel,
+    x: i32,
+    y: i32,
+    scale: Scale,
+    font: &Font,
+    text: &str,
+    options: &LayoutOptions,
+) -> Option<IpRect>
+where
+    C: Canvas,
+    <C::Pixel as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    draw_samples_mut(
+        canvas,
+        x,
+        y,
+        scale,
+        font,
+        text,
+        options,
+        |canvas, x, y, gv| blend_coverage(canvas, x, y, color, gv),
+    )
+}
+
+/// Calls `plot(canvas, x, y, coverage)` for every coverage sample of the laid out text, with `(x, y)`
+/// in canvas coordinates for a text box whose top-left corner is at `(x, y)`. `plot` should return
+/// whether it drew to the canvas.
+///
+/// Returns the smallest rectangle containing every pixel which was drawn to.
+fn draw_samples_mut<C>(
+    canvas: &mut C,
+    x: i32,
+    y: i32,
+    scale: Scale,
+    font: &Font,
+    text: &str,
+    options: &LayoutOptions,
+    mut plot: impl FnMut(&mut C, i32, i32, f32) -> bool,
+) -> Option<IpRect>
+where
+    C: Canvas,
+{
+    let mut bounds = Bounds::default();
+
+    for_each_sample(scale, font, text, options, |gx, gy, gv| {
+        let image_x = gx + x;
+        let image_y = gy + y;
+
+        if plot(canvas, image_x, image_y, gv) {
+            bounds.add(image_x, image_y);
+        }
+    });
+
+    bounds.rect()
+}
+
+/// The smallest rectangle containing a set of pixels, as `(left, top, right, bottom)`.
+#[derive(Copy, Clone, Debug, Default)]
+struct Bounds(Option<(i32, i32, i32, i32)>);
+
+impl Bounds {
+    fn add(&mut self, x: i32, y: i32) {
+        self.0 = Some(match self.0 {
+            Some((left, top, right, bottom)) => {
+                (left.min(x), top.min(y), right.max(x), bottom.max(y))
+            }
+            None => (x, y, x, y),
+        });
+    }
+
+    fn add_rect(&mut self, rect: Option<IpRect>) {
+        if let Some(rect) = rect {
+            self.add(rect.left(), rect.top());
+            self.add(rect.right(), rect.bottom());
+        }
+    }
+
+    fn rect(&self) -> Option<IpRect> {
+        self.0.map(|(left, top, right, bottom)| {
+            IpRect::at(left, top).of_size((right - left + 1) as u32, (bottom - top + 1) as u32)
+        })
+    }
+}
+
+/// Draws colored text on an image in place, as [`draw_text_mut`](fn.draw_text_mut.html) does, but
+/// only changes pixels which lie inside `clip`.
+///
+/// This is useful for confining text to part of an image, such as a table cell, without rendering
+/// it to a separate image first. Parts of `clip` outside the canvas are ignored.
+pub fn draw_text_clipped_mut<'a, C>(
+    canvas: &'a mut C,
+    color: C::Pixel,
+    x: i32,
+    y: i32,
+    scale: Scale,
+    font: &'a Font<'a>,
+    text: &'a str,
+    clip: IpRect,
+) where
+    C: Canvas,
+    <C::Pixel as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    let options = LayoutOptions::default();
+    for_each_sample(scale, font, text, &options, |gx, gy, gv| {
+        let (image_x, image_y) = (gx + x, gy + y);
+        if clip.contains(image_x, image_y) {
+            blend_coverage(canvas, image_x, image_y, color, gv);
+        }
+    });
+}
+
+/// A store of rasterized glyphs, for use with [`draw_text_cached_mut`](fn.draw_text_cached_mut.html).
+///
+/// Rasterizing glyphs is the most expensive part of drawing text, so caching them greatly speeds up
+/// drawing the same text repeatedly, e.g. when redrawing a label every frame. Glyphs are cached per
+/// glyph id, scale and sub-pixel position. Glyph ids are specific to a font, so a cache must only be
+/// used with a single `Font`.
+#[derive(Debug, Default)]
+pub struct GlyphCache {
+    glyphs: HashMap<GlyphCacheKey, Option<CachedGlyph>>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+struct GlyphCacheKey {
+    id: u16,
+    /// Bit patterns of the horizontal and vertical scale.
+    scale: (u32, u32),
+    /// Bit patterns of the fractional parts of the glyph position.
+    offset: (u32, u32),
+}
+
+#[derive(Clone, Debug)]
+struct CachedGlyph {
+    /// Position of the top-left corner of the glyph relative to the integer part of its position.
+    left: i32,
+    top: i32,
+    width: usize,
+    coverage: Vec<f32>,
+}
+
+impl GlyphCache {
+    /// Creates an empty cache.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The number of rasterized glyphs in this cache.
+    pub fn len(&self) -> usize {
+        self.glyphs.len()
+    }
+
+    /// Whether this cache contains no glyphs.
+    pub fn is_empty(&self) -> bool {
+        self.glyphs.is_empty()
+    }
+
+    /// Removes all glyphs from this cache.
+    pub fn clear(&mut self) {
+        self.glyphs.clear();
+    }
+
+    fn get_or_rasterize(&mut self, glyph: &PositionedGlyph) -> Option<&CachedGlyph> {
+        let position = glyph.position();
+        let (base_x, base_y) = (position.x.floor(), position.y.floor());
+        let scale = glyph.scale();
+        let key = GlyphCacheKey {
+            id: glyph.id().0,
+            scale: (scale.x.to_bits(), scale.y.to_bits()),
+            offset: (
+                (position.x - base_x).to_bits(),
+                (position.y - base_y).to_bits(),
+            ),
+        };
+
+        self.glyphs
+            .entry(key)
+            .or_insert_with(|| {
+                let bb = glyph.pixel_bounding_box()?;
+                let width = bb.width() as usize;
+                let mut coverage = vec![0.0; width * bb.height() as usize];
+                glyph.draw(|gx, gy, gv| coverage[gy as usize * width + gx as usize] = gv);
+                Some(CachedGlyph {
+                    left: bb.min.x - base_x as i32,
+                    top: bb.min.y - base_y as i32,
+                    width,
+                    coverage,
+                })
+            })
+            .as_ref()
+    }
+}
+
+/// Draws colored text on an image in place, reusing glyphs rasterized by previous calls with the same `cache`.
+///
+/// The output is identical to that of [`draw_text_mut`](fn.draw_text_mut.html). See
+/// [`GlyphCache`](struct.GlyphCache.html) for when this is useful.
+pub fn draw_text_cached_mut<'a, C>(
+    canvas: &'a mut C,
+    color: C::Pixel,
+    x: i32,
+    y: i32,
+    scale: Scale,
+    font: &'a Font<'a>,
+    text: &'a str,
+    cache: &mut GlyphCache,
+) where
+    C: Canvas,
+    <C::Pixel as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    let options = LayoutOptions::default();
+    for_each_cached_sample(cache, scale, font, text, &options, |gx, gy, gv| {
+        blend_coverage(canvas, gx + x, gy + y, color, gv);
+    });
+}
+
+/// Calls `f(x, y, coverage)` for every glyph coverage sample of the laid out text as `for_each_sample`
+/// does, taking glyphs from `cache` and rasterizing only those it does not yet contain.
+///
+/// Synthetic bold and shear are not applied to cached glyphs.
+fn for_each_cached_sample(
+    cache: &mut GlyphCache,
+    scale: Scale,
+    font: &Font,
+    text: &str,
+    options: &LayoutOptions,
+    mut f: impl FnMut(i32, i32, f32),
+) {
+    let mirror = sample_mirror(scale, font, text, options);
+    layout_glyphs(scale, font, text, options, |g, _| {
+        let position = g.position();
+        let (base_x, base_y) = (position.x.floor() as i32, position.y.floor() as i32);
+        if let Some(glyph) = cache.get_or_rasterize(&g) {
+            for (i, &gv) in glyph.coverage.iter().enumerate() {
+                let gx = base_x + glyph.left + (i % glyph.width) as i32;
+                let gy = base_y + glyph.top + (i / glyph.width) as i32;
+                let (gx, gy) = mirror(gx, gy);
+                f(gx, gy, gv);
+            }
+        }
+    });
+}
+
+/// A piece of text to draw with [`draw_labels_mut`](fn.draw_labels_mut.html).
+#[derive(Copy, Clone, Debug)]
+pub struct Label<'a, P> {
+    /// The text of the label.
+    pub text: &'a str,
+    /// The x coordinate of the top-left corner of the text box.
+    pub x: i32,
+    /// The y coordinate of the top-left corner of the text box.
+    pub y: i32,
+    /// The style the label is drawn in.
+    pub style: TextStyle<'a, P>,
+}
+
+/// Draws many labels on an image in place, e.g. the names of thousands of points on a map, more quickly
+/// than drawing each separately.
+///
+/// Each label is drawn as by [`TextStyle::draw_mut`](struct.TextStyle.html#method.draw_mut), in order, and
+/// the region each label drew to is returned in the same order. Glyphs are rasterized once and shared
+/// between all labels with the same font, as for [`draw_text_cached_mut`](fn.draw_text_cached_mut.html).
+/// Labels whose style has an outline, decorations, synthetic bold or shear gain nothing from this, and are
+/// drawn without the cache.
+pub fn draw_labels_mut<C>(canvas: &mut C, labels: &[Label<C::Pixel>]) -> Vec<Option<IpRect>>
+where
+    C: Canvas,
+    <C::Pixel as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    let mut caches: Vec<(&Font, GlyphCache)> = vec![];
+    let mut drawn = Vec::with_capacity(labels.len());
+
+    for label in labels {
+        let style = &label.style;
+        let cacheable = style.outline.is_none()
+            && style.decoration == TextDecoration::NONE
+            && !style.options.synthesizes();
+        if !cacheable {
+            drawn.push(style.draw_mut(canvas, label.x, label.y, label.text));
+            continue;
+        }
+
+        let index = match caches
+            .iter()
+            .position(|(font, _)| std::ptr::eq(*font, style.font))
+        {
+            Some(index) => index,
+            None => {
+                caches.push((style.font, GlyphCache::new()));
+                caches.len() - 1
+            }
+        };
+        let cache = &mut caches[index].1;
+
+        let mut bounds = Bounds::default();
+        for_each_cached_sample(
+            cache,
+            style.scale,
+            style.font,
+            label.text,
+            &style.options,
+            |gx, gy, gv| {
+                let (gx, gy) = (gx + label.x, gy + label.y);
+                if style.plot(canvas, gx, gy, (style.coverage_map.0)(gv)) {
+                    bounds.add(gx, gy);
+                }
+            },
+        );
+        drawn.push(bounds.rect());
+    }
+
+    drawn
+}
+
+/// Calls `f(x, y, coverage)` for every glyph coverage sample of the laid out text, where
+/// `(x, y)` is relative to the top-left corner of the text box.
+fn for_each_sample(
+    scale: Scale,
+    font: &Font,
+    text: &str,
+    options: &LayoutOptions,
+    mut f: impl FnMut(i32, i32, f32),
+) -> (i32, i32) {
+    if scale.x >= 0.0 && scale.y >= 0.0 {
+        return layout_glyphs(scale, font, text, options, |g, bb| {
+            for_each_glyph_sample(&g, bb, options, &mut f)
+        });
+    }
+
+    let mirror = sample_mirror(scale, font, text, options);
+    layout_glyphs(scale, font, text, options, |g, bb| {
+        for_each_glyph_sample(&g, bb, options, |gx, gy, gv| {
+            let (gx, gy) = mirror(gx, gy);
+            f(gx, gy, gv)
+        })
+    })
+}
+
+/// Maps a sample of the text laid out at `magnitude(scale)` to the pixel it is drawn to at `scale`.
+///
+/// Negative scales mirror the samples within the text box of the text laid out at positive scale,
+/// as given by `measure`, so mirrored text covers the same box as unmirrored text.
+fn sample_mirror(
+    scale: Scale,
+    font: &Font,
+    text: &str,
+    options: &LayoutOptions,
+) -> impl Fn(i32, i32) -> (i32, i32) {
+    let (flip_x, flip_y) = (scale.x < 0.0, scale.y < 0.0);
+    let (w, h) = match flip_x || flip_y {
+        true => measure(scale, font, text, options),
+        false => (0, 0),
+    };
+    move |x, y| {
+        let x = if flip_x { w - 1 - x } else { x };
+        let y = if flip_y { h - 1 - y } else { y };
+        (x, y)
+    }
+}
+
+/// The smallest rectangle containing every sample given by `for_each_sample`, as `(left, top, right, bottom)`
+/// with `right` and `bottom` exclusive, or `None` if there are no samples.
+fn sample_bounds(
+    scale: Scale,
+    font: &Font,
+    text: &str,
+    options: &LayoutOptions,
+) -> Option<(i32, i32, i32, i32)> {
+    let (mut left, mut top) = (i32::MAX, i32::MAX);
+    let (right, bottom) = layout_glyphs(scale, font, text, options, |_, bb| {
+        left = left.min(bb.min.x);
+        top = top.min(bb.min.y);
+    });
+    if left == i32::MAX {
+        return None;
+    }
+
+    let (flip_x, flip_y) = (scale.x < 0.0, scale.y < 0.0);
+    if !flip_x && !flip_y {
+        return Some((left, top, right, bottom));
+    }
+    let (w, h) = measure(scale, font, text, options);
+    let (left, right) = if flip_x {
+        (w - right, w - left)
+    } else {
+        (left, right)
+    };
+    let (top, bottom) = if flip_y {
+        (h - bottom, h - top)
+    } else {
+        (top, bottom)
+    };
+    Some((left, top, right, bottom))
+}
+
+/// A view of a canvas which is flipped along each axis whose component of a scale is negative, for drawing
+/// text laid out at the magnitude of that scale so that it is mirrored within its text box.
+struct MirroredCanvas<'c, C> {
+    canvas: &'c mut C,
+    flip_x: bool,
+    flip_y: bool,
+}
+
+impl<C: Canvas> Canvas for MirroredCanvas<'_, C> {
+    type Pixel = C::Pixel;
+
+    fn dimensions(&self) -> (u32, u32) {
+        self.canvas.dimensions()
+    }
+
+    fn get_pixel(&self, x: u32, y: u32) -> Self::Pixel {
+        let (x, y) = self.mirror(x, y);
+        self.canvas.get_pixel(x, y)
+    }
+
+    fn draw_pixel(&mut self, x: u32, y: u32, color: Self::Pixel) {
+        let (x, y) = self.mirror(x, y);
+        self.canvas.draw_pixel(x, y, color)
+    }
+}
+
+impl<C: Canvas> MirroredCanvas<'_, C> {
+    fn mirror(&self, x: u32, y: u32) -> (u32, u32) {
+        let (width, height) = self.canvas.dimensions();
+        let x = if self.flip_x { width - 1 - x } else { x };
+        let y = if self.flip_y { height - 1 - y } else { y };
+        (x, y)
+    }
+}
+
+/// Draws text at `scale`, which may have negative components, with the top-left corner of its text box
+/// of the given size at `(x, y)`.
+///
+/// `draw` is called with a view of `canvas` and the position on it of the top-left corner of the text
+/// box, and should draw the text there laid out at `magnitude(scale)`. The text is then mirrored within
+/// its text box along each axis whose scale component is negative. `size` is only called if so.
+fn draw_mirrored_mut<C>(
+    canvas: &mut C,
+    x: i32,
+    y: i32,
+    scale: Scale,
+    size: impl FnOnce() -> (i32, i32),
+    draw: impl FnOnce(&mut MirroredCanvas<C>, i32, i32),
+) where
+    C: Canvas,
+{
+    let (flip_x, flip_y) = (scale.x < 0.0, scale.y < 0.0);
+    let (w, h) = match flip_x || flip_y {
+        true => size(),
+        false => (0, 0),
+    };
+    let (width, height) = canvas.dimensions();
+    let x = if flip_x { width as i32 - w - x } else { x };
+    let y = if flip_y { height as i32 - h - y } else { y };
+    let mut mirrored = MirroredCanvas {
+        canvas,
+        flip_x,
+        flip_y,
+    };
+    draw(&mut mirrored, x, y);
+}
+
+/// Calls `f(x, y, coverage)` for every coverage sample of a single glyph laid out by `layout_glyphs`
+/// with pixel bounding box `bb`, where `(x, y)` is relative to the top-left corner of the text box.
+fn for_each_glyph_sample(
+    g: &PositionedGlyph,
+    bb: Rect<i32>,
+    options: &LayoutOptions,
+    mut f: impl FnMut(i32, i32, f32),
+) {
+    let f = |gx: u32, gy: u32, gv| f(gx as i32 + bb.min.x, gy as i32 + bb.min.y, gv);
+    if options.synthesizes() {
+        draw_synthesized(g, bb, options, f)
+    } else {
+        g.draw(f)
+    }
+}
+
+/// Calls `f(x, y, coverage)` for every pixel of `bb`, which is the pixel bounding box of `glyph` after applying
+/// `options.synthetic_bold` and `options.shear` as computed by `LayoutOptions::synthesized_bounds`.
+///
+/// Faux bold smears the glyph's coverage `synthetic_bold` pixels to the right, with each pixel taking the
+/// maximum coverage smeared over it and the last pixel weighted by the fractional part of `synthetic_bold`.
+/// Faux italic then shifts each row of the result horizontally in proportion to its height above the
+/// baseline, splitting coverage between the two pixels a fractional shift straddles.
+fn draw_synthesized(
+    glyph: &PositionedGlyph,
+    bb: Rect<i32>,
+    options: &LayoutOptions,
+    mut f: impl FnMut(u32, u32, f32),
+) {
+    let glyph_bb = match glyph.pixel_bounding_box() {
+        Some(glyph_bb) => glyph_bb,
+        None => return,
+    };
+    let strength = options.synthetic_bold.max(0.0);
+    let extra = options.bold_columns() as usize;
+    let bold_width = glyph_bb.width() as usize + extra;
+    let (width, height) = (bb.width() as usize, bb.height() as usize);
+
+    let mut bold = vec![0f32; bold_width * height];
+    glyph.draw(|gx, gy, gv| {
+        let row = gy as usize * bold_width;
+        for k in 0..=extra {
+            let weight = (strength + 1.0 - k as f32).min(1.0);
+            let index = row + gx as usize + k;
+            bold[index] = bold[index].max(gv * weight);
+        }
+    });
+
+    let baseline = glyph.position().y;
+    let left = (glyph_bb.min.x - bb.min.x) as f32;
+    let mut coverage = vec![0f32; width * height];
+    for y in 0..height {
+        let shift = options.shear_offset((glyph_bb.min.y + y as i32) as f32 + 0.5, baseline);
+        for x in 0..bold_width {
+            let value = bold[y * bold_width + x];
+            if value == 0.0 {
+                continue;
+            }
+            let position = left + x as f32 + shift;
+            let x0 = position.floor();
+            let fraction = position - x0;
+            for &(target, weight) in &[(x0 as usize, 1.0 - fraction), (x0 as usize + 1, fraction)] {
+                if target < width && weight > 0.0 {
+                    let index = y * width + target;
+                    coverage[index] = (coverage[index] + value * weight).min(1.0);
+                }
+            }
+        }
+    }
+
+    for y in 0..height {
+        for x in 0..width {
+            f(x as u32, y as u32, coverage[y * width + x]);
+        }
+    }
+}
+
+/// Blends `color` into the canvas pixel at `(x, y)` weighted by `coverage`, if this point
+/// lies within the canvas bounds. Returns whether a pixel was drawn.
+fn blend_coverage<C>(canvas: &mut C, x: i32, y: i32, color: C::Pixel, coverage: f32) -> bool
+where
+    C: Canvas,
+    <C::Pixel as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    update_pixel(canvas, x, y, |pixel| blend_over(pixel, color, coverage))
+}
+
+/// Composites `color` with opacity scaled by `coverage` over `pixel`.
+///
+/// Pixels without an alpha channel are interpolated using `weighted_sum`. For pixels with an alpha
+/// channel this is the Porter-Duff source-over operator on premultiplied colors, so drawing onto
+/// transparent pixels takes on the text color rather than darkening towards transparent black.
+fn blend_over<P>(pixel: P, color: P, coverage: f32) -> P
+where
+    P: Pixel,
+    P::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    let alpha_index = match alpha_index::<P>() {
+        Some(index) => index,
+        None => return weighted_sum(pixel, color, 1.0 - coverage, coverage),
+    };
+
+    let max: f32 = cast(<P::Subpixel as Bounded>::max_value());
+    let dst_alpha: f32 = cast(pixel.channels()[alpha_index]);
+    let src_alpha: f32 = cast(color.channels()[alpha_index]);
+    let (dst_alpha, src_alpha) = (dst_alpha / max, src_alpha / max * coverage);
+    let out_alpha = src_alpha + dst_alpha * (1.0 - src_alpha);
+    if out_alpha <= 0.0 {
+        return pixel;
+    }
+
+    let mut out = pixel;
+    for (i, (o, &c)) in out
+        .channels_mut()
+        .iter_mut()
+        .zip(color.channels().iter())
+        .enumerate()
+    {
+        let blended = if i == alpha_index {
+            out_alpha * max
+        } else {
+            let (p, c): (f32, f32) = (cast(*o), cast(c));
+            (c * src_alpha + p * dst_alpha * (1.0 - src_alpha)) / out_alpha
+        };
+        *o = <P::Subpixel as Clamp<f32>>::clamp(blended.round());
+    }
+    out
+}
+
+/// Replaces the canvas pixel at `(x, y)` with `f` applied to its current value, if this point
+/// lies within the canvas bounds. Returns whether a pixel was drawn.
+fn update_pixel<C>(canvas: &mut C, x: i32, y: i32, f: impl FnOnce(C::Pixel) -> C::Pixel) -> bool
+where
+    C: Canvas,
+{
+    if x >= 0 && x < canvas.width() as i32 && y >= 0 && y < canvas.height() as i32 {
+        let pixel = canvas.get_pixel(x as u32, y as u32);
+        canvas.draw_pixel(x as u32, y as u32, f(pixel));
+        true
+    } else {
+        false
+    }
+}
+
+/// Distributes `coverage` at the fractional pixel position `(x, y)` over the (up to) four
+/// pixels it overlaps, using bilinear weights.
+fn splat_coverage<C>(canvas: &mut C, x: f32, y: f32, color: C::Pixel, coverage: f32)
+where
+    C: Canvas,
+    <C::Pixel as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    let (x0, y0) = (x.floor(), y.floor());
+    let (fx, fy) = (x - x0, y - y0);
+    let (x0, y0) = (x0 as i32, y0 as i32);
+
+    for &(dx, dy, weight) in &[
+        (0, 0, (1.0 - fx) * (1.0 - fy)),
+        (1, 0, fx * (1.0 - fy)),
+        (0, 1, (1.0 - fx) * fy),
+        (1, 1, fx * fy),
+    ] {
+        if weight > 0.0 {
+            blend_coverage(canvas, x0 + dx, y0 + dy, color, coverage * weight);
+        }
+    }
+}
+
+/// Get the bounding box of the given text when drawn by
+/// [`draw_text_rotated_mut`](fn.draw_text_rotated_mut.html) with the same `angle`.
+///
+/// The returned rectangle is relative to the anchor point of the rotation, i.e. it must be offset by the
+/// `x` and `y` passed to `draw_text_rotated_mut` to get image coordinates. Returns `None` if the text is empty.
+pub fn rotated_text_bounds(scale: Scale, font: &Font, text: &str, angle: f32) -> Option<IpRect> {
+    let (w, h) = text_size(scale, font, text);
+    if w <= 0 || h <= 0 {
+        return None;
+    }
+
+    let (sin, cos) = angle.sin_cos();
+    let corners = [
+        (0.0, 0.0),
+        (w as f32, 0.0),
+        (0.0, h as f32),
+        (w as f32, h as f32),
+    ];
+    let rotated = corners
+        .iter()
+        .map(|&(cx, cy)| (cx * cos - cy * sin, cx * sin + cy * cos));
+
+    let (mut left, mut top, mut right, mut bottom) = (f32::MAX, f32::MAX, f32::MIN, f32::MIN);
+    for (rx, ry) in rotated {
+        left = left.min(rx);
+        top = top.min(ry);
+        right = right.max(rx);
+        bottom = bottom.max(ry);
+    }
+
+    let (left, top) = (left.floor() as i32, top.floor() as i32);
+    let (right, bottom) = (right.ceil() as i32, bottom.ceil() as i32);
+    Some(IpRect::at(left, top).of_size(max(right - left, 1) as u32, max(bottom - top, 1) as u32))
+}
+
+/// Draws colored text on an image in place, rotated clockwise by `angle` radians about `(x, y)`.
+///
+/// `(x, y)` is the top-left corner of the unrotated text, so an `angle` of zero places the text exactly
+/// where [`draw_text_mut`](fn.draw_text_mut.html) would. Rotated coverage samples generally fall between
+/// pixels, so each one is distributed over the four nearest pixels with bilinear weights.
+/// Use [`rotated_text_bounds`](fn.rotated_text_bounds.html) to find the area covered by the text.
+pub fn draw_text_rotated_mut<'a, C>(
+    canvas: &'a mut C,
+    color: C::Pixel,
+    x: i32,
+    y: i32,
+    angle: f32,
+    scale: Scale,
+    font: &'a Font<'a>,
+    text: &'a str,
+) where
+    C: Canvas,
+    <C::Pixel as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    let (sin, cos) = angle.sin_cos();
+
+    for_each_sample(
+        scale,
+        font,
+        text,
+        &LayoutOptions::default(),
+        |gx, gy, gv| {
+            // Rotate the center of the sample, then shift back so that the splat is centered on it
+            let (cx, cy) = (gx as f32 + 0.5, gy as f32 + 0.5);
+            let rx = cx * cos - cy * sin + x as f32 - 0.5;
+            let ry = cx * sin + cy * cos + y as f32 - 0.5;
+            splat_coverage(canvas, rx, ry, color, gv);
+        },
+    );
+}
+
+/// The projective transformation mapping the corners `(0, 0)`, `(1, 0)`, `(1, 1)` and `(0, 1)` of the unit
+/// square to the corners of `quad`, in order, or `None` if `quad` is degenerate.
+///
+/// This is the closed form given by Heckbert in "Fundamentals of Texture Mapping and Image Warping", which
+/// unlike `Projection::from_control_points` does not depend on the scale of the coordinates.
+fn square_to_quad(quad: [(f32, f32); 4]) -> Option<Projection> {
+    let [(x0, y0), (x1, y1), (x2, y2), (x3, y3)] = quad;
+    let (sx, sy) = (x0 - x1 + x2 - x3, y0 - y1 + y2 - y3);
+    let (g, h) = if sx == 0.0 && sy == 0.0 {
+        // The quad is a parallelogram, so the transformation is affine
+        (0.0, 0.0)
+    } else {
+        let (dx1, dx2, dy1, dy2) = (x1 - x2, x3 - x2, y1 - y2, y3 - y2);
+        let det = dx1 * dy2 - dx2 * dy1;
+        if det == 0.0 {
+            return None;
+        }
+        ((sx * dy2 - dx2 * sy) / det, (dx1 * sy - sx * dy1) / det)
+    };
+    #[rustfmt::skip]
+    let transform = [
+        x1 - x0 + g * x1, x3 - x0 + h * x3, x0,
+        y1 - y0 + g * y1, y3 - y0 + h * y3, y0,
+        g,                h,                1.0,
+    ];
+    Projection::from_matrix(transform)
+}
+
+/// Draws colored text on an image in place, projectively transformed so that its text box fills the
+/// quadrilateral `quad`, e.g. to place a label onto a surface seen in perspective in a photo.
+///
+/// The corners of `quad` are the positions on the canvas of the top-left, top-right, bottom-right and
+/// bottom-left corners of the text box, whose size is given by [`text_size`](fn.text_size.html). The text
+/// is rasterized as by [`draw_text_mut`](fn.draw_text_mut.html) and then warped into place by
+/// [`warp_into`](../geometric_transformations/fn.warp_into.html) with bilinear interpolation. Nothing is
+/// drawn if the text is empty or `quad` is degenerate, e.g. if three of its corners lie on a line.
+pub fn draw_text_in_quad_mut<'a, C>(
+    canvas: &'a mut C,
+    color: C::Pixel,
+    quad: [(f32, f32); 4],
+    scale: Scale,
+    font: &'a Font<'a>,
+    text: &'a str,
+) where
+    C: Canvas,
+    <C::Pixel as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    let (w, h) = text_size(scale, font, text);
+    if w <= 0 || h <= 0 {
+        return;
+    }
+    let (mask, (left, top)) = rasterize_mask(scale, font, text, &LayoutOptions::default(), 1);
+
+    // Only the part of the canvas covered by the quad is warped into
+    let (canvas_width, canvas_height) = canvas.dimensions();
+    let bound = |coord: fn(&(f32, f32)) -> f32, init: f32, op: fn(f32, f32) -> f32| {
+        quad.iter().map(coord).fold(init, op)
+    };
+    let x0 = bound(|p| p.0, f32::INFINITY, f32::min).floor().max(0.0);
+    let y0 = bound(|p| p.1, f32::INFINITY, f32::min).floor().max(0.0);
+    let x1 = bound(|p| p.0, f32::NEG_INFINITY, f32::max)
+        .ceil()
+        .min(canvas_width as f32);
+    let y1 = bound(|p| p.1, f32::NEG_INFINITY, f32::max)
+        .ceil()
+        .min(canvas_height as f32);
+    if !(x1 > x0 && y1 > y0) {
+        return;
+    }
+
+    let projection = match square_to_quad(quad) {
+        Some(projection) => projection,
+        None => return,
+    };
+    let projection = Projection::translate(left as f32, top as f32)
+        .and_then(Projection::scale(1.0 / w as f32, 1.0 / h as f32))
+        .and_then(projection)
+        .and_then(Projection::translate(-x0, -y0));
+
+    let mut warped = CoverageMask::new((x1 - x0) as u32, (y1 - y0) as u32);
+    warp_into(
+        &mask,
+        &projection,
+        Interpolation::Bilinear,
+        Luma([0.0]),
+        &mut warped,
+    );
+    draw_mask_mut(canvas, &warped, x0 as i32, y0 as i32, color);
+}
+
+/// Draws colored text on an image in place, with the top-left corner of the text box at the fractional
+/// position `(x, y)`.
+///
+/// [`draw_text_mut`](fn.draw_text_mut.html) can only place text at whole pixels, so text which moves by
+/// less than a pixel per frame jumps instead of scrolling smoothly. Here the coverage of each pixel of the
+/// text is instead distributed over the four nearest canvas pixels with bilinear weights, and the summed
+/// coverage is blended into the canvas. When `x` and `y` are whole numbers the result matches `draw_text_mut`.
+///
+/// If `snap_baseline` is true then `y` is rounded to the nearest whole number, so the baseline always lies
+/// where `draw_text_mut` would place it while `x` keeps its fractional part. This stops horizontal stems from
+/// shimmering as their coverage is spread over two rows when text is animated vertically.
+pub fn draw_text_subpixel_mut<'a, C>(
+    canvas: &'a mut C,
+    color: C::Pixel,
+    x: f32,
+    y: f32,
+    snap_baseline: bool,
+    scale: Scale,
+    font: &'a Font<'a>,
+    text: &'a str,
+) where
+    C: Canvas,
+    <C::Pixel as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    let options = LayoutOptions::default();
+    let (left, top, right, bottom) = match sample_bounds(scale, font, text, &options) {
+        Some(bounds) => bounds,
+        None => return,
+    };
+
+    let y = if snap_baseline { y.round() } else { y };
+    // Samples shifted by a fraction of a pixel spill into one extra column and row
+    let (x0, y0) = (x.floor(), y.floor());
+    let (fx, fy) = (x - x0, y - y0);
+    let (width, height) = ((right - left + 1) as usize, (bottom - top + 1) as usize);
+    let mut coverage = vec![0f32; width * height];
+
+    for_each_sample(scale, font, text, &options, |gx, gy, gv| {
+        let (cx, cy) = ((gx - left) as usize, (gy - top) as usize);
+        for &(dx, dy, weight) in &[
+            (0, 0, (1.0 - fx) * (1.0 - fy)),
+            (1, 0, fx * (1.0 - fy)),
+            (0, 1, (1.0 - fx) * fy),
+            (1, 1, fx * fy),
+        ] {
+            let index = (cy + dy) * width + cx + dx;
+            coverage[index] = (coverage[index] + gv * weight).min(1.0);
+        }
+    });
+
+    let (x0, y0) = (x0 as i32 + left, y0 as i32 + top);
+    for cy in 0..height {
+        for cx in 0..width {
+            let value = coverage[cy * width + cx];
+            if value > 0.0 {
+                blend_coverage(canvas, x0 + cx as i32, y0 + cy as i32, color, value);
+            }
+        }
+    }
+}
+
+/// The position and direction of the polyline `path` at arc length `distance`, as `(x, y, cos, sin)`
+/// where `(cos, sin)` is the unit tangent. `lengths[i]` must be the arc length at `path[i]`.
+fn path_point(path: &[(f32, f32)], lengths: &[f32], distance: f32) -> Option<(f32, f32, f32, f32)> {
+    (1..path.len())
+        .find(|&i| lengths[i] > lengths[i - 1] && distance <= lengths[i])
+        .map(|i| {
+            let ((x0, y0), (x1, y1)) = (path[i - 1], path[i]);
+            let length = lengths[i] - lengths[i - 1];
+            let t = (distance - lengths[i - 1]) / length;
+            let (cos, sin) = ((x1 - x0) / length, (y1 - y0) / length);
+            (x0 + t * (x1 - x0), y0 + t * (y1 - y0), cos, sin)
+        })
+}
+
+/// Draws colored text on an image in place along the polyline `path`, such as a road name on a map.
+///
+/// The text is drawn as a single line, with its baseline following the path from the first point.
+/// Each glyph is placed at its arc length along the path and rotated to match the direction of the
+/// path at its horizontal center. Glyphs which would extend past the end of the path are not drawn.
+/// As for [`draw_text_rotated_mut`](fn.draw_text_rotated_mut.html), each coverage sample is distributed
+/// over the four nearest pixels with bilinear weights.
+///
+/// For each negative component of `scale` the text is mirrored within its text box, as given by
+/// [`text_size`](fn.text_size.html) with its top-left corner at the font's ascent above the start of the
+/// path, before it is bent along the path. So for a negative `scale.x` the text starts at the far end of the
+/// box along the path, and for a negative `scale.y` it hangs upside down below the path.
+pub fn draw_text_on_path_mut<'a, C>(
+    canvas: &'a mut C,
+    color: C::Pixel,
+    path: &[(f32, f32)],
+    scale: Scale,
+    font: &'a Font<'a>,
+    text: &'a str,
+) where
+    C: Canvas,
+    <C::Pixel as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    let mut lengths = Vec::with_capacity(path.len());
+    let mut total = 0.0;
+    for (i, &(x, y)) in path.iter().enumerate() {
+        if i > 0 {
+            let (px, py) = path[i - 1];
+            total += ((x - px).powi(2) + (y - py).powi(2)).sqrt();
+        }
+        lengths.push(total);
+    }
+
+    // Mirrored glyphs are reflected about the center of the text box along each flipped axis
+    let (flip_x, flip_y) = (scale.x < 0.0, scale.y < 0.0);
+    let (w, h) = match flip_x || flip_y {
+        true => text_size(scale, font, text),
+        false => (0, 0),
+    };
+    let scale = magnitude(scale);
+    let ascent = font.v_metrics(scale).ascent;
+
+    let options = LayoutOptions::default();
+    layout_line(scale, font, text, point(0.0, 0.0), &options, |_, g| {
+        let advance = g.unpositioned().h_metrics().advance_width;
+        let start = match flip_x {
+            true => w as f32 - g.position().x - advance,
+            false => g.position().x,
+        };
+        if start + advance > total {
+            return;
+        }
+        let (bb, (px, py, cos, sin)) = match (
+            g.pixel_bounding_box(),
+            path_point(path, &lengths, start + advance / 2.0),
+        ) {
+            (Some(bb), Some(p)) => (bb, p),
+            _ => return,
+        };
+
+        // Rotate each sample center about the middle of the glyph's baseline, then shift back so that
+        // the splat is centered on it
+        let middle = g.position().x + advance / 2.0;
+        g.draw(|gx, gy, gv| {
+            let lx = (gx as i32 + bb.min.x) as f32 + 0.5 - middle;
+            let ly = (gy as i32 + bb.min.y) as f32 + 0.5;
+            let lx = if flip_x { -lx } else { lx };
+            let ly = if flip_y {
+                h as f32 - 2.0 * ascent - ly
+            } else {
+                ly
+            };
+            let rx = px + lx * cos - ly * sin - 0.5;
+            let ry = py + lx * sin + ly * cos - 0.5;
+            splat_coverage(canvas, rx, ry, color, gv);
+        });
+    });
+}
+
+/// Glyph coverage in `[0, 1]` for each pixel of an area of text.
+///
+/// Coverage is stored unquantized so that blending it onto images with deep subpixel types such as
+/// `u16` uses their full range.
+type CoverageMask = Image<Luma<f32>>;
+
+/// Renders the coverage of the laid out text into a mask with `padding` empty pixels on every side.
+///
+/// Returns the mask and the position of its top-left corner relative to the top-left corner of the
+/// text box. Overlapping glyphs are merged by taking the maximum of their coverage.
+fn rasterize_mask(
+    scale: Scale,
+    font: &Font,
+    text: &str,
+    options: &LayoutOptions,
+    padding: u32,
+) -> (CoverageMask, (i32, i32)) {
+    let (min_x, min_y, max_x, max_y) = match sample_bounds(scale, font, text, options) {
+        Some(bounds) => bounds,
+        None => return (CoverageMask::new(0, 0), (0, 0)),
+    };
+
+    let padding = padding as i32;
+    let (left, top) = (min_x - padding, min_y - padding);
+    let mut mask = CoverageMask::new(
+        (max_x - left + padding) as u32,
+        (max_y - top + padding) as u32,
+    );
+
+    for_each_sample(scale, font, text, options, |gx, gy, gv| {
+        let (mx, my) = ((gx - left) as u32, (gy - top) as u32);
+        let current = mask.get_pixel_mut(mx, my);
+        current[0] = current[0].max(gv);
+    });
+
+    (mask, (left, top))
+}
+
+/// Renders the coverage of the given text into a grayscale mask, without drawing it onto an image.
+///
+/// Each pixel of the mask is the glyph coverage of the corresponding pixel of the text, scaled to lie
+/// between 0 and 255, as used by [`draw_text_mut`](fn.draw_text_mut.html) to blend the text color onto
+/// the canvas. Where glyphs overlap the larger coverage is kept. The mask is the smallest image containing
+/// the pixel bounding boxes of all glyphs, and the returned offset is the position of its top-left corner
+/// relative to the top-left corner of the text box. So drawing the mask at `(x + offset.0, y + offset.1)`
+/// matches drawing the text at `(x, y)`.
+///
+/// This is useful for processing the shape of the text before compositing it, e.g. blurring it
+/// or computing its distance transform. Empty text gives an empty mask and an offset of `(0, 0)`.
+pub fn rasterize_text(scale: Scale, font: &Font, text: &str) -> (GrayImage, (i32, i32)) {
+    let (mask, offset) = rasterize_mask(scale, font, text, &LayoutOptions::default(), 0);
+    (coverage_to_gray(&mask), offset)
+}
+
+/// Converts coverage between 0 and 1 to a grayscale image with values between 0 and 255.
+fn coverage_to_gray(mask: &CoverageMask) -> GrayImage {
+    let (width, height) = mask.dimensions();
+    GrayImage::from_fn(width, height, |x, y| {
+        Luma([(mask.get_pixel(x, y)[0].clamp(0.0, 1.0) * 255.0).round() as u8])
+    })
+}
+
+/// Draws colored text on an image in place, and returns the coverage it was drawn with as a stamp which
+/// can be used to recolor or animate the same pixels later without laying out the text again.
+///
+/// The stamp is the mask given by [`rasterize_text`](fn.rasterize_text.html) for the same text, along with
+/// the position of its top-left corner on the canvas. The text is drawn by blending `color` into the canvas
+/// weighted by this mask, which matches [`draw_text_mut`](fn.draw_text_mut.html) except where glyphs overlap,
+/// as the larger coverage is used there rather than blending each glyph in turn.
+pub fn draw_text_stamped_mut<'a, C>(
+    canvas: &'a mut C,
+    color: C::Pixel,
+    x: i32,
+    y: i32,
+    scale: Scale,
+    font: &'a Font<'a>,
+    text: &'a str,
+) -> (GrayImage, (i32, i32))
+where
+    C: Canvas,
+    <C::Pixel as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    let (mask, (left, top)) = rasterize_mask(scale, font, text, &LayoutOptions::default(), 0);
+    draw_mask_mut(canvas, &mask, x + left, y + top, color);
+    (coverage_to_gray(&mask), (x + left, y + top))
+}
+
+/// Draws colored text on an image in place, rasterizing it at `factor` times its size and averaging each
+/// `factor` by `factor` block of the result into one pixel, for smoother edges on small text.
+///
+/// Glyphs are laid out at `scale * factor` and the coverage of the whole text is box filtered down, so the
+/// text is positioned as by [`draw_text_mut`](fn.draw_text_mut.html) at `(x, y)` up to rounding of the
+/// enlarged layout. Where glyphs overlap the larger coverage is kept. A `factor` of 0 or 1 rasterizes the
+/// text at its own size.
+pub fn draw_text_supersampled_mut<'a, C>(
+    canvas: &'a mut C,
+    color: C::Pixel,
+    x: i32,
+    y: i32,
+    scale: Scale,
+    font: &'a Font<'a>,
+    text: &'a str,
+    factor: u32,
+) where
+    C: Canvas,
+    <C::Pixel as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    let factor = factor.max(1);
+    let large = Scale {
+        x: scale.x * factor as f32,
+        y: scale.y * factor as f32,
+    };
+    let (mask, (left, top)) = rasterize_mask(large, font, text, &LayoutOptions::default(), 0);
+    if mask.width() == 0 || mask.height() == 0 {
+        return;
+    }
+
+    // Blocks are aligned to the text box, so the first block may only partially overlap the mask
+    let f = factor as i32;
+    let (small_left, small_top) = (left.div_euclid(f), top.div_euclid(f));
+    let small_right = (left + mask.width() as i32 - 1).div_euclid(f);
+    let small_bottom = (top + mask.height() as i32 - 1).div_euclid(f);
+    let mut small = CoverageMask::new(
+        (small_right - small_left + 1) as u32,
+        (small_bottom - small_top + 1) as u32,
+    );
+    let weight = 1.0 / (factor * factor) as f32;
+    for (mx, my, p) in mask.enumerate_pixels() {
+        let sx = (left + mx as i32).div_euclid(f) - small_left;
+        let sy = (top + my as i32).div_euclid(f) - small_top;
+        small.get_pixel_mut(sx as u32, sy as u32)[0] += p[0] * weight;
+    }
+
+    draw_mask_mut(canvas, &small, x + small_left, y + small_top, color);
+}
+
+/// Blends `color` into the canvas weighted by the values of `mask`, with the top-left
+/// corner of the mask at `(x, y)`.
+/// Returns the smallest rectangle containing every pixel which was drawn to.
+fn draw_mask_mut<C>(
+    canvas: &mut C,
+    mask: &CoverageMask,
+    x: i32,
+    y: i32,
+    color: C::Pixel,
+) -> Option<IpRect>
+where
+    C: Canvas,
+    <C::Pixel as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    let mut bounds = Bounds::default();
+    for (mx, my, p) in mask.enumerate_pixels() {
+        if p[0] > 0.0 {
+            let (cx, cy) = (x + mx as i32, y + my as i32);
+            if blend_coverage(canvas, cx, cy, color, p[0].min(1.0)) {
+                bounds.add(cx, cy);
+            }
+        }
+    }
+    bounds.rect()
+}
+
+/// Grayscale dilation of `mask` by a disk of the given `radius`, i.e. each output pixel is the
+/// maximum of the input pixels within Euclidean distance `radius` of it.
+fn dilate_mask(mask: &CoverageMask, radius: u32) -> CoverageMask {
+    disk_filter(mask, radius, 0.0, f32::max)
+}
+
+/// Grayscale erosion of `mask` by a disk of the given `radius`, i.e. each output pixel is the
+/// minimum of the input pixels within Euclidean distance `radius` of it. Pixels outside the
+/// mask are treated as empty.
+fn erode_mask(mask: &CoverageMask, radius: u32) -> CoverageMask {
+    disk_filter(mask, radius, 1.0, f32::min)
+}
+
+/// Combines the pixels of `mask` within Euclidean distance `radius` of each pixel using `op`,
+/// starting from `init`. Pixels outside the mask are treated as empty.
+fn disk_filter(
+    mask: &CoverageMask,
+    radius: u32,
+    init: f32,
+    op: fn(f32, f32) -> f32,
+) -> CoverageMask {
+    let r = radius as i32;
+    let offsets: Vec<(i32, i32)> = (-r..=r)
+        .flat_map(|dy| (-r..=r).map(move |dx| (dx, dy)))
+        .filter(|&(dx, dy)| dx * dx + dy * dy <= r * r)
+        .collect();
+
+    let (width, height) = mask.dimensions();
+    CoverageMask::from_fn(width, height, |x, y| {
+        let value = offsets
+            .iter()
+            .map(|&(dx, dy)| {
+                let (sx, sy) = (x as i32 + dx, y as i32 + dy);
+                if sx >= 0 && sx < width as i32 && sy >= 0 && sy < height as i32 {
+                    mask.get_pixel(sx as u32, sy as u32)[0]
+                } else {
+                    0.0
+                }
+            })
+            .fold(init, op);
+        Luma([value])
+    })
+}
+
+/// Draws text on an image in place, filled with `fill` and surrounded by an outline of
+/// `outline_width` pixels in the `outline` color.
+///
+/// The outline is the coverage of the whole text dilated by a disk of radius `outline_width`, so the
+/// outlines of overlapping glyphs merge into one. It is drawn first, with the filled text on top.
+/// `(x, y)` is the top-left corner of the text box, as in [`draw_text_mut`](fn.draw_text_mut.html),
+/// so the outline extends `outline_width` pixels beyond this box in every direction.
+pub fn draw_text_outlined_mut<'a, C>(
+    canvas: &'a mut C,
+    fill: C::Pixel,
+    outline: C::Pixel,
+    outline_width: u32,
+    x: i32,
+    y: i32,
+    scale: Scale,
+    font: &'a Font<'a>,
+    text: &'a str,
+) where
+    C: Canvas,
+    <C::Pixel as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    let options = LayoutOptions::default();
+    let (mask, (left, top)) = rasterize_mask(scale, font, text, &options, outline_width);
+
+    draw_mask_mut(
+        canvas,
+        &dilate_mask(&mask, outline_width),
+        x + left,
+        y + top,
+        outline,
+    );
+    draw_mask_mut(canvas, &mask, x + left, y + top, fill);
+}
+
+/// Draws only the contours of the given text, leaving the interior of each glyph untouched.
+///
+/// The stroke lies inside the glyph outlines and is `stroke_width` pixels wide. It is the coverage of the
+/// text minus that coverage eroded by a disk of radius `stroke_width`, so parts of glyphs which are thinner
+/// than twice the stroke width are drawn solid. `(x, y)` is the top-left corner of the text box, as in
+/// [`draw_text_mut`](fn.draw_text_mut.html). A `stroke_width` of zero draws nothing.
+///
+/// This complements [`draw_text_outlined_mut`](fn.draw_text_outlined_mut.html), whose outline surrounds
+/// filled text, for effects where the background shows through the letters.
+pub fn draw_text_hollow_mut<'a, C>(
+    canvas: &'a mut C,
+    color: C::Pixel,
+    stroke_width: u32,
+    x: i32,
+    y: i32,
+    scale: Scale,
+    font: &'a Font<'a>,
+    text: &'a str,
+) where
+    C: Canvas,
+    <C::Pixel as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    if stroke_width == 0 {
+        return;
+    }
+
+    let options = LayoutOptions::default();
+    let (mut mask, (left, top)) = rasterize_mask(scale, font, text, &options, 0);
+    let eroded = erode_mask(&mask, stroke_width);
+    for (p, e) in mask.pixels_mut().zip(eroded.pixels()) {
+        p[0] = (p[0] - e[0]).max(0.0);
+    }
+    draw_mask_mut(canvas, &mask, x + left, y + top, color);
+}
+
+/// Draws text with an inner shadow, so that it looks engraved into the image, e.g. for embossed labels.
+///
+/// The text is filled with `color`, and the band of each glyph's interior within `depth` pixels of the edges
+/// facing the light is then blended with `shadow`, while the band along the opposite edges is blended with
+/// `highlight`. `light` is the direction in which the light travels, in image coordinates, so the default
+/// look of light shining from the top-left is given by `(1.0, 1.0)`. Only the direction of `light` matters,
+/// and a `light` of `(0.0, 0.0)` or a `depth` of zero draws the plain filled text. `(x, y)` is the top-left
+/// corner of the text box, as in [`draw_text_mut`](fn.draw_text_mut.html).
+///
+/// The bands are found by offsetting the coverage of the text by `depth` pixels towards and away from the
+/// light: a pixel is in shadow to the extent that it is covered but its offset towards the light is not.
+pub fn draw_text_embossed_mut<'a, C>(
+    canvas: &'a mut C,
+    color: C::Pixel,
+    shadow: C::Pixel,
+    highlight: C::Pixel,
+    light: (f32, f32),
+    depth: u32,
+    x: i32,
+    y: i32,
+    scale: Scale,
+    font: &'a Font<'a>,
+    text: &'a str,
+) where
+    C: Canvas,
+    <C::Pixel as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    let options = LayoutOptions::default();
+    let (mask, (left, top)) = rasterize_mask(scale, font, text, &options, 0);
+    draw_mask_mut(canvas, &mask, x + left, y + top, color);
+
+    let length = light.0.hypot(light.1);
+    if depth == 0 || length == 0.0 {
+        return;
+    }
+    let dx = (light.0 / length * depth as f32).round() as i32;
+    let dy = (light.1 / length * depth as f32).round() as i32;
+
+    // The coverage of the text which is not also covered when offset by (ox, oy)
+    let uncovered_by = |ox: i32, oy: i32| {
+        let (width, height) = mask.dimensions();
+        CoverageMask::from_fn(width, height, |mx, my| {
+            let (sx, sy) = (mx as i32 - ox, my as i32 - oy);
+            let offset = if sx >= 0 && sx < width as i32 && sy >= 0 && sy < height as i32 {
+                mask.get_pixel(sx as u32, sy as u32)[0]
+            } else {
+                0.0
+            };
+            Luma([mask.get_pixel(mx, my)[0] * (1.0 - offset)])
+        })
+    };
+    draw_mask_mut(canvas, &uncovered_by(dx, dy), x + left, y + top, shadow);
+    draw_mask_mut(
+        canvas,
+        &uncovered_by(-dx, -dy),
+        x + left,
+        y + top,
+        highlight,
+    );
+}
+
+/// Collects the contours of a glyph outline as closed polylines, approximating curves by line segments.
+#[derive(Default)]
+struct Contours {
+    /// Added to every point, to convert from glyph to text box coordinates.
+    offset: (f32, f32),
+    current: Vec<(f32, f32)>,
+    contours: Vec<Vec<(f32, f32)>>,
+}
+
+impl Contours {
+    fn last(&self) -> (f32, f32) {
+        *self.current.last().unwrap_or(&self.offset)
+    }
+
+    /// Appends `segments` points of the curve given by `f(t)` for `t` in `(0, 1]`.
+    fn flatten(&mut self, length: f32, f: impl Fn(f32) -> (f32, f32)) {
+        let segments = (length / 2.0).ceil().max(1.0) as usize;
+        for i in 1..=segments {
+            self.current.push(f(i as f32 / segments as f32));
+        }
+    }
+
+    fn finish_contour(&mut self) {
+        if let (Some(&first), Some(&last)) = (self.current.first(), self.current.last()) {
+            if first != last {
+                self.current.push(first);
+            }
+        }
+        if self.current.len() > 1 {
+            self.contours.push(std::mem::take(&mut self.current));
+        }
+        self.current.clear();
+    }
+}
+
+/// A sequence of points joined by straight lines.
+type Polyline = Vec<(f32, f32)>;
+
+/// The contours of the glyphs of the laid out text as closed polylines, in text box coordinates and mirrored
+/// for negative scales as `for_each_sample` mirrors samples, along with the top-left and exclusive bottom-right
+/// corners of the pixel bounds of the glyphs as given by `sample_bounds`.
+fn text_contours(
+    scale: Scale,
+    font: &Font,
+    text: &str,
+    options: &LayoutOptions,
+) -> (Vec<Polyline>, (i32, i32), (i32, i32)) {
+    let mut contours: Vec<Polyline> = vec![];
+    layout_glyphs(scale, font, text, options, |g, bb| {
+        let mut builder = Contours {
+            offset: (bb.min.x as f32, bb.min.y as f32),
+            ..Contours::default()
+        };
+        g.build_outline(&mut builder);
+        builder.finish_contour();
+        contours.extend(builder.contours);
+    });
+
+    // A pixel `p` is mirrored to `w - 1 - p`, so the continuous coordinates of its edges map to `w - x`
+    let (flip_x, flip_y) = (scale.x < 0.0, scale.y < 0.0);
+    if flip_x || flip_y {
+        let (w, h) = measure(scale, font, text, options);
+        for point in contours.iter_mut().flatten() {
+            if flip_x {
+                point.0 = w as f32 - point.0;
+            }
+            if flip_y {
+                point.1 = h as f32 - point.1;
+            }
+        }
+    }
+    let (left, top, right, bottom) =
+        sample_bounds(scale, font, text, options).unwrap_or((0, 0, 0, 0));
+    (contours, (left, top), (right, bottom))
+}
+
+fn distance(a: (f32, f32), b: (f32, f32)) -> f32 {
+    (b.0 - a.0).hypot(b.1 - a.1)
+}
+
+impl OutlineBuilder for Contours {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.finish_contour();
+        self.current.push((x + self.offset.0, y + self.offset.1));
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        self.current.push((x + self.offset.0, y + self.offset.1));
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        let (dx, dy) = self.offset;
+        let (p0, p1, p2) = (self.last(), (x1 + dx, y1 + dy), (x + dx, y + dy));
+        self.flatten(distance(p0, p1) + distance(p1, p2), |t| {
+            let u = 1.0 - t;
+            (
+                u * u * p0.0 + 2.0 * u * t * p1.0 + t * t * p2.0,
+                u * u * p0.1 + 2.0 * u * t * p1.1 + t * t * p2.1,
+            )
+        });
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        let (dx, dy) = self.offset;
+        let p0 = self.last();
+        let (p1, p2, p3) = ((x1 + dx, y1 + dy), (x2 + dx, y2 + dy), (x + dx, y + dy));
+        let length = distance(p0, p1) + distance(p1, p2) + distance(p2, p3);
+        self.flatten(length, |t| {
+            let u = 1.0 - t;
+            let (a, b, c, d) = (u * u * u, 3.0 * u * u * t, 3.0 * u * t * t, t * t * t);
+            (
+                a * p0.0 + b * p1.0 + c * p2.0 + d * p3.0,
+                a * p0.1 + b * p1.1 + c * p2.1 + d * p3.1,
+            )
+        });
+    }
+
+    fn close(&mut self) {
+        self.finish_contour();
+    }
+}
+
+/// Calls `f(start, end)` for each piece of the polyline `points` which is "on" according to `dashes`,
+/// an array of alternating on and off lengths which starts with an on length and repeats along the
+/// whole polyline.
+fn for_each_dash(points: &[(f32, f32)], dashes: &[f32], mut f: impl FnMut((f32, f32), (f32, f32))) {
+    let mut index = 0;
+    let mut remaining = dashes[0];
+    for segment in points.windows(2) {
+        let (a, b) = (segment[0], segment[1]);
+        let length = distance(a, b);
+        let at = |d: f32| {
+            let t = if length > 0.0 { d / length } else { 0.0 };
+            (a.0 + (b.0 - a.0) * t, a.1 + (b.1 - a.1) * t)
+        };
+
+        let mut travelled = 0.0;
+        while travelled < length {
+            let step = remaining.min(length - travelled);
+            if index % 2 == 0 && step > 0.0 {
+                f(at(travelled), at(travelled + step));
+            }
+            travelled += step;
+            remaining -= step;
+            if remaining <= 0.0 {
+                index = (index + 1) % dashes.len();
+                remaining = dashes[index];
+            }
+        }
+    }
+}
+
+/// Adds to `mask` the coverage of a line of width `2 * half_width` from `a` to `b` with round ends,
+/// keeping the maximum coverage of each pixel.
+fn stroke_segment(mask: &mut CoverageMask, a: (f32, f32), b: (f32, f32), half_width: f32) {
+    let reach = half_width + 1.0;
+    let (width, height) = mask.dimensions();
+    let left = (a.0.min(b.0) - reach).floor().max(0.0) as u32;
+    let top = (a.1.min(b.1) - reach).floor().max(0.0) as u32;
+    let right = ((a.0.max(b.0) + reach).ceil().max(0.0) as u32).min(width);
+    let bottom = ((a.1.max(b.1) + reach).ceil().max(0.0) as u32).min(height);
+
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let length_squared = dx * dx + dy * dy;
+    for py in top..bottom {
+        for px in left..right {
+            let p = (px as f32 + 0.5, py as f32 + 0.5);
+            let t = if length_squared > 0.0 {
+                (((p.0 - a.0) * dx + (p.1 - a.1) * dy) / length_squared).clamp(0.0, 1.0)
+            } else {
+                0.0
+            };
+            let d = distance(p, (a.0 + dx * t, a.1 + dy * t));
+            let coverage = (half_width + 0.5 - d).clamp(0.0, 1.0);
+            let current = mask.get_pixel_mut(px, py);
+            current[0] = current[0].max(coverage);
+        }
+    }
+}
+
+/// Draws a dashed line of `width` pixels along the contours of the glyphs of the given text, without
+/// filling them, e.g. for decorative "cut here" labels.
+///
+/// `dashes` gives the lengths in pixels of alternating dashes and gaps, starting with a dash, and is repeated
+/// as often as needed. As in SVG, an array with an odd number of lengths is repeated twice to give an even
+/// number. Each contour starts with the start of the pattern, which then continues around the whole contour,
+/// including across the point where it closes. An empty pattern, or one whose lengths are not all positive,
+/// draws solid lines.
+///
+/// The line is centered on the contours, so it extends `width / 2` pixels outside the text box at
+/// `(x, y)`, which is positioned as in [`draw_text_mut`](fn.draw_text_mut.html).
+pub fn draw_text_dashed_outline_mut<'a, C>(
+    canvas: &'a mut C,
+    color: C::Pixel,
+    x: i32,
+    y: i32,
+    width: f32,
+    dashes: &[f32],
+    scale: Scale,
+    font: &'a Font<'a>,
+    text: &'a str,
+) where
+    C: Canvas,
+    <C::Pixel as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    if width <= 0.0 {
+        return;
+    }
+    let dashes: Vec<f32> = if dashes.is_empty()
+        || dashes
+            .iter()
+            .any(|d| d.partial_cmp(&0.0) != Some(Ordering::Greater))
+    {
+        vec![f32::INFINITY]
+    } else if dashes.len() % 2 == 1 {
+        dashes.iter().chain(dashes.iter()).copied().collect()
+    } else {
+        dashes.to_vec()
+    };
+
+    let (contours, (min_x, min_y), (w, h)) =
+        text_contours(scale, font, text, &LayoutOptions::default());
+    if contours.is_empty() {
+        return;
+    }
+
+    let padding = (width / 2.0).ceil() as i32 + 1;
+    let (left, top) = (min_x - padding, min_y - padding);
+    let mut mask = CoverageMask::new((w - left + padding) as u32, (h - top + padding) as u32);
+    let to_mask = |p: (f32, f32)| (p.0 - left as f32, p.1 - top as f32);
+    for contour in &contours {
+        for_each_dash(contour, &dashes, |a, b| {
+            stroke_segment(&mut mask, to_mask(a), to_mask(b), width / 2.0);
+        });
+    }
+    draw_mask_mut(canvas, &mask, x + left, y + top, color);
+}
+
+/// How the corners of a thick outline are drawn, set by [`TextStyle::outline_join`].
+///
+/// [`TextStyle::outline_join`]: struct.TextStyle.html#method.outline_join
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum StrokeJoin {
+    /// Corners are rounded, with the outline reaching exactly its width from the glyph at every point.
+    #[default]
+    Round,
+    /// Corners are cut off by a straight line between the ends of the outline along the two edges
+    /// which meet there.
+    Bevel,
+    /// The outline along the two edges which meet at a corner is extended until it meets in a sharp
+    /// point. Corners sharp enough that this point would lie more than four times the outline width
+    /// from the corner are beveled instead, as for the default miter limit of SVG.
+    Miter,
+}
+
+/// The largest ratio of the distance from a corner to the tip of its miter join to the stroke width.
+const MITER_LIMIT: f32 = 4.0;
+
+/// Draws the outline of the laid out text, as drawn by [`draw_text_outlined_mut`](fn.draw_text_outlined_mut.html),
+/// without the text itself. Returns the smallest rectangle containing every pixel which was drawn to.
+///
+/// Round joins dilate the coverage of the text. Other joins are built by tracing the glyph contours,
+/// which do not include synthetic bold or shear, so round joins are used whenever these are set.
+fn draw_outline_mut<C>(
+    canvas: &mut C,
+    outline: C::Pixel,
+    outline_width: u32,
+    join: StrokeJoin,
+    x: i32,
+    y: i32,
+    scale: Scale,
+    font: &Font,
+    text: &str,
+    options: &LayoutOptions,
+) -> Option<IpRect>
+where
+    C: Canvas,
+    <C::Pixel as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    if join == StrokeJoin::Round || outline_width == 0 || options.synthesizes() {
+        let (mask, (left, top)) = rasterize_mask(scale, font, text, options, outline_width);
+        let outline_mask = dilate_mask(&mask, outline_width);
+        return draw_mask_mut(canvas, &outline_mask, x + left, y + top, outline);
+    }
+
+    let padding = (outline_width as f32 * MITER_LIMIT).ceil() as u32 + 1;
+    let (mut mask, (left, top)) = rasterize_mask(scale, font, text, options, padding);
+    let (contours, _, _) = text_contours(scale, font, text, options);
+    let to_mask = |p: (f32, f32)| (p.0 - left as f32, p.1 - top as f32);
+    for contour in &contours {
+        let mut points: Vec<(f32, f32)> = contour.iter().map(|&p| to_mask(p)).collect();
+        points.dedup();
+        stroke_closed_polyline(&mut mask, &points, outline_width as f32, join);
+    }
+    draw_mask_mut(canvas, &mask, x + left, y + top, outline)
+}
+
+/// Adds to `mask` the coverage of a line of width `2 * half_width` centered on the closed polyline `points`,
+/// whose last point repeats its first, with corners drawn as given by `join`. Ends of segments are not
+/// rounded, so `join` must not be `StrokeJoin::Round`.
+fn stroke_closed_polyline(
+    mask: &mut CoverageMask,
+    points: &[(f32, f32)],
+    half_width: f32,
+    join: StrokeJoin,
+) {
+    let normal = |a: (f32, f32), b: (f32, f32)| {
+        let length = distance(a, b);
+        (
+            -(b.1 - a.1) / length * half_width,
+            (b.0 - a.0) / length * half_width,
+        )
+    };
+    let offset = |p: (f32, f32), n: (f32, f32), s: f32| (p.0 + s * n.0, p.1 + s * n.1);
+
+    if points.len() < 3 {
+        return;
+    }
+    for i in 0..points.len() - 1 {
+        let (a, b) = (points[i], points[i + 1]);
+        let n = normal(a, b);
+        fill_convex_polygon(
+            mask,
+            &[
+                offset(a, n, 1.0),
+                offset(b, n, 1.0),
+                offset(b, n, -1.0),
+                offset(a, n, -1.0),
+            ],
+        );
+
+        // The join at b with the following edge, wrapping around at the end of the contour
+        let c = points.get(i + 2).copied().unwrap_or(points[1]);
+        let m = normal(b, c);
+        for &side in &[1.0, -1.0] {
+            let (p, q) = (offset(b, n, side), offset(b, m, side));
+            let bisector = (n.0 + m.0, n.1 + m.1);
+            let length = bisector.0.hypot(bisector.1);
+            // The cosine of half the angle between the two normals
+            let cos = length / (2.0 * half_width);
+            if join == StrokeJoin::Miter && cos > 1.0 / MITER_LIMIT {
+                let reach = half_width / cos / length;
+                let tip = offset(b, (bisector.0 * reach, bisector.1 * reach), side);
+                fill_convex_polygon(mask, &[b, p, tip, q]);
+            } else {
+                fill_convex_polygon(mask, &[b, p, q]);
+            }
+        }
+    }
+}
+
+/// Adds to `mask` the coverage of the convex polygon with the given vertices, which may be in either
+/// winding order, keeping the maximum coverage of each pixel. Coverage is estimated from a 4x4 grid of
+/// samples in each pixel.
+fn fill_convex_polygon(mask: &mut CoverageMask, vertices: &[(f32, f32)]) {
+    const SAMPLES: u32 = 4;
+
+    let (width, height) = mask.dimensions();
+    let xs = vertices.iter().map(|v| v.0);
+    let ys = vertices.iter().map(|v| v.1);
+    let left = xs.clone().fold(f32::INFINITY, f32::min).floor().max(0.0) as u32;
+    let top = ys.clone().fold(f32::INFINITY, f32::min).floor().max(0.0) as u32;
+    let right = (xs.fold(f32::NEG_INFINITY, f32::max).ceil().max(0.0) as u32).min(width);
+    let bottom = (ys.fold(f32::NEG_INFINITY, f32::max).ceil().max(0.0) as u32).min(height);
+
+    let crosses = |p: (f32, f32)| {
+        (0..vertices.len()).map(move |i| {
+            let (a, b) = (vertices[i], vertices[(i + 1) % vertices.len()]);
+            (b.0 - a.0) * (p.1 - a.1) - (b.1 - a.1) * (p.0 - a.0)
+        })
+    };
+    let inside = |p: (f32, f32)| crosses(p).all(|c| c >= 0.0) || crosses(p).all(|c| c <= 0.0);
+
+    for py in top..bottom {
+        for px in left..right {
+            let mut hits = 0;
+            for sy in 0..SAMPLES {
+                for sx in 0..SAMPLES {
+                    let p = (
+                        px as f32 + (sx as f32 + 0.5) / SAMPLES as f32,
+                        py as f32 + (sy as f32 + 0.5) / SAMPLES as f32,
+                    );
+                    if inside(p) {
+                        hits += 1;
+                    }
+                }
+            }
+            let coverage = hits as f32 / (SAMPLES * SAMPLES) as f32;
+            let current = mask.get_pixel_mut(px, py);
+            current[0] = current[0].max(coverage);
+        }
+    }
+}
+
+/// Draws colored text on an image in place, above a drop shadow in the `shadow` color.
+///
+/// The shadow is a copy of the text offset by `shadow_offset` pixels and blurred by a Gaussian
+/// with standard deviation `shadow_blur`. A `shadow_blur` of zero draws a hard-edged shadow,
+/// which is cheaper as no blurring is required. `(x, y)` is the top-left corner of the text box,
+/// as in [`draw_text_mut`](fn.draw_text_mut.html).
+pub fn draw_text_with_shadow_mut<'a, C>(
+    canvas: &'a mut C,
+    color: C::Pixel,
+    shadow: C::Pixel,
+    shadow_offset: (i32, i32),
+    shadow_blur: f32,
+    x: i32,
+    y: i32,
+    scale: Scale,
+    font: &'a Font<'a>,
+    text: &'a str,
+) where
+    C: Canvas,
+    <C::Pixel as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    let options = LayoutOptions::default();
+    let (dx, dy) = shadow_offset;
+
+    if shadow_blur > 0.0 {
+        // Leave room for the blur to spread beyond the glyphs
+        let padding = (3.0 * shadow_blur).ceil() as u32;
+        let (mask, (left, top)) = rasterize_mask(scale, font, text, &options, padding);
+        if mask.width() > 0 {
+            let blurred = gaussian_blur_f32(&mask, shadow_blur);
+            draw_mask_mut(canvas, &blurred, x + dx + left, y + dy + top, shadow);
+        }
+    } else {
+        draw_text_with_options_mut(canvas, shadow, x + dx, y + dy, scale, font, text, &options);
+    }
+
+    draw_text_with_options_mut(canvas, color, x, y, scale, font, text, &options);
+}
+
+/// Draws colored text on an image in place, above a faded reflection of itself, e.g. for a glossy
+/// effect in user interfaces.
+///
+/// The reflection is a vertically flipped copy of the text which starts `gap` pixels below the lowest
+/// pixel of the text. Its opacity fades linearly from `opacity` nearest the text to zero at the far end
+/// of the reflection, where `opacity` is clamped to lie between 0 and 1. `(x, y)` is the top-left corner
+/// of the text box, as in [`draw_text_mut`](fn.draw_text_mut.html).
+pub fn draw_text_with_reflection_mut<'a, C>(
+    canvas: &'a mut C,
+    color: C::Pixel,
+    opacity: f32,
+    gap: u32,
+    x: i32,
+    y: i32,
+    scale: Scale,
+    font: &'a Font<'a>,
+    text: &'a str,
+) where
+    C: Canvas,
+    <C::Pixel as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    let (mask, (left, top)) = rasterize_mask(scale, font, text, &LayoutOptions::default(), 0);
+    let (width, height) = mask.dimensions();
+    if height == 0 {
+        return;
+    }
+    draw_mask_mut(canvas, &mask, x + left, y + top, color);
+
+    let opacity = opacity.clamp(0.0, 1.0);
+    let reflection = CoverageMask::from_fn(width, height, |mx, my| {
+        let fade = 1.0 - (my as f32 + 0.5) / height as f32;
+        Luma([mask.get_pixel(mx, height - 1 - my)[0] * opacity * fade])
+    });
+    let reflection_y = y + top + height as i32 + gap as i32;
+    draw_mask_mut(canvas, &reflection, x + left, reflection_y, color);
+}
+
+/// Draws colored text on an image in place, on top of a rectangle filled with `background`.
+///
+/// The rectangle is the text box reported by [`text_size`](fn.text_size.html) at `(x, y)`, extended by
+/// `padding` pixels on every side. The background is blended onto the canvas according to its alpha
+/// channel, so a translucent `background` lets the image show through. Pixel types without an alpha
+/// channel give an opaque background. Nothing is drawn for empty text.
+pub fn draw_text_with_background_mut<'a, C>(
+    canvas: &'a mut C,
+    color: C::Pixel,
+    background: C::Pixel,
+    padding: u32,
+    x: i32,
+    y: i32,
+    scale: Scale,
+    font: &'a Font<'a>,
+    text: &'a str,
+) where
+    C: Canvas,
+    <C::Pixel as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    let (w, h) = text_size(scale, font, text);
+    if w <= 0 || h <= 0 {
+        return;
+    }
+
+    let padding = padding as i32;
+    for by in (y - padding)..(y + h + padding) {
+        for bx in (x - padding)..(x + w + padding) {
+            blend_coverage(canvas, bx, by, background, 1.0);
+        }
+    }
+
+    draw_text_mut(canvas, color, x, y, scale, font, text);
+}
+
+/// Draws text on an image in place in whichever of `light` and `dark` contrasts more with the image behind
+/// it, e.g. for captions over photos. Returns the color chosen.
+///
+/// The background is the average luminance of the canvas pixels in the [`ink_bounds`](fn.ink_bounds.html) of
+/// the text, sampled before anything is drawn, and the color whose luminance differs most from it is used.
+/// Luminance is computed by `Pixel::to_luma`, so alpha channels are ignored. If the ink box lies entirely
+/// outside the canvas then nothing is drawn and `light` is returned. `(x, y)` is the top-left corner of the
+/// text box, as in [`draw_text_mut`](fn.draw_text_mut.html).
+pub fn draw_text_contrast_mut<'a, C>(
+    canvas: &'a mut C,
+    light: C::Pixel,
+    dark: C::Pixel,
+    x: i32,
+    y: i32,
+    scale: Scale,
+    font: &'a Font<'a>,
+    text: &'a str,
+) -> C::Pixel
+where
+    C: Canvas,
+    <C::Pixel as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    let ink = match ink_bounds(scale, font, text) {
+        Some(ink) => ink,
+        None => return light,
+    };
+    let (left, top) = ((ink.left() + x).max(0), (ink.top() + y).max(0));
+    let right = (ink.right() + x).min(canvas.width() as i32 - 1);
+    let bottom = (ink.bottom() + y).min(canvas.height() as i32 - 1);
+    if left > right || top > bottom {
+        return light;
+    }
+
+    let mut sum = 0.0;
+    for cy in top..=bottom {
+        for cx in left..=right {
+            sum += luminance(canvas.get_pixel(cx as u32, cy as u32));
+        }
+    }
+    let background = sum / ((right - left + 1) * (bottom - top + 1)) as f32;
+
+    let contrast = |color| (luminance(color) - background).abs();
+    let color = if contrast(dark) > contrast(light) {
+        dark
+    } else {
+        light
+    };
+    draw_text_mut(canvas, color, x, y, scale, font, text);
+    color
+}
+
+/// Draws text on an image in place in black or white, whichever contrasts more with the image behind it.
+/// Returns the color chosen.
+///
+/// Both colors are opaque. See [`draw_text_contrast_mut`](fn.draw_text_contrast_mut.html) for how the
+/// color is chosen, and to use other colors.
+pub fn draw_text_auto_contrast_mut<'a, C>(
+    canvas: &'a mut C,
+    x: i32,
+    y: i32,
+    scale: Scale,
+    font: &'a Font<'a>,
+    text: &'a str,
+) -> C::Pixel
+where
+    C: Canvas,
+    <C::Pixel as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    let max = <<C::Pixel as Pixel>::Subpixel as Bounded>::max_value();
+    let min = <<C::Pixel as Pixel>::Subpixel as Bounded>::min_value();
+    let channels = [max; 4];
+    let white = *C::Pixel::from_slice(&channels[..C::Pixel::CHANNEL_COUNT as usize]);
+    let black = white.map_without_alpha(|_| min);
+    draw_text_contrast_mut(canvas, white, black, x, y, scale, font, text)
+}
+
+/// The luminance of `pixel` as a fraction of the largest possible value.
+fn luminance<P>(pixel: P) -> f32
+where
+    P: Pixel,
+    P::Subpixel: ValueInto<f32>,
+{
+    let max: f32 = cast(<P::Subpixel as Bounded>::max_value());
+    let value: f32 = cast(pixel.to_luma()[0]);
+    value / max
+}
+
+/// A set of lines which can be drawn along with text by
+/// [`draw_text_decorated_mut`](fn.draw_text_decorated_mut.html).
+///
+/// Decorations are combined using `|`, e.g. `TextDecoration::UNDERLINE | TextDecoration::OVERLINE`.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct TextDecoration(u8);
+
+impl TextDecoration {
+    /// No decoration.
+    pub const NONE: TextDecoration = TextDecoration(0);
+    /// A line below the baseline, in the space used by descenders.
+    pub const UNDERLINE: TextDecoration = TextDecoration(1);
+    /// A line through the middle of lowercase letters.
+    pub const STRIKETHROUGH: TextDecoration = TextDecoration(1 << 1);
+    /// A line along the top of each line of text, at the font's ascent.
+    pub const OVERLINE: TextDecoration = TextDecoration(1 << 2);
+
+    /// Whether all decorations in `other` are also in `self`.
+    pub fn contains(self, other: TextDecoration) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl std::ops::BitOr for TextDecoration {
+    type Output = TextDecoration;
+
+    fn bitor(self, rhs: TextDecoration) -> TextDecoration {
+        TextDecoration(self.0 | rhs.0)
+    }
+}
+
+/// The default thickness of decoration lines at a given scale, `max(1, scale.y / 16)`.
+fn default_decoration_thickness(scale: Scale) -> f32 {
+    (scale.y / 16.0).max(1.0)
+}
+
+/// The height of lowercase letters above the baseline, measured from the glyph for 'x'.
+/// Falls back to half the ascent for fonts without this glyph.
+fn x_height(scale: Scale, font: &Font) -> f32 {
+    glyph_top(scale, font, 'x').unwrap_or_else(|| font.v_metrics(scale).ascent / 2.0)
+}
+
+/// The height of capital letters above the baseline, measured from the glyph for 'H'.
+/// Falls back to the ascent for fonts without this glyph.
+fn cap_height(scale: Scale, font: &Font) -> f32 {
+    glyph_top(scale, font, 'H').unwrap_or_else(|| font.v_metrics(scale).ascent)
+}
+
+/// The height above the baseline of the top of the glyph for `c`, if the font has one with an outline.
+fn glyph_top(scale: Scale, font: &Font, c: char) -> Option<f32> {
+    let glyph = font.glyph(c);
+    if glyph.id().0 == 0 {
+        return None;
+    }
+    glyph.scaled(scale).exact_bounding_box().map(|bb| -bb.min.y)
+}
+
+/// Draws colored text on an image in place, along with the lines given by `decoration` in the same color.
+///
+/// Decorations are drawn separately for each line of text, and span the width of that line as
+/// measured by [`text_size`](fn.text_size.html). The lines are `thickness` pixels thick, or
+/// `max(1, scale.y / 16)` if `thickness` is `None`. Relative to the baseline of each line:
+///
+/// * `UNDERLINE` is centered half way between the baseline and the descent,
+/// * `STRIKETHROUGH` is centered at half the x-height of the font,
+/// * `OVERLINE` starts at the ascent.
+///
+/// These positions and the thickness need not be whole numbers of pixels. The rows at the top and bottom
+/// edges of a line are blended with the coverage of the part of the row the line covers, so that decorations
+/// are anti-aliased in the same way as the edges of the glyphs.
+pub fn draw_text_decorated_mut<'a, C>(
+    canvas: &'a mut C,
+    color: C::Pixel,
+    x: i32,
+    y: i32,
+    decoration: TextDecoration,
+    thickness: Option<f32>,
+    scale: Scale,
+    font: &'a Font<'a>,
+    text: &'a str,
+) where
+    C: Canvas,
+    <C::Pixel as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    TextStyle::new(font, scale, color)
+        .decoration(decoration, thickness)
+        .draw_mut(canvas, x, y, text);
+}
+
+/// Draws the decoration lines of the laid out text, as drawn by
+/// [`draw_text_decorated_mut`](fn.draw_text_decorated_mut.html), without the text itself.
+/// Each line spans the glyphs of its line of text, wherever they are placed by `options`. With
+/// `Aliasing::Aliased`, rows which a line covers at least half of are set to `color` and others are
+/// left unchanged.
+///
+/// Returns the smallest rectangle containing every pixel which was drawn to.
+fn draw_decorations_mut<C>(
+    canvas: &mut C,
+    color: C::Pixel,
+    x: i32,
+    y: i32,
+    decoration: TextDecoration,
+    thickness: Option<f32>,
+    aliasing: Aliasing,
+    scale: Scale,
+    font: &Font,
+    text: &str,
+    options: &LayoutOptions,
+) -> Option<IpRect>
+where
+    C: Canvas,
+    <C::Pixel as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    if decoration == TextDecoration::NONE {
+        return None;
+    }
+
+    let thickness = thickness
+        .unwrap_or_else(|| default_decoration_thickness(scale))
+        .max(0.0);
+    let v_metrics = font.v_metrics(scale);
+    let x_height = x_height(scale, font);
+    let line_widths: Vec<i32> = text
+        .lines()
+        .map(|line| line_width(scale, font, line, options))
+        .collect();
+    let box_width = line_widths.iter().copied().max().unwrap_or(0);
+    let mut bounds = Bounds::default();
+
+    for (i, &width) in line_widths.iter().enumerate() {
+        if width <= 0 {
+            continue;
+        }
+        let left = x + match options.align {
+            HorizontalAlign::Left => 0,
+            HorizontalAlign::Center => (box_width - width) / 2,
+            HorizontalAlign::Right => box_width - width,
+        };
+
+        let line_advance = options.line_spacing.line_advance(&v_metrics);
+        let baseline = y as f32 + v_metrics.ascent + i as f32 * line_advance;
+        let mut tops = vec![];
+        if decoration.contains(TextDecoration::UNDERLINE) {
+            tops.push(baseline - v_metrics.descent / 2.0 - thickness / 2.0);
+        }
+        if decoration.contains(TextDecoration::STRIKETHROUGH) {
+            tops.push(baseline - x_height / 2.0 - thickness / 2.0);
+        }
+        if decoration.contains(TextDecoration::OVERLINE) {
+            tops.push(baseline - v_metrics.ascent);
+        }
+
+        for top in tops {
+            let bottom = top + thickness;
+            for ly in top.floor() as i32..bottom.ceil() as i32 {
+                // The fraction of this row which the line covers
+                let coverage = ((ly + 1) as f32).min(bottom) - (ly as f32).max(top);
+                for lx in left..left + width {
+                    let drawn = match aliasing {
+                        Aliasing::AntiAliased => {
+                            coverage > 0.0 && blend_coverage(canvas, lx, ly, color, coverage)
+                        }
+                        Aliasing::Aliased => {
+                            coverage >= 0.5 && update_pixel(canvas, lx, ly, |_| color)
+                        }
+                    };
+                    if drawn {
+                        bounds.add(lx, ly);
+                    }
+                }
+            }
+        }
+    }
+
+    bounds.rect()
+}
+
+/// Converts an sRGB encoded value in `[0, 1]` to linear light.
+fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Converts a linear light value in `[0, 1]` to sRGB encoding.
+fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.003_130_8 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// The index of the alpha channel of pixels of type `P`, if they have one.
+fn alpha_index<P: Pixel>() -> Option<usize> {
+    if P::COLOR_MODEL.ends_with('A') {
+        Some(P::CHANNEL_COUNT as usize - 1)
+    } else {
+        None
+    }
+}
+
+/// Interpolates between `pixel` and `color` by `coverage` in linear light, treating both as sRGB encoded.
+/// Alpha channels are not gamma encoded, so are interpolated directly.
+fn blend_linear<P>(pixel: P, color: P, coverage: f32) -> P
+where
+    P: Pixel,
+    P::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    let max: f32 = cast(<P::Subpixel as Bounded>::max_value());
+    let alpha_index = alpha_index::<P>();
+
+    let mut out = pixel;
+    for (i, (o, &c)) in out
+        .channels_mut()
+        .iter_mut()
+        .zip(color.channels().iter())
+        .enumerate()
+    {
+        let (p, c): (f32, f32) = (cast(*o), cast(c));
+        let blended = if Some(i) == alpha_index {
+            p * (1.0 - coverage) + c * coverage
+        } else {
+            let (p, c) = (srgb_to_linear(p / max), srgb_to_linear(c / max));
+            linear_to_srgb(p * (1.0 - coverage) + c * coverage) * max
+        };
+        *o = <P::Subpixel as Clamp<f32>>::clamp(blended.round());
+    }
+    out
+}
+
+/// Draws colored text on an image in place, blending glyph edges in linear light.
+///
+/// [`draw_text_mut`](fn.draw_text_mut.html) blends the text color with the image directly in the
+/// (usually sRGB encoded) pixel values, which makes anti-aliased text look too thin on dark
+/// backgrounds and too bold on light ones. This function instead decodes both colors to linear
+/// light, blends them according to glyph coverage and re-encodes the result, treating pixel values
+/// as sRGB relative to the maximum value of their subpixel type. This is slower than `draw_text_mut`
+/// but gives better looking edges, particularly for `Rgb<u8>` images.
+pub fn draw_text_linear_mut<'a, C>(
+    canvas: &'a mut C,
+    color: C::Pixel,
+    x: i32,
+    y: i32,
+    scale: Scale,
+    font: &'a Font<'a>,
+    text: &'a str,
+) where
+    C: Canvas,
+    <C::Pixel as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    let options = LayoutOptions::default();
+    for_each_sample(scale, font, text, &options, |gx, gy, gv| {
+        update_pixel(canvas, gx + x, gy + y, |pixel| {
+            blend_linear(pixel, color, gv)
+        });
+    });
+}
+
+/// How the text color is combined with the pixels it is drawn over, used by
+/// [`draw_text_blended_mut`](fn.draw_text_blended_mut.html).
+///
+/// Each mode combines a canvas channel value `p` and the corresponding text color channel value `c`,
+/// both scaled to lie between 0 and 1 relative to the maximum value of their subpixel type.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum BlendMode {
+    /// Replaces the canvas with the text color: `c`. This is how [`draw_text_mut`](fn.draw_text_mut.html) blends.
+    #[default]
+    Normal,
+    /// Darkens the canvas: `p * c`. Drawing white has no effect.
+    Multiply,
+    /// Lightens the canvas: `1 - (1 - p) * (1 - c)`. Drawing black has no effect.
+    Screen,
+    /// Adds the text color to the canvas, saturating at the maximum value: `min(p + c, 1)`.
+    Add,
+}
+
+impl BlendMode {
+    fn apply(&self, p: f32, c: f32) -> f32 {
+        match self {
+            BlendMode::Normal => c,
+            BlendMode::Multiply => p * c,
+            BlendMode::Screen => 1.0 - (1.0 - p) * (1.0 - c),
+            BlendMode::Add => (p + c).min(1.0),
+        }
+    }
+}
+
+/// Combines each color channel of `pixel` with `color` according to `mode`, then interpolates between
+/// `pixel` and the result by `coverage`. Alpha channels are interpolated directly towards `color`.
+/// `BlendMode::Normal` composites as `draw_text_mut` does.
+fn blend_with_mode<P>(pixel: P, color: P, mode: BlendMode, coverage: f32) -> P
+where
+    P: Pixel,
+    P::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    if mode == BlendMode::Normal {
+        return blend_over(pixel, color, coverage);
+    }
+
+    let max: f32 = cast(<P::Subpixel as Bounded>::max_value());
+    let alpha_index = alpha_index::<P>();
+
+    let mut out = pixel;
+    for (i, (o, &c)) in out
+        .channels_mut()
+        .iter_mut()
+        .zip(color.channels().iter())
+        .enumerate()
+    {
+        let (p, c): (f32, f32) = (cast(*o), cast(c));
+        let target = if Some(i) == alpha_index {
+            c
+        } else {
+            mode.apply(p / max, c / max) * max
+        };
+        let blended = p * (1.0 - coverage) + target * coverage;
+        *o = <P::Subpixel as Clamp<f32>>::clamp(blended.round());
+    }
+    out
+}
+
+/// Draws colored text on an image in place, combining the text color with the canvas according to `mode`.
+///
+/// Each color channel of a pixel is first combined with the text color as described by [`BlendMode`],
+/// and the result is then blended with the original pixel weighted by glyph coverage, so anti-aliased
+/// edges fade smoothly into the canvas. Single channel images such as `Luma` are handled in the same way.
+///
+/// For images with an alpha channel, the mode applies only to the color channels. The alpha channel
+/// is interpolated towards the alpha of `color` by glyph coverage, as for [`BlendMode::Normal`].
+///
+/// [`BlendMode`]: enum.BlendMode.html
+/// [`BlendMode::Normal`]: enum.BlendMode.html#variant.Normal
+pub fn draw_text_blended_mut<'a, C>(
+    canvas: &'a mut C,
+    color: C::Pixel,
+    x: i32,
+    y: i32,
+    mode: BlendMode,
+    scale: Scale,
+    font: &'a Font<'a>,
+    text: &'a str,
+) where
+    C: Canvas,
+    <C::Pixel as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    let options = LayoutOptions::default();
+    for_each_sample(scale, font, text, &options, |gx, gy, gv| {
+        update_pixel(canvas, gx + x, gy + y, |pixel| {
+            blend_with_mode(pixel, color, mode, gv)
+        });
+    });
+}
+
+/// Draws colored text on a new copy of an image. `scale` is augmented font scaling on both the x and y axis (in pixels).
+///
+/// See [`draw_text_mut`](fn.draw_text_mut.html) for how newlines are handled.
+pub fn draw_text<'a, I>(
+    image: &'a mut I,
+    color: I::Pixel,
+    x: i32,
+    y: i32,
+    scale: Scale,
+    font: &'a Font<'a>,
+    text: &'a str,
+) -> Image<I::Pixel>
+where
+    I: GenericImage,
+    <I::Pixel as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
+    I::Pixel: 'static,
+{
+    let mut out = ImageBuffer::new(image.width(), image.height());
+    out.copy_from(image, 0, 0).unwrap();
+    draw_text_mut(&mut out, color, x, y, scale, font, text);
+    out
+}
+
+/// A coordinate on one design axis of a variable font, used by [`variable_font`](fn.variable_font.html).
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct FontVariation {
+    /// The four byte tag of the axis, e.g. `*b"wght"`.
+    pub axis: [u8; 4],
+    /// The coordinate on the axis, in the axis' own units.
+    pub value: f32,
+}
+
+impl FontVariation {
+    /// A coordinate on the axis with the given tag.
+    pub fn new(axis: [u8; 4], value: f32) -> Self {
+        FontVariation { axis, value }
+    }
+
+    /// A coordinate on the weight axis `wght`, where 400 is regular and 700 is bold.
+    pub fn weight(value: f32) -> Self {
+        Self::new(*b"wght", value)
+    }
+
+    /// A coordinate on the width axis `wdth`, as a percentage of the normal width.
+    pub fn width(value: f32) -> Self {
+        Self::new(*b"wdth", value)
+    }
+}
+
+/// Loads a font from `data`, selecting an instance of a variable font by setting coordinates on its
+/// design axes.
+///
+/// Glyph outlines and advances of the returned font reflect the given `variations`, so the same font
+/// file can be drawn at e.g. several weights. A variation on an axis which the font does not have is
+/// silently ignored while the remaining variations still apply, and all variations are ignored for a
+/// font which is not variable, so the default instance is used in that case. Values outside the range
+/// of an axis are clamped to it. Returns `None` if `data` is not a valid font.
+///
+/// # Examples
+/// ```no_run
+/// use imageproc::drawing::{variable_font, FontVariation};
+///
+/// let data = std::fs::read("MyVariableFont.ttf").unwrap();
+/// let heading = variable_font(&data, &[FontVariation::weight(700.0)]).unwrap();
+/// let body = variable_font(&data, &[FontVariation::weight(400.0)]).unwrap();
+/// ```
+pub fn variable_font<'a>(data: &'a [u8], variations: &[FontVariation]) -> Option<Font<'a>> {
+    let mut face = owned_ttf_parser::Face::from_slice(data, 0).ok()?;
+    for variation in variations {
+        let _ = face.set_variation(
+            owned_ttf_parser::Tag::from_bytes(&variation.axis),
+            variation.value,
+        );
+    }
+    Some(Font::Ref(std::sync::Arc::new(face)))
+}
+
+/// An ordered list of fonts, used to draw text containing characters which are missing from a single font.
+///
+/// Each character is drawn with the first font in the stack which contains a glyph for it, so e.g. a
+/// Latin font can be followed by a CJK font to draw mixed text. Characters which no font contains are
+/// drawn with the primary font's replacement glyph.
+///
+/// # Examples
+/// ```no_run
+/// use imageproc::drawing::{draw_text_with_fallback_mut, FontStack};
+/// use image::{Rgb, RgbImage};
+/// use rusttype::{Font, Scale};
+///
+/// let latin: Font = unimplemented!(); // load your fonts here
+/// let cjk: Font = unimplemented!();
+/// let fonts = FontStack::new(latin).with_fallback(cjk);
+///
+/// let mut image = RgbImage::new(200, 100);
+/// let white = Rgb([255u8, 255u8, 255u8]);
+/// draw_text_with_fallback_mut(&mut image, white, 10, 10, Scale::uniform(16.0), &fonts, "Hello 世界");
+/// ```
+#[derive(Clone, Debug)]
+pub struct FontStack<'a> {
+    fonts: Vec<Font<'a>>,
+}
+
+impl<'a> FontStack<'a> {
+    /// A stack containing only `primary`.
+    pub fn new(primary: Font<'a>) -> Self {
+        FontStack {
+            fonts: vec![primary],
+        }
+    }
+
+    /// Adds `font` to the end of the stack, to be used for characters missing from every earlier font.
+    pub fn with_fallback(mut self, font: Font<'a>) -> Self {
+        self.fonts.push(font);
+        self
+    }
+
+    /// The fonts in the stack, starting with the primary font.
+    pub fn fonts(&self) -> &[Font<'a>] {
+        &self.fonts
+    }
+
+    /// The index of the font used to draw `c`, and its glyph in that font.
+    fn glyph(&self, c: char) -> (usize, Glyph<'a>) {
+        self.fonts
+            .iter()
+            .enumerate()
+            .map(|(i, font)| (i, font.glyph(c)))
+            .find(|(_, glyph)| glyph.id() != GlyphId(0))
+            .unwrap_or_else(|| (0, self.fonts[0].glyph(c)))
+    }
+
+    /// Vertical metrics which fit every font in the stack, so that lines mixing several fonts
+    /// share a single baseline.
+    fn v_metrics(&self, scale: Scale) -> VMetrics {
+        let mut metrics = self.fonts.iter().map(|font| font.v_metrics(scale));
+        let first = metrics.next().unwrap();
+        metrics.fold(first, |acc, m| VMetrics {
+            ascent: acc.ascent.max(m.ascent),
+            descent: acc.descent.min(m.descent),
+            line_gap: acc.line_gap.max(m.line_gap),
+        })
+    }
+}
+
+/// Lays out text as `layout_glyphs` does with default options, choosing a font from `fonts` for each character.
+///
+/// Every line uses the combined vertical metrics of the stack. Kerning is applied only between
+/// consecutive glyphs from the same font.
+fn layout_fallback_glyphs<'a>(
+    scale: Scale,
+    fonts: &FontStack<'a>,
+    text: &str,
+    mut f: impl FnMut(PositionedGlyph<'a>, Rect<i32>),
+) -> (i32, i32) {
+    let scale = magnitude(scale);
+    let v_metrics = fonts.v_metrics(scale);
+    let (mut w, mut h) = (0, 0);
+
+    for (i, line) in text.lines().enumerate() {
+        let mut caret = point(0.0, v_metrics.ascent + i as f32 * line_advance(&v_metrics));
+        let mut last_glyph: Option<(usize, GlyphId)> = None;
+
+        for c in line.chars() {
+            let (index, glyph) = fonts.glyph(c);
+            let g = glyph.scaled(scale);
+            if let Some((last_index, last_id)) = last_glyph {
+                if last_index == index {
+                    caret.x += fonts.fonts[index].pair_kerning(scale, last_id, g.id());
+                }
+            }
+            last_glyph = Some((index, g.id()));
+            let advance_width = g.h_metrics().advance_width;
+
+            let g = g.positioned(caret);
+            if let Some(bb) = g.pixel_bounding_box() {
+                w = max(w, bb.max.x);
+                h = max(h, bb.max.y);
+                f(g, bb);
+            }
+            caret.x += advance_width;
+        }
+    }
+
+    (w, h)
+}
+
+/// The characters of `text` which `font` has no glyph for, in order of first appearance and without duplicates.
+///
+/// A character is missing if it maps to glyph id 0, the `.notdef` glyph, which is usually drawn as an empty box.
+/// Line breaks and tabs are never drawn as glyphs, so are not reported. Checking this before drawing allows
+/// text to be rejected or substituted, or shows which fonts to add to a [`FontStack`](struct.FontStack.html).
+pub fn missing_glyphs(font: &Font, text: &str) -> Vec<char> {
+    let mut missing = vec![];
+    for c in text.chars() {
+        if matches!(c, '\n' | '\r' | '\t') || missing.contains(&c) {
+            continue;
+        }
+        if font.glyph(c).id() == GlyphId(0) {
+            missing.push(c);
+        }
+    }
+    missing
+}
+
+/// Get the width and height of the given text when drawn by
+/// [`draw_text_with_fallback_mut`](fn.draw_text_with_fallback_mut.html).
+pub fn text_size_with_fallback(scale: Scale, fonts: &FontStack, text: &str) -> (i32, i32) {
+    let size = layout_fallback_glyphs(scale, fonts, text, |_, _| {});
+    let v_metrics = fonts.v_metrics(magnitude(scale));
+    reserve_descent(size, &v_metrics, text, LineSpacing::default())
+}
+
+/// Draws colored text on an image in place, drawing each character with the first font in `fonts`
+/// which contains it.
+///
+/// Lines are spaced as for [`draw_text_mut`](fn.draw_text_mut.html), using the largest ascent, descent
+/// and line gap of any font in the stack, so glyphs from different fonts share a baseline. For each
+/// negative component of `scale` the text is mirrored within its text box, as given by
+/// [`text_size_with_fallback`](fn.text_size_with_fallback.html).
+pub fn draw_text_with_fallback_mut<'a, C>(
+    canvas: &'a mut C,
+    color: C::Pixel,
+    x: i32,
+    y: i32,
+    scale: Scale,
+    fonts: &'a FontStack<'a>,
+    text: &'a str,
+) where
+    C: Canvas,
+    <C::Pixel as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    let size = || text_size_with_fallback(scale, fonts, text);
+    draw_mirrored_mut(canvas, x, y, scale, size, |canvas, x, y| {
+        layout_fallback_glyphs(scale, fonts, text, |g, bb| {
+            g.draw(|gx, gy, gv| {
+                let (gx, gy) = (gx as i32 + bb.min.x, gy as i32 + bb.min.y);
+                blend_coverage(canvas, gx + x, gy + y, color, gv);
+            })
+        });
+    });
+}
+
+/// Draws text on an image in place, as [`draw_text_mut`](fn.draw_text_mut.html) does, except that glyphs
+/// which the font stores as color bitmaps are drawn as those images instead of in `color`.
+///
+/// This is intended for color emoji fonts. Color bitmaps are read from the font's `sbix` or `CBDT` table,
+/// choosing the strike whose size is closest to but not less than `scale.y`, and must be PNG encoded. Each
+/// image is resized from the size of its strike to `scale` and placed at the offset the font gives it from
+/// the glyph origin, and the pen advances by the glyph's advance width as usual. Images are composited using
+/// their alpha channel as coverage. All other glyphs, including layered color (`COLR`) glyphs, which are not
+/// supported, are drawn from their outlines in `color`. For each negative component of `scale` the text,
+/// color images included, is mirrored within the text box given by [`text_size`](fn.text_size.html).
+///
+/// Requires the `color-glyphs` feature.
+#[cfg(feature = "color-glyphs")]
+pub fn draw_text_with_color_glyphs_mut<'a, C>(
+    canvas: &'a mut C,
+    color: C::Pixel,
+    x: i32,
+    y: i32,
+    scale: Scale,
+    font: &'a Font<'a>,
+    text: &'a str,
+) where
+    C: Canvas,
+    <C::Pixel as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    let options = LayoutOptions::default();
+    let size = || measure(scale, font, text, &options);
+    let (flipped, scale) = (scale, magnitude(scale));
+    let v_metrics = font.v_metrics(scale);
+    let mut color_glyphs: HashMap<GlyphId, Option<ColorGlyph>> = HashMap::new();
+
+    draw_mirrored_mut(canvas, x, y, flipped, size, |canvas, x, y| {
+        for (i, line) in text.lines().enumerate() {
+            let baseline = v_metrics.ascent + i as f32 * line_advance(&v_metrics);
+            layout_line(scale, font, line, point(0.0, baseline), &options, |_, g| {
+                let color_glyph = color_glyphs
+                    .entry(g.id())
+                    .or_insert_with(|| color_glyph(scale, font, g.id()));
+                if let Some(glyph) = color_glyph {
+                    let position = g.position();
+                    let left = x + (position.x + glyph.left).round() as i32;
+                    let top = y + (position.y + glyph.top).round() as i32;
+                    for (ix, iy, pixel) in glyph.image.enumerate_pixels() {
+                        let alpha = pixel[3] as f32 / 255.0;
+                        if alpha > 0.0 {
+                            let color = pixel_from_rgb(pixel.to_rgb());
+                            blend_coverage(canvas, left + ix as i32, top + iy as i32, color, alpha);
+                        }
+                    }
+                } else if let Some(bb) = g.pixel_bounding_box() {
+                    for_each_glyph_sample(&g, bb, &options, |gx, gy, gv| {
+                        blend_coverage(canvas, gx + x, gy + y, color, gv);
+                    });
+                }
+            });
+        }
+    });
+}
+
+/// A color bitmap glyph decoded and resized to the scale it is drawn at.
+#[cfg(feature = "color-glyphs")]
+struct ColorGlyph {
+    image: image::RgbaImage,
+    /// Position of the top-left corner of the image relative to the glyph origin.
+    left: f32,
+    top: f32,
+}
+
+/// Reads and decodes the color bitmap for the glyph `id` from the `sbix` or `CBDT` table of `font`, resized
+/// to `scale`. Returns `None` if the font has no bitmap for the glyph or it cannot be decoded.
+#[cfg(feature = "color-glyphs")]
+fn color_glyph(scale: Scale, font: &Font, id: GlyphId) -> Option<ColorGlyph> {
+    let ppem = scale.y.abs().ceil().clamp(1.0, u16::MAX as f32) as u16;
+    let raster = font_face(font).glyph_raster_image(owned_ttf_parser::GlyphId(id.0), ppem)?;
+    let decoded = image::load_from_memory_with_format(raster.data, image::ImageFormat::Png).ok()?;
+    let decoded = decoded.to_rgba8();
+
+    let strike = raster.pixels_per_em.max(1) as f32;
+    let (sx, sy) = (scale.x.abs() / strike, scale.y.abs() / strike);
+    let width = (decoded.width() as f32 * sx).round().max(1.0) as u32;
+    let height = (decoded.height() as f32 * sy).round().max(1.0) as u32;
+    // The offsets are in pixels of the strike, with y measured upwards to the bottom of the image
+    Some(ColorGlyph {
+        image: resize(&decoded, width, height, FilterType::Triangle),
+        left: raster.x as f32 * sx,
+        top: -(raster.y as f32 + decoded.height() as f32) * sy,
+    })
+}
+
+/// The parsed font data of `font`, for reading tables which rusttype does not expose.
+fn font_face<'f>(font: &'f Font) -> &'f owned_ttf_parser::Face<'f> {
+    use owned_ttf_parser::AsFaceRef;
+    match font {
+        Font::Ref(face) => face.as_face_ref(),
+        Font::Owned(face) => face.as_face_ref(),
+    }
+}
+
+/// Converts an opaque color given by 8-bit RGB channels to a pixel of type `P`, scaling each channel to the
+/// range of `P::Subpixel`. Pixels with one or two channels take the luma of the color.
+#[cfg(feature = "color-glyphs")]
+fn pixel_from_rgb<P>(rgb: image::Rgb<u8>) -> P
+where
+    P: Pixel,
+    P::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    let max: f32 = cast(<P::Subpixel as Bounded>::max_value());
+    let channel = |value: u8| <P::Subpixel as Clamp<f32>>::clamp(value as f32 / 255.0 * max);
+    let ([r, g, b], luma) = (rgb.0, rgb.to_luma()[0]);
+    let channels = match (P::CHANNEL_COUNT, P::COLOR_MODEL) {
+        (1, _) | (2, _) => [luma, 255, 0, 0],
+        (_, "BGR") | (_, "BGRA") => [b, g, r, 255],
+        _ => [r, g, b, 255],
+    };
+    let channels: Vec<P::Subpixel> = channels.iter().map(|&c| channel(c)).collect();
+    *P::from_slice(&channels[..P::CHANNEL_COUNT as usize])
+}
+
+/// A reusable description of how to draw text: which font, scale and color to use,
+/// and how to arrange the glyphs.
+///
+/// # Examples
+/// ```no_run
+/// use imageproc::drawing::{TabWidth, TextStyle};
+/// use image::{Rgb, RgbImage};
+/// use rusttype::{Font, Scale};
+///
+/// let font: Font = unimplemented!(); // load your font here
+/// let mut image = RgbImage::new(200, 100);
+///
+/// let style = TextStyle::new(&font, Scale::uniform(16.0), Rgb([255u8, 255u8, 255u8]))
+///     .tab_width(TabWidth::Spaces(8.0));
+/// let (width, height) = style.size("Name\tValue");
+/// style.draw_mut(&mut image, 10, 10, "Name\tValue");
+/// ```
+#[derive(Copy, Clone, Debug)]
+pub struct TextStyle<'a, P> {
+    font: &'a Font<'a>,
+    scale: Scale,
+    color: P,
+    options: LayoutOptions,
+    aliasing: Aliasing,
+    decoration: TextDecoration,
+    decoration_thickness: Option<f32>,
+    outline: Option<(P, u32)>,
+    outline_join: StrokeJoin,
+    coverage_map: CoverageMap<'a>,
+}
+
+/// A function applied to glyph coverage by [`TextStyle::coverage_map`](struct.TextStyle.html#method.coverage_map).
+#[derive(Copy, Clone)]
+struct CoverageMap<'a>(&'a dyn Fn(f32) -> f32);
+
+impl std::fmt::Debug for CoverageMap<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("CoverageMap")
+    }
+}
+
+fn unchanged_coverage(coverage: f32) -> f32 {
+    coverage
+}
+
+/// Whether glyph edges are anti-aliased, used by [`TextStyle`](struct.TextStyle.html).
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum Aliasing {
+    /// Glyph edges are blended with the canvas according to how much of each pixel they cover.
+    #[default]
+    AntiAliased,
+    /// Pixels which are at least half covered by a glyph are set to the text color, and all others
+    /// are left unchanged. This gives crisp, pixel-art style text and is faster to draw.
+    Aliased,
+}
+
+impl<'a, P: Pixel> TextStyle<'a, P> {
+    /// A style which draws text in `font` at `scale` in the given `color`, with default settings
+    /// for everything else.
+    pub fn new(font: &'a Font<'a>, scale: Scale, color: P) -> Self {
+        TextStyle {
+            font,
+            scale,
+            color,
+            options: LayoutOptions::default(),
+            aliasing: Aliasing::default(),
+            decoration: TextDecoration::NONE,
+            decoration_thickness: None,
+            coverage_map: CoverageMap(&unchanged_coverage),
+            outline: None,
+            outline_join: StrokeJoin::default(),
+        }
+    }
+
+    /// Sets the color of the text.
+    pub fn color(mut self, color: P) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Sets the scale of the text.
+    pub fn scale(mut self, scale: Scale) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    /// Sets the font of the text.
+    pub fn font(mut self, font: &'a Font<'a>) -> Self {
+        self.font = font;
+        self
+    }
+
+    /// Adds `tracking` pixels of extra space between consecutive glyphs, as for
+    /// [`draw_text_spaced_mut`](fn.draw_text_spaced_mut.html). The default is 0.
+    pub fn tracking(mut self, tracking: f32) -> Self {
+        self.options.tracking = tracking;
+        self
+    }
+
+    /// Sets the distance between the baselines of consecutive lines. The default is
+    /// [`LineSpacing::default()`](enum.LineSpacing.html).
+    pub fn line_spacing(mut self, line_spacing: LineSpacing) -> Self {
+        self.options.line_spacing = line_spacing;
+        self
+    }
+
+    /// Sets how each line is aligned within the box formed by the widest line, as for
+    /// [`draw_text_aligned_mut`](fn.draw_text_aligned_mut.html). The default is left aligned.
+    pub fn align(mut self, align: HorizontalAlign) -> Self {
+        self.options.align = align;
+        self
+    }
+
+    /// Draws `decoration` lines in the text color along with the text, as for
+    /// [`draw_text_decorated_mut`](fn.draw_text_decorated_mut.html). The lines are `thickness` pixels thick,
+    /// which may be fractional, or `max(1, scale.y / 16)` if `thickness` is `None`. The default is no decoration.
+    pub fn decoration(mut self, decoration: TextDecoration, thickness: Option<f32>) -> Self {
+        self.decoration = decoration;
+        self.decoration_thickness = thickness;
+        self
+    }
+
+    /// Draws an outline of `width` pixels in the `outline` color around the text, as for
+    /// [`draw_text_outlined_mut`](fn.draw_text_outlined_mut.html). The outline extends beyond the text box
+    /// reported by [`size`](#method.size). The default is no outline.
+    pub fn outline(mut self, outline: P, width: u32) -> Self {
+        self.outline = Some((outline, width));
+        self
+    }
+
+    /// Sets how the corners of the outline are drawn, which makes a visible difference for thick outlines.
+    /// Joins other than [`StrokeJoin::Round`] are ignored when [`synthetic_bold`](#method.synthetic_bold) or
+    /// [`shear`](#method.shear) are set. The default is [`StrokeJoin::Round`].
+    ///
+    /// [`StrokeJoin::Round`]: enum.StrokeJoin.html#variant.Round
+    pub fn outline_join(mut self, join: StrokeJoin) -> Self {
+        self.outline_join = join;
+        self
+    }
+
+    /// Sets whether glyph edges are anti-aliased. The default is [`Aliasing::AntiAliased`].
+    ///
+    /// [`Aliasing::AntiAliased`]: enum.Aliasing.html#variant.AntiAliased
+    pub fn aliasing(mut self, aliasing: Aliasing) -> Self {
+        self.aliasing = aliasing;
+        self
+    }
+
+    /// Sets a function applied to the coverage of each pixel of the glyphs before it is used, e.g. to
+    /// adjust the gamma of the edges with `&|c| c.powf(gamma)` or for stylized effects. The function may
+    /// capture values chosen at runtime, and is borrowed so that the style stays `Copy`. The result is
+    /// clamped to lie between 0 and 1, and is then blended or thresholded as set by [`aliasing`](#method.aliasing).
+    /// Outlines and decorations are not affected. The default leaves coverage unchanged.
+    ///
+    /// To use a function which mutates the state it captures, pass it to [`draw_mut_with`](#method.draw_mut_with).
+    pub fn coverage_map(mut self, map: &'a dyn Fn(f32) -> f32) -> Self {
+        self.coverage_map = CoverageMap(map);
+        self
+    }
+
+    /// Sets the distance between tab stops. The default is four spaces.
+    pub fn tab_width(mut self, tab_width: TabWidth) -> Self {
+        self.options.tab_width = tab_width;
+        self
+    }
+
+    /// Fakes a bold weight by smearing each glyph `strength` pixels to the right, widening every
+    /// advance by the same amount. This is useful when a font has no bold face. The default is 0,
+    /// which leaves glyphs unchanged.
+    pub fn synthetic_bold(mut self, strength: f32) -> Self {
+        self.options.synthetic_bold = strength;
+        self
+    }
+
+    /// Fakes an italic style by slanting each glyph, shifting points `shear` pixels to the right for every
+    /// pixel they lie above the baseline. Points below the baseline shift left, so glyphs keep their
+    /// vertical position. Typical values are around 0.2; negative values slant glyphs to the left.
+    /// The default is 0, which leaves glyphs unchanged.
+    pub fn shear(mut self, shear: f32) -> Self {
+        self.options.shear = shear;
+        self
+    }
+
+    /// Sets whether the space between pairs of glyphs such as "AV" is adjusted using the font's kerning
+    /// table. Disabling kerning is useful for monospace rendering. The default is `true`.
+    pub fn kerning(mut self, kerning: bool) -> Self {
+        self.options.kerning = kerning;
+        self
+    }
+
+    /// Sets whether the digits '0' to '9' all take up the advance of the font's widest digit, emulating
+    /// tabular figures so that columns of numbers, and the decimal points within them, line up. Each digit
+    /// is centered within this advance and is not kerned, and other characters are unaffected. Sizes given
+    /// by [`size`](#method.size) include the widened advances. The default is `false`.
+    pub fn tabular_figures(mut self, tabular: bool) -> Self {
+        self.options.tabular_figures = tabular;
+        self
+    }
+
+    /// The width and height of `text` when drawn in this style.
+    /// See [`text_size`](fn.text_size.html).
+    pub fn size(&self, text: &str) -> (i32, i32) {
+        measure(self.scale, self.font, text, &self.options)
+    }
+
+    /// The position of the caret after the first `char_index` characters of `text` when drawn in this style,
+    /// including tracking, line spacing and alignment. See [`caret_position`](fn.caret_position.html).
+    pub fn caret_position(&self, text: &str, char_index: usize) -> (i32, i32) {
+        let (x, y) = caret_offset(self.scale, self.font, text, char_index, &self.options);
+        (x.round() as i32, y.round() as i32)
+    }
+
+    /// Draws the caret after the first `char_index` characters of `text`, when drawn in this style at `(x, y)`.
+    /// See [`draw_caret_mut`](fn.draw_caret_mut.html).
+    pub fn draw_caret_mut<C>(&self, canvas: &mut C, x: i32, y: i32, text: &str, char_index: usize)
+    where
+        C: Canvas<Pixel = P>,
+        P::Subpixel: ValueInto<f32> + Clamp<f32>,
+    {
+        let (cx, baseline) = caret_offset(self.scale, self.font, text, char_index, &self.options);
+        draw_caret_line(
+            canvas, self.color, x, y, self.scale, self.font, cx, baseline,
+        );
+    }
+
+    /// Draws `text` in this style on an image in place, with the top-left corner of the text box at `(x, y)`.
+    ///
+    /// The outline is drawn first, then the text, then any decorations. Returns the region which was drawn
+    /// to, including the outline and decorations, as described in
+    /// [`draw_text_bounded_mut`](fn.draw_text_bounded_mut.html).
+    pub fn draw_mut<C>(&self, canvas: &mut C, x: i32, y: i32, text: &str) -> Option<IpRect>
+    where
+        C: Canvas<Pixel = P>,
+        P::Subpixel: ValueInto<f32> + Clamp<f32>,
+    {
+        self.draw_mut_with(canvas, x, y, text, self.coverage_map.0)
+    }
+
+    /// Draws `text` as [`draw_mut`](#method.draw_mut) does, except that `map` is applied to the coverage of each
+    /// pixel of the glyphs in place of the style's [`coverage_map`](#method.coverage_map).
+    ///
+    /// As `map` is only used for this call it may mutate the state it captures, e.g. to count the pixels
+    /// drawn or to vary the coverage from one pixel to the next.
+    pub fn draw_mut_with<C>(
+        &self,
+        canvas: &mut C,
+        x: i32,
+        y: i32,
+        text: &str,
+        mut map: impl FnMut(f32) -> f32,
+    ) -> Option<IpRect>
+    where
+        C: Canvas<Pixel = P>,
+        P::Subpixel: ValueInto<f32> + Clamp<f32>,
+    {
+        let (color, scale, font, options) = (self.color, self.scale, self.font, &self.options);
+        let mut bounds = Bounds::default();
+
+        if let Some((outline, width)) = self.outline {
+            let join = self.outline_join;
+            let drawn = draw_outline_mut(
+                canvas, outline, width, join, x, y, scale, font, text, options,
+            );
+            bounds.add_rect(drawn);
+        }
+
+        bounds.add_rect(draw_samples_mut(
+            canvas,
+            x,
+            y,
+            scale,
+            font,
+            text,
+            options,
+            |canvas, x, y, gv| self.plot(canvas, x, y, map(gv)),
+        ));
+
+        let (decoration, thickness) = (self.decoration, self.decoration_thickness);
+        bounds.add_rect(draw_decorations_mut(
+            canvas,
+            color,
+            x,
+            y,
+            decoration,
+            thickness,
+            self.aliasing,
+            scale,
+            font,
+            text,
+            options,
+        ));
+
+        bounds.rect()
+    }
+
+    /// Draws a single glyph coverage sample in this style, after the coverage map has been applied to it.
+    /// Returns whether the pixel was drawn to.
+    fn plot<C>(&self, canvas: &mut C, x: i32, y: i32, coverage: f32) -> bool
+    where
+        C: Canvas<Pixel = P>,
+        P::Subpixel: ValueInto<f32> + Clamp<f32>,
+    {
+        let coverage = coverage.clamp(0.0, 1.0);
+        match self.aliasing {
+            Aliasing::AntiAliased => blend_coverage(canvas, x, y, self.color, coverage),
+            Aliasing::Aliased => coverage >= 0.5 && update_pixel(canvas, x, y, |_| self.color),
+        }
+    }
+
+    /// Lays out `text` in this style once, so that it can be measured and drawn without repeating the work.
+    ///
+    /// The layout uses the font, scale and arrangement settings of this style. It is drawn with
+    /// [`Layout::draw_mut`](struct.Layout.html#method.draw_mut), which does not draw outlines or decorations.
+    pub fn layout(&self, text: &str) -> Layout<'a> {
+        Layout::with_options(self.scale, self.font, text, self.options)
+    }
+}
+
+/// A single line of text made up of runs drawn in different styles, e.g. a bold red label followed
+/// by regular text.
+///
+/// Runs are placed one after another, each starting where the advance of the previous run ends.
+/// All runs share a baseline, which lies at the largest ascent of any run's font below the top of
+/// the line, so runs of different sizes line up. Kerning is not applied between runs. Runs should not
+/// contain newlines.
+///
+/// # Examples
+/// ```no_run
+/// use imageproc::drawing::{RichLine, TextStyle};
+/// use image::{Rgb, RgbImage};
+/// use rusttype::{Font, Scale};
+///
+/// let font: Font = unimplemented!(); // load your fonts here
+/// let bold: Font = unimplemented!();
+/// let mut image = RgbImage::new(200, 100);
+///
+/// let error = TextStyle::new(&bold, Scale::uniform(16.0), Rgb([255u8, 0u8, 0u8]));
+/// let message = TextStyle::new(&font, Scale::uniform(16.0), Rgb([255u8, 255u8, 255u8]));
+/// let line = RichLine::new()
+///     .with_run("Error: ", error)
+///     .with_run("file not found", message);
+/// let (width, height) = line.size();
+/// line.draw_mut(&mut image, (200 - width) / 2, 10);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct RichLine<'a, P> {
+    runs: Vec<(&'a str, TextStyle<'a, P>)>,
+}
+
+impl<'a, P: Pixel> RichLine<'a, P> {
+    /// Creates a line with no runs.
+    pub fn new() -> Self {
+        RichLine { runs: vec![] }
+    }
+
+    /// Appends `text` drawn in `style` to the end of this line.
+    pub fn with_run(mut self, text: &'a str, style: TextStyle<'a, P>) -> Self {
+        self.runs.push((text, style));
+        self
+    }
+
+    /// The position of the top-left corner of the text box of each run, relative to the top-left corner
+    /// of the line.
+    fn run_offsets(&self) -> Vec<(i32, i32)> {
+        let ascent = |style: &TextStyle<P>| style.font.v_metrics(style.scale).ascent;
+        let baseline = self
+            .runs
+            .iter()
+            .map(|(_, style)| ascent(style))
+            .fold(0.0, f32::max);
+
+        let mut pen = 0.0f32;
+        self.runs
+            .iter()
+            .map(|(text, style)| {
+                let offset = (
+                    pen.round() as i32,
+                    (baseline - ascent(style)).round() as i32,
+                );
+                pen += advance_width(style.scale, style.font, text, &style.options);
+                offset
+            })
+            .collect()
+    }
+
+    /// The width and height of the box containing every run, as measured by [`TextStyle::size`].
+    ///
+    /// [`TextStyle::size`]: struct.TextStyle.html#method.size
+    pub fn size(&self) -> (i32, i32) {
+        self.runs
+            .iter()
+            .zip(self.run_offsets())
+            .map(|((text, style), (x, y))| {
+                let (w, h) = style.size(text);
+                if w <= 0 || h <= 0 {
+                    (0, 0)
+                } else {
+                    (x + w, y + h)
+                }
+            })
+            .fold((0, 0), |(w, h), (rw, rh)| (max(w, rw), max(h, rh)))
+    }
+
+    /// Draws every run on an image in place, with the top-left corner of the line at `(x, y)`.
+    ///
+    /// Returns the region which was drawn to, as described in
+    /// [`draw_text_bounded_mut`](fn.draw_text_bounded_mut.html).
+    pub fn draw_mut<C>(&self, canvas: &mut C, x: i32, y: i32) -> Option<IpRect>
+    where
+        C: Canvas<Pixel = P>,
+        P::Subpixel: ValueInto<f32> + Clamp<f32>,
+    {
+        let mut bounds = Bounds::default();
+        for ((text, style), (dx, dy)) in self.runs.iter().zip(self.run_offsets()) {
+            bounds.add_rect(style.draw_mut(canvas, x + dx, y + dy, text));
+        }
+        bounds.rect()
+    }
+}
+
+/// Draws `ruby_text` in `ruby_style` centered above `base_text` in `base_style`, e.g. for furigana
+/// annotating the reading of Japanese text, with the top-left corner of the combined box at `(x, y)`.
+///
+/// The base text is drawn immediately below the box of the ruby text, as measured by
+/// [`TextStyle::size`]. If the ruby text is wider than the base text it overhangs it equally on both
+/// sides, and the base text is shifted right so that the combined box still starts at `x`.
+///
+/// Returns the region which was drawn to, as described in
+/// [`draw_text_bounded_mut`](fn.draw_text_bounded_mut.html).
+///
+/// [`TextStyle::size`]: struct.TextStyle.html#method.size
+pub fn draw_ruby_mut<'a, C>(
+    canvas: &mut C,
+    base_style: &TextStyle<'a, C::Pixel>,
+    ruby_style: &TextStyle<'a, C::Pixel>,
+    base_text: &str,
+    ruby_text: &str,
+    x: i32,
+    y: i32,
+) -> Option<IpRect>
+where
+    C: Canvas,
+    <C::Pixel as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    let (base_width, _) = base_style.size(base_text);
+    let (ruby_width, ruby_height) = ruby_style.size(ruby_text);
+    let width = max(base_width, ruby_width);
+
+    let mut bounds = Bounds::default();
+    bounds.add_rect(ruby_style.draw_mut(canvas, x + (width - ruby_width) / 2, y, ruby_text));
+    bounds.add_rect(base_style.draw_mut(
+        canvas,
+        x + (width - base_width) / 2,
+        y + ruby_height,
+        base_text,
+    ));
+    bounds.rect()
+}
+
+/// Draws `label` at the left of `rect` and `value` at its right, on the same baseline and both in `style`,
+/// e.g. for the rows of an invoice or table of contents, with the top of the text boxes at the top of `rect`.
+///
+/// The two are separated by at least the width of a space. If they do not both fit then `label` is truncated
+/// with an ellipsis by [`truncate_with_ellipsis`](fn.truncate_with_ellipsis.html), and `value` is always drawn
+/// in full. If `leader` is given, the gap between them is filled with copies of it, e.g. `Some('.')` for a
+/// dotted leader. Leader characters are placed at whole multiples of their advance from the left of `rect`, so
+/// that the leaders of rows drawn in the same style line up.
+///
+/// Returns the region which was drawn to, as described in
+/// [`draw_text_bounded_mut`](fn.draw_text_bounded_mut.html).
+pub fn draw_label_value_mut<'a, C>(
+    canvas: &mut C,
+    style: &TextStyle<'a, C::Pixel>,
+    label: &str,
+    value: &str,
+    rect: IpRect,
+    leader: Option<char>,
+) -> Option<IpRect>
+where
+    C: Canvas,
+    <C::Pixel as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    let (scale, font, options) = (style.scale, style.font, &style.options);
+    let gap = advance_width(scale, font, " ", options).ceil() as i32;
+    let (left, top) = (rect.left(), rect.top());
+
+    let value_x = left + rect.width() as i32 - style.size(value).0;
+    let available = max(0, value_x - left - gap) as u32;
+    let label = truncate_with_ellipsis(scale, font, label, available);
+
+    let mut bounds = Bounds::default();
+    bounds.add_rect(style.draw_mut(canvas, left, top, &label));
+    bounds.add_rect(style.draw_mut(canvas, value_x, top, value));
+
+    if let Some(leader) = leader {
+        let leader = leader.to_string();
+        let advance = advance_width(scale, font, &leader, options);
+        if advance > 0.0 {
+            let start = (style.size(&label).0 + gap) as f32;
+            let end = (value_x - left - gap) as f32;
+            let mut i = (start / advance).ceil();
+            while (i + 1.0) * advance <= end {
+                let leader_x = left + (i * advance).round() as i32;
+                bounds.add_rect(style.draw_mut(canvas, leader_x, top, &leader));
+                i += 1.0;
+            }
+        }
+    }
+
+    bounds.rect()
+}
+
+/// Text which has been laid out once, and can then be measured and drawn any number of times.
+///
+/// Calling [`text_size`](fn.text_size.html) followed by [`draw_text_mut`](fn.draw_text_mut.html)
+/// positions every glyph twice. A `Layout` positions them once, remembering the font and scale used.
+///
+/// # Examples
+/// ```no_run
+/// use imageproc::drawing::Layout;
+/// use image::{Rgb, RgbImage};
+/// use rusttype::{Font, Scale};
+///
+/// let font: Font = unimplemented!(); // load your font here
+/// let mut image = RgbImage::new(200, 100);
+///
+/// // Center the text horizontally
+/// let layout = Layout::new(Scale::uniform(16.0), &font, "Hello");
+/// let (width, _) = layout.size();
+/// layout.draw_mut(&mut image, Rgb([255u8, 255u8, 255u8]), (200 - width) / 2, 10);
+/// ```
+#[derive(Clone)]
+pub struct Layout<'a> {
+    font: &'a Font<'a>,
+    scale: Scale,
+    options: LayoutOptions,
+    glyphs: Vec<(PositionedGlyph<'a>, Rect<i32>)>,
+    size: (i32, i32),
+}
+
+impl<'a> Layout<'a> {
+    /// Lays out `text` as [`draw_text_mut`](fn.draw_text_mut.html) would.
+    pub fn new(scale: Scale, font: &'a Font<'a>, text: &str) -> Self {
+        Self::with_options(scale, font, text, LayoutOptions::default())
+    }
+
+    fn with_options(scale: Scale, font: &'a Font<'a>, text: &str, options: LayoutOptions) -> Self {
+        let mut glyphs = vec![];
+        let size = layout_chars(scale, font, text, &options, |_, g, bb| glyphs.push((g, bb)));
+        let v_metrics = font.v_metrics(magnitude(scale));
+        let size = reserve_descent(size, &v_metrics, text, options.line_spacing);
+        Layout {
+            font,
+            scale,
+            options,
+            glyphs,
+            size,
+        }
+    }
+
+    /// The width and height of the laid out text, as returned by [`text_size`](fn.text_size.html).
+    pub fn size(&self) -> (i32, i32) {
+        self.size
+    }
+
+    /// The font used to lay out the text.
+    pub fn font(&self) -> &'a Font<'a> {
+        self.font
+    }
+
+    /// The scale used to lay out the text.
+    pub fn scale(&self) -> Scale {
+        self.scale
+    }
+
+    /// Draws the laid out text on an image in place, with the top-left corner of the text box at `(x, y)`.
+    /// Returns the region which was drawn to, as described in
+    /// [`draw_text_bounded_mut`](fn.draw_text_bounded_mut.html). Negative components of the scale mirror
+    /// the text as for [`draw_text_mut`](fn.draw_text_mut.html).
+    pub fn draw_mut<C>(&self, canvas: &mut C, color: C::Pixel, x: i32, y: i32) -> Option<IpRect>
+    where
+        C: Canvas,
+        <C::Pixel as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
+    {
+        let (w, h) = self.size;
+        let (flip_x, flip_y) = (self.scale.x < 0.0, self.scale.y < 0.0);
+        let mut bounds = Bounds::default();
+        for (g, bb) in &self.glyphs {
+            for_each_glyph_sample(g, *bb, &self.options, |gx, gy, gv| {
+                let gx = if flip_x { w - 1 - gx } else { gx };
+                let gy = if flip_y { h - 1 - gy } else { gy };
+                if blend_coverage(canvas, gx + x, gy + y, color, gv) {
+                    bounds.add(gx + x, gy + y);
+                }
+            });
+        }
+        bounds.rect()
+    }
+}
+
+/// This helper function is used to find the top (or) left corner of a text.
+/// It takes handles only one dimension per call to make it more reusable.
+/// It takes a `rectangle_size` which is the length (width or height) of the surrounding rectangle
+/// and a `content_size` which is the length (width or height) of the text inside.
+/// The `relative_position` is then used to divide the space
+/// which is not used up by the content (=`content_size`) to determine a padding
+/// inside the rectangle `rectangle_size`.
+/// If the content does not fit inside the rectangle there is no padding, i.e. the content is pinned
+/// to the start of the rectangle.
+fn calculate_center(
+    rectangle_size: u32,
+    content_size: u32,
+    relative_position: &EdgePosition,
+) -> u32 {
+    // The field of `EdgePosition` is public, so it may not have been clamped on construction
+    let percent = clamp_percent(relative_position.0);
+    (rectangle_size.saturating_sub(content_size) as f32 * percent / 100.0) as u32
+}
+
+fn find_text_area_coordinates(
+    position: &Position,
+    rectangle: &IpRect,
+    width: u32,
+    height: u32,
+) -> (u32, u32) {
+    match position {
+        Position::HorizontalCenter(edge_position) => (
+            rectangle.left() as u32 + calculate_center(rectangle.width(), width, edge_position),
+            rectangle.top() as u32
+                + calculate_center(rectangle.height(), height, &EdgePosition::center()),
+        ),
+        Position::HorizontalBottom(edge_position) => (
+            rectangle.left() as u32 + calculate_center(rectangle.width(), width, edge_position),
+            max(rectangle.top(), rectangle.bottom() - height as i32) as u32,
+        ),
+        Position::HorizontalTop(edge_position) => (
+            rectangle.left() as u32 + calculate_center(rectangle.width(), width, edge_position),
+            rectangle.top() as u32,
+        ),
+        Position::VerticalCenter(edge_position) => (
+            rectangle.left() as u32
+                + calculate_center(rectangle.width(), width, &EdgePosition::center()),
+            rectangle.top() as u32 + calculate_center(rectangle.height(), height, edge_position),
+        ),
+        Position::VerticalRight(edge_position) => (
+            max(rectangle.left(), rectangle.right() - width as i32) as u32,
+            rectangle.top() as u32 + calculate_center(rectangle.height(), height, edge_position),
+        ),
+        Position::VerticalLeft(edge_position) => (
+            rectangle.left() as u32,
+            rectangle.top() as u32 + calculate_center(rectangle.height(), height, edge_position),
+        ),
+        Position::Any(horizontal_edge, vertical_edge) => (
+            rectangle.left() as u32 + calculate_center(rectangle.width(), width, horizontal_edge),
+            rectangle.top() as u32 + calculate_center(rectangle.height(), height, vertical_edge),
+        ),
+    }
+}
+
+/// An arrangement of glyphs which can be drawn onto an image.
+/// This string also knows about its size, according to scaling and font properties.
+///
+/// The text may contain newlines, in which case each line starts at the left edge of the string and
+/// lies one [`line height`](#method.line_height) below the previous line.
+pub struct GlyphString<'a> {
+    lines: Vec<Vec<PositionedGlyph<'a>>>,
+    v_metrics: VMetrics,
+    line_spacing: Option<LineSpacing>,
+    font: &'a Font<'a>,
+    scale: Scale,
+    text: &'a str,
+    vertical_metric: VerticalMetric,
+    overflow: Option<Overflow>,
+}
+
+/// Which part of a line of text is placed inside a rectangle when positioning a [`GlyphString`]
+/// vertically, set by [`GlyphString::with_vertical_metric`].
+///
+/// [`GlyphString`]: struct.GlyphString.html
+/// [`GlyphString::with_vertical_metric`]: struct.GlyphString.html#method.with_vertical_metric
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum VerticalMetric {
+    /// The whole [`height`](struct.GlyphString.html#method.height) of the string, including space for
+    /// ascenders and descenders.
+    #[default]
+    LineHeight,
+    /// From the top of capital letters on the first line to the baseline of the last line, so that e.g.
+    /// an all-caps button label looks centered.
+    CapHeight,
+    /// From the top of lowercase letters such as 'x' on the first line to the baseline of the last line.
+    XHeight,
+}
+
+impl<'a> GlyphString<'a> {
+    /// Construct a `GlyphString` from `text` scaled by `scale` using the Font `font`.
+    ///
+    /// For each negative component of `scale` the string is mirrored within the box given by its
+    /// [`width`](#method.width) and [`height`](#method.height) when drawn.
+    pub fn new(scale: Scale, font: &'a Font<'a>, text: &'a str) -> Self {
+        let v_metrics = font.v_metrics(magnitude(scale));
+        let offset = point(0.0, v_metrics.ascent);
+
+        // Every line is laid out on the same baseline, and offset by its line index when drawn
+        let lines = text
+            .lines()
+            .map(|line| font.layout(line, magnitude(scale), offset).collect())
+            .collect();
+
+        Self {
+            lines,
+            v_metrics,
+            line_spacing: None,
+            font,
+            scale,
+            text,
+            vertical_metric: VerticalMetric::LineHeight,
+            overflow: None,
+        }
+    }
+
+    /// A `GlyphString` of `text` at `scale`, with the same font and settings as this one.
+    fn relaid<'c>(&self, scale: Scale, text: &'c str) -> GlyphString<'c>
+    where
+        'a: 'c,
+    {
+        GlyphString {
+            line_spacing: self.line_spacing,
+            vertical_metric: self.vertical_metric,
+            overflow: self.overflow,
+            ..GlyphString::new(scale, self.font, text)
+        }
+    }
+
+    /// Use `metric` to determine which part of this `GlyphString` is placed inside the rectangle when
+    /// drawing it with [`draw_positioned_mut`](#method.draw_positioned_mut), instead of its whole height.
+    ///
+    /// The heights of capital and lowercase letters are measured from the glyphs for 'H' and 'x'.
+    pub fn with_vertical_metric(mut self, metric: VerticalMetric) -> Self {
+        self.vertical_metric = metric;
+        self
+    }
+
+    /// The distance from the top of this `GlyphString` to the top of the part of it which is positioned,
+    /// and the height of that part, as chosen by its vertical metric.
+    fn positioned_extent(&self) -> (u32, u32) {
+        let top = match self.vertical_metric {
+            VerticalMetric::LineHeight => return (0, self.height()),
+            VerticalMetric::CapHeight => cap_height(magnitude(self.scale), self.font),
+            VerticalMetric::XHeight => x_height(magnitude(self.scale), self.font),
+        };
+        if self.lines.is_empty() {
+            return (0, 0);
+        }
+        let offset = (self.v_metrics.ascent - top).round().max(0.0) as u32;
+        let last_baseline = self.v_metrics.ascent.round() as u32
+            + (self.lines.len() as u32 - 1) * self.line_height();
+        let extent = last_baseline.saturating_sub(offset);
+
+        // Upside down text is mirrored within the string, and so is the part of it which is positioned
+        match self.scale.y < 0.0 {
+            true => (self.height().saturating_sub(offset + extent), extent),
+            false => (offset, extent),
+        }
+    }
+
+    /// Handle text which is too big for the rectangle given to [`draw_positioned_mut`](#method.draw_positioned_mut)
+    /// as set by `overflow`, and do not draw any pixels outside the rectangle. Without this the whole string
+    /// is drawn wherever it is placed, even if it extends beyond the rectangle.
+    ///
+    /// The text is shrunk, wrapped or shortened before it is positioned, keeping the line spacing and
+    /// vertical metric of this `GlyphString`, so it is placed and drawn using the same line heights.
+    pub fn with_overflow(mut self, overflow: Overflow) -> Self {
+        self.overflow = Some(overflow);
+        self
+    }
+
+    /// Use `line_spacing` to determine the [`line_height`](#method.line_height) of this `GlyphString`,
+    /// instead of the default. `LineSpacing::Multiplier(1.0)` gives the font-native leading, which includes
+    /// the font's line gap, and other values give custom leading.
+    pub fn with_line_spacing(mut self, line_spacing: LineSpacing) -> Self {
+        self.line_spacing = Some(line_spacing);
+        self
+    }
+
+    /// Find out how much horizontal space this `GlyphString` needs when drawn.
+    ///
+    /// This is the distance from the start of the text to the end of the advance of the last glyph of its
+    /// longest line, including kerning, rounded up to a whole number of pixels. No extra padding is added, so
+    /// glyphs whose ink extends beyond their advance (e.g. the tail of an italic 'f') may overhang slightly.
+    pub fn width(&self) -> u32 {
+        self.lines
+            .iter()
+            .filter_map(|line| line.last())
+            .map(|glyph| {
+                let end = glyph.position().x + glyph.unpositioned().h_metrics().advance_width;
+                end.ceil().max(0.0) as u32
+            })
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// The vertical distance between the tops of consecutive lines of this `GlyphString`.
+    ///
+    /// This is the line spacing set by [`with_line_spacing`](#method.with_line_spacing), or the font-native
+    /// leading `ascent - descent + line_gap` if none was set, as intended by the font's designer and as used
+    /// by [`draw_text_mut`](fn.draw_text_mut.html). It is rounded down to a whole number of pixels.
+    pub fn line_height(&self) -> u32 {
+        self.line_spacing
+            .unwrap_or_default()
+            .line_advance(&self.v_metrics) as u32
+    }
+
+    /// Find out how much vertical space this `GlyphString` needs when drawn.
+    ///
+    /// This is the [`line_height`](#method.line_height) times the number of lines, so for a single
+    /// line of text it is just the line height. An empty string has a height of zero.
+    pub fn height(&self) -> u32 {
+        self.lines.len() as u32 * self.line_height()
+    }
+
+    /// Draws this `GlyphString` onto the `canvas` at the given coordinates `x` and `y`.
+    /// For an out-of-place version use [`GlyphString::draw`](#method.draw).
+    /// Behaves identical to [`draw_text_mut`](fn.draw_text_mut), and like it can draw to any
+    /// [`Canvas`](trait.Canvas.html), e.g. a [`Blend`](struct.Blend.html).
+    pub fn draw_mut<C>(&self, canvas: &mut C, color: C::Pixel, x: u32, y: u32)
+    where
+        C: Canvas,
+        <C::Pixel as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
+    {
+        self.draw_colored_mut(canvas, &[color], x, y)
+    }
+
+    /// Draws this `GlyphString` onto the `canvas` at the given coordinates `x` and `y`,
+    /// using a separate color for each glyph.
+    ///
+    /// The `i`th glyph is drawn in `colors[i]`. There is one glyph per `char` of the text, including
+    /// whitespace but excluding newlines. If `colors` is shorter than the number of glyphs then the remaining
+    /// glyphs are all drawn in the last color, so passing a single color is equivalent to [`draw_mut`](#method.draw_mut).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `colors` is empty.
+    pub fn draw_colored_mut<C>(&self, canvas: &mut C, colors: &[C::Pixel], x: u32, y: u32)
+    where
+        C: Canvas,
+        <C::Pixel as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
+    {
+        self.draw_clipped_mut(canvas, colors, x, y, None)
+    }
+
+    /// Draws this `GlyphString` as [`draw_colored_mut`](#method.draw_colored_mut) does, only changing
+    /// pixels inside `clip` if it is given.
+    fn draw_clipped_mut<C>(
+        &self,
+        canvas: &mut C,
+        colors: &[C::Pixel],
+        x: u32,
+        y: u32,
+        clip: Option<&IpRect>,
+    ) where
+        C: Canvas,
+        <C::Pixel as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
+    {
+        assert!(!colors.is_empty(), "colors must not be empty");
+
+        let line_height = self.line_height();
+        let glyphs =
+            self.lines.iter().enumerate().flat_map(|(line, glyphs)| {
+                glyphs.iter().map(move |g| (line as u32 * line_height, g))
+            });
+        let (w, h) = (self.width() as i32, self.height() as i32);
+        let (flip_x, flip_y) = (self.scale.x < 0.0, self.scale.y < 0.0);
+
+        for (i, (line_y, g)) in glyphs.enumerate() {
+            let color = colors[i.min(colors.len() - 1)];
+            if let Some(bb) = g.pixel_bounding_box() {
+                g.draw(|gx, gy, gv| {
+                    let sx = gx as i32 + bb.min.x;
+                    let sy = gy as i32 + bb.min.y + line_y as i32;
+                    let sx = if flip_x { w - 1 - sx } else { sx };
+                    let sy = if flip_y { h - 1 - sy } else { sy };
+                    let (canvas_x, canvas_y) = (sx + x as i32, sy + y as i32);
+                    if clip.is_none_or(|clip| clip.contains(canvas_x, canvas_y)) {
+                        blend_coverage(canvas, canvas_x, canvas_y, color, gv);
+                    }
+                })
+            }
+        }
+    }
+
+    /// Draws this `GlyphString` onto a copy of `image` at the given coordinates `x` and `y` and return the copy.
+    /// The copy has the same dimensions as `image`.
     /// For an in-place version use [`GlyphString::draw_mut`](#method.draw_mut).
     /// Behaves identical to [`draw_text`](fn.draw_text.html).
     pub fn draw<I>(&self, image: &I, color: I::Pixel, x: u32, y: u32) -> Image<I::Pixel>
@@ -230,271 +5964,5040 @@ impl<'a> GlyphString<'a> {
         <I::Pixel as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
         I::Pixel: 'static,
     {
-        let mut out = ImageBuffer::new(image.width(), image.height());
-        out.copy_from(image, 0, 0);
-        self.draw_mut(&mut out, color, x, y);
-        out
+        let mut out = ImageBuffer::new(image.width(), image.height());
+        out.copy_from(image, 0, 0).unwrap();
+        self.draw_mut(&mut out, color, x, y);
+        out
+    }
+
+    /// Draws this `GlyphString` onto the `canvas` inside a `rectangle` at a `position`.
+    /// For an out-of-place version use [`GlyphString::draw_positioned`](#method.draw_positioned).
+    ///
+    /// ##Example: drawing some text to the center and top-left corner of an image
+    /// ```no_run
+    /// use imageproc::drawing::{EdgePosition, GlyphString, Position};
+    /// use imageproc::rect::Rect;
+    /// use image::{ImageBuffer, Rgb};
+    /// use rusttype::Scale;
+    ///
+    /// let text = "Hello World";
+    /// let scale = Scale::uniform(12.0);
+    /// let font = unimplemented!(); // load your font here
+    /// let mut image = ImageBuffer::from_pixel(100, 100, Rgb([0u8, 0u8, 0u8]));
+    /// let rect = Rect::at(0, 0).of_size(image.width(), image.height());
+    ///
+    /// let position = Position::HorizontalCenter(50.0.into());
+    /// GlyphString::new(scale, &font, &text)
+    ///     .draw_positioned_mut(&mut image, Rgb([0u8, 0u8, 255u8]), &position, &rect);
+    ///
+    /// let position = Position::HorizontalTop(0.0.into());
+    /// GlyphString::new(scale, &font, &text)
+    ///     .draw_positioned_mut(&mut image, Rgb([0u8, 255u8, 0u8]), &position, &rect);
+    /// ```
+    ///
+    /// The text area is the whole [`width`](#method.width) and [`height`](#method.height) of the string,
+    /// unless a different [`VerticalMetric`](enum.VerticalMetric.html) was chosen with
+    /// [`with_vertical_metric`](#method.with_vertical_metric). Text which does not fit in `rectangle` is
+    /// handled as set by [`with_overflow`](#method.with_overflow).
+    ///
+    /// What we are doing here:
+    ///
+    /// 1. Find an x, y such that
+    ///     1.1 (x, y) lies on the edge of `position` and inside of `rectangle`
+    ///     1.2 (x, y) should be the top-left corner of the text area (the space consumed by the glyphs)
+    ///     1.3 the text area should divide the rectangle according to the given `position`
+    ///     1.4 there should be an equal padding in all directions (from the edges of the text area to the edges of rectangle)
+    /// 2. Draw the text to x, y
+    ///
+    /// <pre>
+    ///     +-----------------------------------------+  = `image` bounds
+    ///     |                                         |
+    ///     |     +-----------------------------+     |  = `rectangle` bounds
+    ///     |     |                             |     |
+    ///     |     |     +-----------------+     |     |  = text area <- y
+    ///     |     |     |Some example text|     |     |
+    ///     |     |     +-----------------+     |     |
+    ///     |     |                             |     |
+    ///     |     +-----------------------------+     |
+    ///     |                                         |
+    ///     +-----------------------------------------+
+    ///
+    ///                 ^
+    ///                 |
+    ///                 x
+    /// </pre>
+    pub fn draw_positioned_mut<'b, C>(
+        &self,
+        canvas: &'b mut C,
+        color: C::Pixel,
+        position: &Position,
+        rectangle: &IpRect,
+    ) where
+        C: Canvas,
+        <C::Pixel as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
+    {
+        let overflow = match self.overflow {
+            Some(overflow) => overflow,
+            None => return self.draw_within_mut(canvas, color, position, rectangle, None),
+        };
+
+        let fits = |string: &GlyphString| {
+            string.width() <= rectangle.width() && string.height() <= rectangle.height()
+        };
+        let (scale, text) = match overflow {
+            Overflow::Clip => (self.scale, self.text.to_string()),
+            Overflow::Shrink if fits(self) => (self.scale, self.text.to_string()),
+            Overflow::Shrink => {
+                let fitted = largest_fitting_scale(self.scale.y.abs(), |scale| {
+                    fits(&self.relaid(scale, self.text))
+                });
+                let scale = Scale {
+                    x: fitted.x.copysign(self.scale.x),
+                    y: fitted.y.copysign(self.scale.y),
+                };
+                (scale, self.text.to_string())
+            }
+            Overflow::Wrap => (
+                self.scale,
+                wrap_text(self.scale, self.font, self.text, rectangle.width()).join("\n"),
+            ),
+            Overflow::Ellipsis => (
+                self.scale,
+                self.text
+                    .lines()
+                    .map(|line| {
+                        truncate_with_ellipsis(self.scale, self.font, line, rectangle.width())
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            ),
+        };
+        if scale.x == 0.0 || scale.y == 0.0 {
+            return;
+        }
+
+        let string = self.relaid(scale, &text);
+        string.draw_within_mut(canvas, color, position, rectangle, Some(rectangle));
+    }
+
+    /// Places this `GlyphString` inside `rectangle` at `position` and draws it, only changing pixels
+    /// inside `clip` if it is given.
+    fn draw_within_mut<C>(
+        &self,
+        canvas: &mut C,
+        color: C::Pixel,
+        position: &Position,
+        rectangle: &IpRect,
+        clip: Option<&IpRect>,
+    ) where
+        C: Canvas,
+        <C::Pixel as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
+    {
+        let width = self.width();
+        let (offset, height) = self.positioned_extent();
+        let (x, y) = find_text_area_coordinates(position, rectangle, width, height);
+
+        self.draw_clipped_mut(canvas, &[color], x, y.saturating_sub(offset), clip)
+    }
+
+    /// Draws this `GlyphString` onto a copy of `image` inside a `rectangle` at a `position` and return the copy.
+    /// The copy has the same dimensions as `image`.
+    /// For an in-place version use [`GlyphString::draw_positioned_mut`](#method.draw_positioned_mut).
+    pub fn draw_positioned<I>(
+        &self,
+        image: &I,
+        color: I::Pixel,
+        position: &Position,
+        rectangle: &IpRect,
+    ) -> Image<I::Pixel>
+    where
+        I: GenericImage,
+        <I::Pixel as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
+        I::Pixel: 'static,
+    {
+        let mut out = ImageBuffer::new(image.width(), image.height());
+        out.copy_from(image, 0, 0).unwrap();
+        self.draw_positioned_mut(&mut out, color, position, rectangle);
+        out
+    }
+}
+
+/// The relative position of a point on an edge.
+///
+/// +--------------------------------+
+/// 0%       something else          100%
+///
+/// Edge in this context does not exclusively refer to the edges
+/// which are boundaries of a rectangle but rather all lines which are parallel to two opposing boundaries.
+///
+/// <pre>
+///    +---------------+  <- "typical" horizontal edge
+///    |               |
+///    +---------------+  <- "typical" horizontal edge
+///    ^               ^
+///    |               |
+/// "typical" vertical edges
+///
+///   +---------------+
+///   |               |
+///   |---------------| <- parallel to horizontal edges (= also an edge)
+///   |               |
+///   +---------------+
+/// </pre>
+pub struct EdgePosition(pub f32);
+
+impl EdgePosition {
+    /// The left-most point of a horizontal edge.
+    /// Equivalent to `EdgePosition(0.into())`.
+    pub fn left() -> Self {
+        0.into()
+    }
+    /// The top-most point of a vertical edge.
+    /// Equivalent to `EdgePosition(0.into())`.
+    pub fn top() -> Self {
+        0.into()
+    }
+
+    /// The center point of a horizontal or vertical edge.
+    /// Equivalent to `EdgePosition(50.into())`.
+    pub fn center() -> Self {
+        50.into()
+    }
+
+    /// The right-most point of a horizontal edge.
+    /// Equivalent to `EdgePosition(100.into())`.
+    pub fn right() -> Self {
+        100.into()
+    }
+    /// The bottom-most point of a vertical edge.
+    /// Equivalent to `EdgePosition(100.into())`.
+    pub fn bottom() -> Self {
+        100.into()
+    }
+
+    /// An edge position `percent`% of the way along an edge, or an error if `percent`
+    /// does not lie between 0 and 100.
+    ///
+    /// Converting an `f32` or `u32` using `into()` instead clamps out of range values.
+    pub fn try_from(percent: f32) -> Result<Self, InvalidEdgePosition> {
+        if (0.0..=100.0).contains(&percent) {
+            Ok(Self(percent))
+        } else {
+            Err(InvalidEdgePosition(percent))
+        }
+    }
+}
+
+/// Clamps `percent` to lie between 0 and 100, mapping NaN to 0.
+fn clamp_percent(percent: f32) -> f32 {
+    if percent.is_nan() {
+        0.0
+    } else {
+        percent.clamp(0.0, 100.0)
+    }
+}
+
+/// Converts a percentage to an edge position, clamping it to lie between 0 and 100.
+impl From<f32> for EdgePosition {
+    fn from(from: f32) -> Self {
+        Self(clamp_percent(from))
+    }
+}
+
+/// Converts a percentage to an edge position, clamping it to be at most 100.
+impl From<u32> for EdgePosition {
+    fn from(from: u32) -> Self {
+        Self(clamp_percent(from as _))
+    }
+}
+
+/// The error returned by [`EdgePosition::try_from`](struct.EdgePosition.html#method.try_from) for a
+/// percentage outside the range 0 to 100.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct InvalidEdgePosition(pub f32);
+
+impl std::fmt::Display for InvalidEdgePosition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "edge position {}% does not lie between 0% and 100%",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for InvalidEdgePosition {}
+
+/// A position inside a rectangle
+///
+/// Text is positioned using its advance-based size, as given by [`GlyphString::width`] and
+/// [`GlyphString::height`], rather than the pixel bounds of the glyphs drawn. This means that strings
+/// with the same characters are placed identically whatever their shapes, but the ink of the text
+/// may not be exactly centered. See [`logical_bounds`] and [`ink_bounds`] for the two measurements.
+///
+/// [`GlyphString::width`]: struct.GlyphString.html#method.width
+/// [`GlyphString::height`]: struct.GlyphString.html#method.height
+/// [`logical_bounds`]: fn.logical_bounds.html
+/// [`ink_bounds`]: fn.ink_bounds.html
+pub enum Position {
+    /// top edge
+    /// <pre>
+    /// +---------------+  <- this
+    /// |               |
+    /// +---------------+
+    /// </pre>
+    HorizontalTop(EdgePosition),
+    /// horizontal center edge (horizontally centered between top and bottom)
+    /// <pre>
+    /// +---------------+
+    /// |               |
+    /// |---------------| <- this
+    /// |               |
+    /// +---------------+
+    /// </pre>
+    HorizontalCenter(EdgePosition),
+    /// bottom edge
+    /// <pre>
+    /// +---------------+
+    /// |               |
+    /// +---------------+  <- this
+    /// </pre>
+    HorizontalBottom(EdgePosition),
+    /// left edge
+    /// <pre>
+    /// +---------------+
+    /// |               |
+    /// +---------------+
+    /// ^
+    /// |
+    /// this
+    /// </pre>
+    VerticalLeft(EdgePosition),
+    /// Vertical center edge (vertically centered between left and right)
+    /// <pre>
+    /// +---------------+
+    /// |       |       |
+    /// |       |       |
+    /// |       |       |
+    /// +---------------+
+    ///         ^
+    ///         |
+    ///         this
+    /// </pre>
+    VerticalCenter(EdgePosition),
+    /// left edge
+    /// <pre>
+    /// +---------------+
+    /// |               |
+    /// +---------------+
+    ///                 ^
+    ///                 |
+    ///                 this
+    /// </pre>
+    VerticalRight(EdgePosition),
+    /// fine-grained control over horizontal and vertical edges
+    Any(EdgePosition, EdgePosition),
+}
+
+/// The direction in which the members of an [`ArrangedGlyphStrings`](struct.ArrangedGlyphStrings.html) are arranged.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Orientation {
+    /// Each string is placed to the right of the previous one.
+    #[default]
+    Horizontal,
+    /// Each string is placed below the previous one, with all strings starting at the same x coordinate.
+    Vertical,
+}
+
+/// An arrangement of PositionedGlyphStrings
+pub struct GlyphStrings<'a>(pub &'a [&'a GlyphString<'a>]);
+
+impl<'a> GlyphStrings<'a> {
+    /// create
+    #[inline]
+    pub fn new(glyph_strings: &'a [&GlyphString<'a>]) -> Self {
+        Self(glyph_strings)
+    }
+
+    /// Arrange the strings according to `orientation` rather than horizontally.
+    pub fn with_orientation(&self, orientation: Orientation) -> ArrangedGlyphStrings<'a> {
+        self.arranged().with_orientation(orientation)
+    }
+
+    /// If `align` is true, place horizontally arranged strings so that they share a common baseline,
+    /// rather than aligning their top edges. See
+    /// [`ArrangedGlyphStrings::with_baseline_alignment`](struct.ArrangedGlyphStrings.html#method.with_baseline_alignment).
+    pub fn with_baseline_alignment(&self, align: bool) -> ArrangedGlyphStrings<'a> {
+        self.arranged().with_baseline_alignment(align)
+    }
+
+    /// The default arrangement of the strings, placed horizontally with their top edges aligned.
+    fn arranged(&self) -> ArrangedGlyphStrings<'a> {
+        ArrangedGlyphStrings {
+            strings: self.0,
+            orientation: Orientation::default(),
+            align_baselines: false,
+        }
+    }
+
+    /// draw text
+    ///
+    /// The `i`th string is drawn in `colors[i]`. If `colors` is shorter than the number of strings then the
+    /// remaining strings are all drawn in the last color, so passing a single color draws every string in it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `colors` is empty.
+    #[inline]
+    pub fn draw_positioned_mut<'b, C>(
+        &self,
+        canvas: &'b mut C,
+        colors: &[C::Pixel],
+        position: &Position,
+        rectangle: &IpRect,
+    ) where
+        C: Canvas,
+        <C::Pixel as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
+    {
+        self.arranged()
+            .draw_positioned_mut(canvas, colors, position, rectangle)
+    }
+
+    /// Find out how much vertical space this `GlyphStrings` needs when drawn.
+    ///
+    /// This is the largest [`height`](struct.GlyphString.html#method.height) of its members, respecting any
+    /// line spacing set on them.
+    pub fn height(&self) -> u32 {
+        self.arranged().height()
+    }
+
+    /// Find out how much horizontal space this `GlyphStrings` needs when drawn.
+    pub fn width(&self) -> u32 {
+        self.arranged().width()
+    }
+}
+
+/// A [`GlyphStrings`](struct.GlyphStrings.html) together with how its members are arranged, created by
+/// [`GlyphStrings::with_orientation`](struct.GlyphStrings.html#method.with_orientation) or
+/// [`GlyphStrings::with_baseline_alignment`](struct.GlyphStrings.html#method.with_baseline_alignment).
+pub struct ArrangedGlyphStrings<'a> {
+    strings: &'a [&'a GlyphString<'a>],
+    orientation: Orientation,
+    align_baselines: bool,
+}
+
+impl<'a> ArrangedGlyphStrings<'a> {
+    /// Arrange the strings according to `orientation`.
+    pub fn with_orientation(mut self, orientation: Orientation) -> Self {
+        self.orientation = orientation;
+        self
+    }
+
+    /// If `align` is true, place horizontally arranged strings so that they share a common baseline,
+    /// rather than aligning their top edges. This is useful for mixing scales on one line, e.g. a
+    /// smaller subscript or currency symbol next to larger text.
+    ///
+    /// The common baseline lies at the largest ascent of any member below the top of the arrangement,
+    /// and each member is moved down by the difference between this and its own ascent. Strings arranged
+    /// vertically are not affected.
+    pub fn with_baseline_alignment(mut self, align: bool) -> Self {
+        self.align_baselines = align;
+        self
+    }
+
+    /// The vertical offset of each member from the top of the arrangement, when drawn horizontally.
+    fn baseline_offsets(&self) -> impl Iterator<Item = u32> + '_ {
+        let ascent = self
+            .strings
+            .iter()
+            .map(|string| string.v_metrics.ascent)
+            .fold(0.0, f32::max);
+        let align = self.align_baselines;
+        self.strings.iter().map(move |string| {
+            if align {
+                (ascent - string.v_metrics.ascent).round() as u32
+            } else {
+                0
+            }
+        })
+    }
+
+    /// Draws the strings in this arrangement, placed within `rectangle` as given by `position`. Colors are
+    /// used as by [`GlyphStrings::draw_positioned_mut`](struct.GlyphStrings.html#method.draw_positioned_mut).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `colors` is empty.
+    pub fn draw_positioned_mut<C>(
+        &self,
+        canvas: &mut C,
+        colors: &[C::Pixel],
+        position: &Position,
+        rectangle: &IpRect,
+    ) where
+        C: Canvas,
+        <C::Pixel as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
+    {
+        assert!(!colors.is_empty(), "colors must not be empty");
+
+        let width = self.width();
+        let height = self.height();
+        let (mut x, mut y) = find_text_area_coordinates(position, rectangle, width, height);
+
+        let offsets = self.baseline_offsets();
+        for (i, (string, offset)) in self.strings.iter().zip(offsets).enumerate() {
+            let color = colors[i.min(colors.len() - 1)];
+            match self.orientation {
+                Orientation::Horizontal => {
+                    string.draw_mut(canvas, color, x as _, (y + offset) as _);
+                    x += string.width();
+                }
+                Orientation::Vertical => {
+                    string.draw_mut(canvas, color, x as _, y as _);
+                    y += string.height();
+                }
+            }
+        }
+    }
+
+    /// Find out how much vertical space this arrangement needs when drawn.
+    ///
+    /// For a horizontal arrangement this is the largest [`height`](struct.GlyphString.html#method.height)
+    /// of its members, and for a vertical arrangement it is the sum of their heights. In both cases
+    /// it respects any line spacing set on the members. When baselines are aligned, the height of each
+    /// horizontally arranged member includes its offset from the top.
+    pub fn height(&self) -> u32 {
+        let heights = self.strings.iter().map(|string| string.height());
+        match self.orientation {
+            Orientation::Horizontal => heights
+                .zip(self.baseline_offsets())
+                .map(|(height, offset)| height + offset)
+                .max()
+                .unwrap_or(0),
+            Orientation::Vertical => heights.sum(),
+        }
+    }
+
+    /// Find out how much horizontal space this arrangement needs when drawn.
+    ///
+    /// For a horizontal arrangement this is the sum of the widths of its members, and for
+    /// a vertical arrangement it is the largest of their widths.
+    pub fn width(&self) -> u32 {
+        let widths = self.strings.iter().map(|string| string.width());
+        match self.orientation {
+            Orientation::Horizontal => widths.sum(),
+            Orientation::Vertical => widths.max().unwrap_or(0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test::{black_box, Bencher};
+
+    fn font() -> Font<'static> {
+        Font::try_from_bytes(include_bytes!("../../examples/DejaVuSans.ttf")).unwrap()
+    }
+
+    #[test]
+    fn test_text_size_multiple_lines() {
+        let font = font();
+        let scale = Scale::uniform(20.0);
+        let (w1, h1) = text_size(scale, &font, "Hello");
+        let (w2, h2) = text_size(scale, &font, "Hello\nHello");
+
+        let advance = line_advance(&font.v_metrics(scale));
+        assert_eq!(w1, w2);
+        assert_eq!(h2, h1 + advance.round() as i32);
+    }
+
+    #[test]
+    fn test_text_size_ignores_trailing_newline() {
+        let font = font();
+        let scale = Scale::uniform(20.0);
+        assert_eq!(
+            text_size(scale, &font, "Hello\nWorld"),
+            text_size(scale, &font, "Hello\nWorld\n")
+        );
+    }
+
+    #[test]
+    fn test_draw_text_mut_newline_starts_at_x() {
+        let font = font();
+        let scale = Scale::uniform(20.0);
+        let mut single = GrayImage::new(100, 100);
+        let mut multi = GrayImage::new(100, 100);
+        draw_text_mut(&mut single, Luma([255u8]), 10, 0, scale, &font, "l");
+        draw_text_mut(&mut multi, Luma([255u8]), 10, 0, scale, &font, "\nl");
+
+        let left_most = |image: &GrayImage| {
+            image
+                .enumerate_pixels()
+                .filter(|(_, _, p)| p[0] > 0)
+                .map(|(x, _, _)| x)
+                .min()
+        };
+        assert!(left_most(&single).is_some());
+        assert_eq!(left_most(&single), left_most(&multi));
+    }
+
+    #[test]
+    fn test_draw_text_bounded_mut_matches_drawn_pixels() {
+        let font = font();
+        let scale = Scale::uniform(20.0);
+        let mut image = GrayImage::new(100, 100);
+        let rect = draw_text_bounded_mut(&mut image, Luma([255u8]), 5, 5, scale, &font, "Hi")
+            .expect("text is inside the canvas");
+
+        for (x, y, p) in image.enumerate_pixels() {
+            if p[0] > 0 {
+                assert!(rect.contains(x as i32, y as i32));
+            }
+        }
+        let (w, h) = text_size(scale, &font, "Hi");
+        assert!(rect.right() < 5 + w && rect.bottom() < 5 + h);
+    }
+
+    #[test]
+    fn test_draw_text_bounded_mut_clamps_to_canvas() {
+        let font = font();
+        let scale = Scale::uniform(20.0);
+        let mut image = GrayImage::new(10, 10);
+        let rect =
+            draw_text_bounded_mut(&mut image, Luma([255u8]), -3, -3, scale, &font, "Hi").unwrap();
+        assert_eq!((rect.left(), rect.top()), (0, 0));
+        assert!(rect.right() < 10 && rect.bottom() < 10);
+    }
+
+    fn ink_columns(image: &GrayImage, rows: std::ops::Range<u32>) -> Option<(u32, u32)> {
+        let xs = image
+            .enumerate_pixels()
+            .filter(|(_, y, p)| rows.contains(y) && p[0] > 0)
+            .map(|(x, _, _)| x);
+        let (min, max) = xs.fold((u32::MAX, 0), |(lo, hi), x| (lo.min(x), hi.max(x)));
+        if min == u32::MAX {
+            None
+        } else {
+            Some((min, max))
+        }
+    }
+
+    #[test]
+    fn test_draw_text_aligned_mut() {
+        let font = font();
+        let scale = Scale::uniform(20.0);
+        let advance = line_advance(&font.v_metrics(scale)) as u32;
+        let text = "wide line\nab  ";
+
+        for &align in &[
+            HorizontalAlign::Left,
+            HorizontalAlign::Center,
+            HorizontalAlign::Right,
+        ] {
+            let mut image = GrayImage::new(200, 100);
+            draw_text_aligned_mut(
+                &mut image,
+                Luma([255u8]),
+                0,
+                0,
+                align,
+                LineSpacing::default(),
+                scale,
+                &font,
+                text,
+            );
+
+            let (first_left, first_right) = ink_columns(&image, 0..advance).unwrap();
+            let (second_left, second_right) = ink_columns(&image, advance..100).unwrap();
+            match align {
+                HorizontalAlign::Left => {
+                    assert!(second_left.max(first_left) - second_left.min(first_left) <= 1)
+                }
+                HorizontalAlign::Right => {
+                    assert!(second_right.max(first_right) - second_right.min(first_right) <= 1)
+                }
+                HorizontalAlign::Center => {
+                    let first_mid = (first_left + first_right) as i32;
+                    let second_mid = (second_left + second_right) as i32;
+                    assert!((first_mid - second_mid).abs() <= 3);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_multiline_text_size_line_spacing() {
+        let font = font();
+        let scale = Scale::uniform(20.0);
+        let text = "a\nb";
+        assert_eq!(
+            multiline_text_size(scale, &font, text, LineSpacing::default()),
+            text_size(scale, &font, text)
+        );
+
+        let (_, tight) = multiline_text_size(scale, &font, text, LineSpacing::Multiplier(0.5));
+        let (_, loose) = multiline_text_size(scale, &font, text, LineSpacing::Multiplier(2.0));
+        let (_, fixed) = multiline_text_size(scale, &font, text, LineSpacing::Pixels(100.0));
+        let (_, single) = text_size(scale, &font, "b");
+        assert!(tight < loose);
+        assert!(fixed > 100 && fixed <= 100 + single);
+    }
+
+    #[test]
+    fn test_glyph_string_height_line_spacing() {
+        let font = font();
+        let scale = Scale::uniform(20.0);
+        let v_metrics = font.v_metrics(scale);
+        let native = line_advance(&v_metrics) as u32;
+
+        assert_eq!(GlyphString::new(scale, &font, "a").height(), native);
+        let spaced =
+            GlyphString::new(scale, &font, "a").with_line_spacing(LineSpacing::Pixels(40.0));
+        assert_eq!(spaced.height(), 40);
+    }
+
+    /// A copy of the test font whose line gap in its `hhea` table is `line_gap` font units.
+    fn font_data_with_line_gap(line_gap: i16) -> Vec<u8> {
+        let mut data = include_bytes!("../../examples/DejaVuSans.ttf").to_vec();
+        let read_u16 =
+            |data: &[u8], at: usize| u16::from_be_bytes([data[at], data[at + 1]]) as usize;
+        let tables = read_u16(&data, 4);
+        let hhea = (0..tables)
+            .map(|i| 12 + 16 * i)
+            .find(|&record| &data[record..record + 4] == b"hhea")
+            .map(|record| (read_u16(&data, record + 8) << 16) | read_u16(&data, record + 10))
+            .unwrap();
+        data[hhea + 8..hhea + 10].copy_from_slice(&line_gap.to_be_bytes());
+        data
+    }
+
+    /// A minimal variable TrueType font with a weight axis from 100 to 800, whose default is 400, and a
+    /// single glyph for 'I'. The glyph is a stem 100 font units wide with an advance of 300 at the default
+    /// weight, 50 wide with an advance of 250 at the lightest weight and 250 wide with an advance of 450 at
+    /// the heaviest, with outlines varied by `gvar` and advances by `HVAR`.
+    fn variable_font_data() -> Vec<u8> {
+        fn bytes(values: &[i32], width: usize) -> Vec<u8> {
+            let mut out = vec![];
+            for &v in values {
+                out.extend_from_slice(&v.to_be_bytes()[4 - width..]);
+            }
+            out
+        }
+        let u16s = |values: &[i32]| bytes(values, 2);
+        let u32s = |values: &[i32]| bytes(values, 4);
+        let (thin, bold) = (-50, 150);
+
+        let mut head = u32s(&[0x10000, 0x10000, 0, 0x5F0F3CF5]);
+        head.extend(u16s(&[
+            0, 1000, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 400, 700, 0, 8, 2, 1, 0,
+        ]));
+        let mut hhea = u32s(&[0x10000]);
+        hhea.extend(u16s(&[
+            800, -200, 0, 450, 0, 0, 450, 1, 0, 0, 0, 0, 0, 0, 0, 2,
+        ]));
+        let maxp = [u32s(&[0x5000]), u16s(&[2])].concat();
+        let hmtx = u16s(&[500, 0, 300, 100]);
+        let mut cmap = u16s(&[0, 1, 0, 4]);
+        cmap.extend(u32s(&[12]));
+        cmap.extend(u16s(&[12, 0]));
+        cmap.extend(u32s(&[28, 0, 1, 'I' as i32, 'I' as i32, 1]));
+
+        // A clockwise rectangle of four on-curve points, with coordinates stored as deltas
+        let mut glyf = u16s(&[1, 100, 0, 200, 700, 3, 0]);
+        glyf.extend_from_slice(&[1, 1, 1, 1]);
+        glyf.extend(u16s(&[100, 0, 100, 0, 0, 700, 0, -700]));
+        glyf.resize(36, 0);
+        let loca = u32s(&[0, 0, 36]);
+
+        let mut fvar = u16s(&[1, 0, 16, 2, 1, 20, 0, 8]);
+        fvar.extend_from_slice(b"wght");
+        fvar.extend(u32s(&[100 << 16, 400 << 16, 800 << 16]));
+        fvar.extend(u16s(&[0, 256]));
+
+        // Two tuples peaking at the ends of the axis, each moving the right edge of the stem and the
+        // advance phantom point, with deltas for all four points and four phantom points
+        let tuple = |delta: i32| {
+            let mut data = vec![0x47];
+            data.extend(u16s(&[0, 0, delta, delta, 0, delta, 0, 0]));
+            data.push(0x87);
+            data
+        };
+        let (light, heavy) = (tuple(thin), tuple(bold));
+        let mut variations = u16s(&[2, 16, light.len() as i32, 0x8000, -0x4000]);
+        variations.extend(u16s(&[heavy.len() as i32, 0x8000, 0x4000]));
+        variations.extend(light);
+        variations.extend(heavy);
+        let mut gvar = u32s(&[0x10000]);
+        gvar.extend(u16s(&[1, 0]));
+        gvar.extend(u32s(&[32]));
+        gvar.extend(u16s(&[2, 1]));
+        gvar.extend(u32s(&[32, 0, 0, variations.len() as i32]));
+        gvar.extend(variations);
+
+        // One region for each end of the axis, and a delta for each region per glyph
+        let mut hvar = u32s(&[0x10000, 20, 0, 0, 0]);
+        hvar.extend(u16s(&[1]));
+        hvar.extend(u32s(&[12]));
+        hvar.extend(u16s(&[1]));
+        hvar.extend(u32s(&[28]));
+        hvar.extend(u16s(&[1, 2, -0x4000, -0x4000, 0, 0, 0x4000, 0x4000]));
+        hvar.extend(u16s(&[2, 2, 2, 0, 1, 0, 0, thin, bold]));
+
+        let mut tables: Vec<(&[u8; 4], Vec<u8>)> = vec![
+            (b"HVAR", hvar),
+            (b"cmap", cmap),
+            (b"fvar", fvar),
+            (b"glyf", glyf),
+            (b"gvar", gvar),
+            (b"head", head),
+            (b"hhea", hhea),
+            (b"hmtx", hmtx),
+            (b"loca", loca),
+            (b"maxp", maxp),
+        ];
+        let mut data = u32s(&[0x10000]);
+        data.extend(u16s(&[tables.len() as i32, 0, 0, 0]));
+        let mut offset = 12 + 16 * tables.len();
+        for (tag, table) in &mut tables {
+            data.extend_from_slice(&tag[..]);
+            data.extend(u32s(&[0, offset as i32, table.len() as i32]));
+            table.resize((table.len() + 3) / 4 * 4, 0);
+            offset += table.len();
+        }
+        for (_, table) in tables {
+            data.extend(table);
+        }
+        data
+    }
+
+    /// A copy of the test font with an `sbix` table holding a single strike of `ppem` pixels per em, in which
+    /// the glyph for `c` is the PNG encoded `image` with the bottom-left corner at `origin` from the glyph origin.
+    #[cfg(feature = "color-glyphs")]
+    fn font_data_with_color_glyph(
+        c: char,
+        image: &image::RgbaImage,
+        ppem: u16,
+        origin: (i16, i16),
+    ) -> Vec<u8> {
+        let data = include_bytes!("../../examples/DejaVuSans.ttf").to_vec();
+        let font = font();
+        let (id, glyphs) = (font.glyph(c).id().0 as usize, font.glyph_count());
+        let mut png = vec![];
+        image::DynamicImage::ImageRgba8(image.clone())
+            .write_to(&mut png, image::ImageOutputFormat::Png)
+            .unwrap();
+
+        // The header, with one strike whose glyph data offsets are all equal except for that of `c`
+        let mut sbix = vec![];
+        sbix.extend_from_slice(&1u16.to_be_bytes());
+        sbix.extend_from_slice(&1u16.to_be_bytes());
+        sbix.extend_from_slice(&1u32.to_be_bytes());
+        sbix.extend_from_slice(&12u32.to_be_bytes());
+        sbix.extend_from_slice(&ppem.to_be_bytes());
+        sbix.extend_from_slice(&72u16.to_be_bytes());
+        let start = 4 + 4 * (glyphs + 1);
+        for i in 0..=glyphs {
+            let offset = if i <= id {
+                start
+            } else {
+                start + 8 + png.len()
+            };
+            sbix.extend_from_slice(&(offset as u32).to_be_bytes());
+        }
+        sbix.extend_from_slice(&origin.0.to_be_bytes());
+        sbix.extend_from_slice(&origin.1.to_be_bytes());
+        sbix.extend_from_slice(b"png ");
+        sbix.extend_from_slice(&png);
+
+        // Add a record to the table directory, moving every table down to make room for it
+        let read_u32 =
+            |at: usize| u32::from_be_bytes([data[at], data[at + 1], data[at + 2], data[at + 3]]);
+        let tables = u16::from_be_bytes([data[4], data[5]]) as usize;
+        let directory_end = 12 + 16 * tables;
+        let padded_len = (data.len() + 16 + 3) / 4 * 4;
+        let mut records: Vec<([u8; 4], u32, u32, u32)> = (0..tables)
+            .map(|i| 12 + 16 * i)
+            .map(|r| {
+                let tag = [data[r], data[r + 1], data[r + 2], data[r + 3]];
+                (tag, read_u32(r + 4), read_u32(r + 8) + 16, read_u32(r + 12))
+            })
+            .collect();
+        records.push((*b"sbix", 0, padded_len as u32, sbix.len() as u32));
+        records.sort_unstable();
+
+        let mut font_data = data[..12].to_vec();
+        font_data[4..6].copy_from_slice(&(tables as u16 + 1).to_be_bytes());
+        for (tag, checksum, offset, length) in records {
+            font_data.extend_from_slice(&tag);
+            for value in &[checksum, offset, length] {
+                font_data.extend_from_slice(&value.to_be_bytes());
+            }
+        }
+        font_data.extend_from_slice(&data[directory_end..]);
+        font_data.resize(padded_len, 0);
+        font_data.extend_from_slice(&sbix);
+        font_data
+    }
+
+    #[test]
+    fn test_glyph_string_multiline_font_native_leading() {
+        let scale = Scale::uniform(20.0);
+        let (tight_data, loose_data) = (font_data_with_line_gap(0), font_data_with_line_gap(600));
+        let tight = Font::try_from_vec(tight_data).unwrap();
+        let loose = Font::try_from_vec(loose_data).unwrap();
+        let tight_metrics = tight.v_metrics(scale);
+        let loose_metrics = loose.v_metrics(scale);
+        assert_eq!(tight_metrics.line_gap, 0.0);
+        assert!(loose_metrics.line_gap > 5.0);
+
+        // Multiple lines follow each font's line gap
+        let tight_lines = GlyphString::new(scale, &tight, "a\nb");
+        let loose_lines = GlyphString::new(scale, &loose, "a\nb");
+        assert_eq!(
+            tight_lines.line_height(),
+            line_advance(&tight_metrics) as u32
+        );
+        assert_eq!(
+            loose_lines.line_height(),
+            line_advance(&loose_metrics) as u32
+        );
+        assert!(loose_lines.height() > tight_lines.height());
+
+        // Lines are spaced as by draw_text_mut, up to rounding
+        for font in &[&tight, &loose] {
+            let bottom = |image: &GrayImage| {
+                (0..80)
+                    .rev()
+                    .find(|&y| (0..40).any(|x| image.get_pixel(x, y)[0] > 0))
+                    .unwrap() as i32
+            };
+            let mut image = GrayImage::new(40, 80);
+            GlyphString::new(scale, font, "a\nb").draw_mut(&mut image, Luma([255u8]), 0, 0);
+            let mut expected = GrayImage::new(40, 80);
+            draw_text_mut(&mut expected, Luma([255u8]), 0, 0, scale, font, "a\nb");
+            assert!((bottom(&image) - bottom(&expected)).abs() <= 1);
+        }
+
+        // Custom leading overrides the font
+        let custom = LineSpacing::Pixels(30.0);
+        assert_eq!(tight_lines.with_line_spacing(custom).line_height(), 30);
+        assert_eq!(loose_lines.with_line_spacing(custom).line_height(), 30);
+    }
+
+    #[test]
+    fn test_layout_matches_font_layout() {
+        let font = font();
+        let scale = Scale::uniform(20.0);
+        let text = "AVTo wy";
+        let v_metrics = font.v_metrics(scale);
+
+        let expected: Vec<_> = font
+            .layout(text, scale, point(0.0, v_metrics.ascent))
+            .map(|g| g.position())
+            .collect();
+        let mut actual = vec![];
+        let origin = point(0.0, v_metrics.ascent);
+        layout_line(
+            scale,
+            &font,
+            text,
+            origin,
+            &LayoutOptions::default(),
+            |_, g| actual.push(g.position()),
+        );
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_spaced_text_size() {
+        let font = font();
+        let scale = Scale::uniform(20.0);
+        let (w, h) = text_size(scale, &font, "abc");
+
+        assert_eq!(spaced_text_size(scale, &font, "abc", 0.0), (w, h));
+        // Two gaps between three glyphs
+        assert_eq!(spaced_text_size(scale, &font, "abc", 5.0), (w + 10, h));
+        assert_eq!(spaced_text_size(scale, &font, "abc", -2.0), (w - 4, h));
+        // No gap after a single glyph
+        assert_eq!(
+            spaced_text_size(scale, &font, "a", 5.0),
+            text_size(scale, &font, "a")
+        );
+    }
+
+    #[test]
+    fn test_draw_text_rotated_mut_zero_angle() {
+        let font = font();
+        let scale = Scale::uniform(20.0);
+        let mut expected = GrayImage::new(60, 30);
+        draw_text_mut(&mut expected, Luma([255u8]), 3, 2, scale, &font, "Hi");
+        let mut actual = GrayImage::new(60, 30);
+        draw_text_rotated_mut(&mut actual, Luma([255u8]), 3, 2, 0.0, scale, &font, "Hi");
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_draw_text_rotated_mut_inside_bounds() {
+        let font = font();
+        let scale = Scale::uniform(20.0);
+        let angle = std::f32::consts::FRAC_PI_4;
+        let mut image = GrayImage::new(100, 100);
+        draw_text_rotated_mut(
+            &mut image,
+            Luma([255u8]),
+            40,
+            20,
+            angle,
+            scale,
+            &font,
+            "Hello",
+        );
+
+        let bounds = rotated_text_bounds(scale, &font, "Hello", angle).unwrap();
+        // Allow for the bilinear splat spilling into neighbouring pixels
+        let bounds = IpRect::at(bounds.left() + 39, bounds.top() + 19)
+            .of_size(bounds.width() + 2, bounds.height() + 2);
+        let mut drawn = 0;
+        for (x, y, p) in image.enumerate_pixels() {
+            if p[0] > 0 {
+                drawn += 1;
+                assert!(bounds.contains(x as i32, y as i32));
+            }
+        }
+        assert!(drawn > 0);
+    }
+
+    #[test]
+    fn test_rotated_text_bounds_quarter_turn() {
+        let font = font();
+        let scale = Scale::uniform(20.0);
+        let (w, h) = text_size(scale, &font, "Hello");
+        let bounds =
+            rotated_text_bounds(scale, &font, "Hello", std::f32::consts::FRAC_PI_2).unwrap();
+        // Rounding errors in the rotation may add a pixel on either side
+        assert!((bounds.width() as i32 - h).abs() <= 2);
+        assert!((bounds.height() as i32 - w).abs() <= 2);
+        assert_eq!(rotated_text_bounds(scale, &font, "", 1.0), None);
+    }
+
+    #[test]
+    fn test_rasterize_mask_matches_draw_text() {
+        let font = font();
+        let scale = Scale::uniform(20.0);
+        let options = LayoutOptions::default();
+        let (mask, (left, top)) = rasterize_mask(scale, &font, "jolly", &options, 2);
+
+        let mut image = GrayImage::new(100, 50);
+        draw_text_mut(&mut image, Luma([255u8]), 10, 10, scale, &font, "jolly");
+        for (x, y, p) in mask.enumerate_pixels() {
+            let (ix, iy) = (10 + left + x as i32, 10 + top + y as i32);
+            let drawn = image.get_pixel(ix as u32, iy as u32)[0];
+            assert!((p[0] * 255.0 - drawn as f32).abs() <= 1.0);
+        }
+        assert_eq!(
+            rasterize_mask(scale, &font, "", &options, 2).0.dimensions(),
+            (0, 0)
+        );
+    }
+
+    #[test]
+    fn test_draw_text_outlined_mut() {
+        let font = font();
+        let scale = Scale::uniform(20.0);
+        let fill = Luma([255u8]);
+        let outline = Luma([100u8]);
+
+        let mut plain = GrayImage::new(100, 50);
+        draw_text_mut(&mut plain, fill, 10, 10, scale, &font, "Hi");
+        let mut outlined = GrayImage::new(100, 50);
+        draw_text_outlined_mut(&mut outlined, fill, outline, 2, 10, 10, scale, &font, "Hi");
+
+        // Fully covered pixels keep the fill color
+        for (x, y, p) in plain.enumerate_pixels() {
+            if p[0] == 255 {
+                assert_eq!(outlined.get_pixel(x, y)[0], 255);
+            }
+        }
+        // The outline surrounds the text
+        let count = |image: &GrayImage| image.pixels().filter(|p| p[0] > 0).count();
+        assert!(count(&outlined) > count(&plain));
+        assert!(outlined.pixels().any(|p| p[0] == 100));
+    }
+
+    #[test]
+    fn test_draw_text_with_shadow_mut_hard_edged() {
+        let font = font();
+        let scale = Scale::uniform(20.0);
+        let mut expected = GrayImage::new(60, 40);
+        draw_text_mut(&mut expected, Luma([100u8]), 13, 14, scale, &font, "Hi");
+        draw_text_mut(&mut expected, Luma([255u8]), 10, 10, scale, &font, "Hi");
+
+        let mut actual = GrayImage::new(60, 40);
+        draw_text_with_shadow_mut(
+            &mut actual,
+            Luma([255u8]),
+            Luma([100u8]),
+            (3, 4),
+            0.0,
+            10,
+            10,
+            scale,
+            &font,
+            "Hi",
+        );
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_draw_text_with_shadow_mut_blurred() {
+        let font = font();
+        let scale = Scale::uniform(20.0);
+        let draw = |blur| {
+            let mut image = GrayImage::new(60, 40);
+            draw_text_with_shadow_mut(
+                &mut image,
+                Luma([255u8]),
+                Luma([100u8]),
+                (3, 3),
+                blur,
+                10,
+                10,
+                scale,
+                &font,
+                "Hi",
+            );
+            image
+        };
+        let (hard, soft) = (draw(0.0), draw(2.0));
+        let count = |image: &GrayImage| image.pixels().filter(|p| p[0] > 0).count();
+        assert!(count(&soft) > count(&hard));
+    }
+
+    #[test]
+    fn test_draw_text_with_background_mut() {
+        use image::{Rgba, RgbaImage};
+
+        let font = font();
+        let scale = Scale::uniform(20.0);
+        let white = Rgba([255u8, 255, 255, 255]);
+        let (w, h) = text_size(scale, &font, "Hi");
+
+        let mut image = RgbaImage::from_pixel(60, 40, Rgba([0, 0, 0, 255]));
+        let background = Rgba([255u8, 0, 0, 255]);
+        draw_text_with_background_mut(&mut image, white, background, 2, 10, 10, scale, &font, "Hi");
+        assert_eq!(*image.get_pixel(8, 8), background);
+        assert_eq!(
+            *image.get_pixel((11 + w) as u32, (11 + h) as u32),
+            background
+        );
+        assert_eq!(*image.get_pixel(7, 7), Rgba([0, 0, 0, 255]));
+
+        let mut image = RgbaImage::from_pixel(60, 40, Rgba([0, 0, 0, 255]));
+        let translucent = Rgba([255u8, 0, 0, 127]);
+        draw_text_with_background_mut(
+            &mut image,
+            white,
+            translucent,
+            2,
+            10,
+            10,
+            scale,
+            &font,
+            "Hi",
+        );
+        assert_eq!(image.get_pixel(8, 8)[0], 127);
+    }
+
+    #[test]
+    fn test_draw_text_with_background_mut_clipped() {
+        let font = font();
+        let scale = Scale::uniform(20.0);
+        let mut image = GrayImage::new(20, 20);
+        draw_text_with_background_mut(
+            &mut image,
+            Luma([255u8]),
+            Luma([50u8]),
+            4,
+            -5,
+            -5,
+            scale,
+            &font,
+            "Hi",
+        );
+        assert_eq!(image.get_pixel(0, 0)[0], 50);
+    }
+
+    #[test]
+    fn test_text_decoration_contains() {
+        let both = TextDecoration::UNDERLINE | TextDecoration::STRIKETHROUGH;
+        assert!(both.contains(TextDecoration::UNDERLINE));
+        assert!(both.contains(TextDecoration::STRIKETHROUGH));
+        assert!(!both.contains(TextDecoration::OVERLINE));
+        assert!(TextDecoration::NONE.contains(TextDecoration::NONE));
+    }
+
+    #[test]
+    fn test_draw_text_decorated_mut() {
+        let font = font();
+        let scale = Scale::uniform(32.0);
+        let v_metrics = font.v_metrics(scale);
+        let (w, _) = text_size(scale, &font, "ab");
+        let full_rows = |decoration| {
+            let mut image = GrayImage::new(100, 100);
+            draw_text_decorated_mut(
+                &mut image,
+                Luma([255u8]),
+                0,
+                0,
+                decoration,
+                Some(2.0),
+                scale,
+                &font,
+                "ab\nab",
+            );
+            (0..100)
+                .filter(|&y| (0..w as u32).all(|x| image.get_pixel(x, y)[0] == 255))
+                .collect::<Vec<_>>()
+        };
+
+        assert!(full_rows(TextDecoration::NONE).is_empty());
+
+        let underline = full_rows(TextDecoration::UNDERLINE);
+        // One or two fully covered rows per line of text, depending on how the line straddles pixels,
+        // all below the baselines
+        assert!((2..=4).contains(&underline.len()));
+        assert!(underline[0] as f32 > v_metrics.ascent);
+
+        let overline = full_rows(TextDecoration::OVERLINE);
+        assert_eq!(overline[0], 0);
+
+        let strikethrough = full_rows(TextDecoration::STRIKETHROUGH);
+        assert!((strikethrough[0] as f32) < v_metrics.ascent);
+        assert!((strikethrough[0] as f32) > overline[1] as f32);
+    }
+
+    #[test]
+    fn test_glyph_string_draw_colored_mut() {
+        let font = font();
+        let scale = Scale::uniform(20.0);
+        let text = GlyphString::new(scale, &font, "l l");
+
+        let mut single = GrayImage::new(40, 30);
+        text.draw_mut(&mut single, Luma([255u8]), 0, 0);
+        let mut clamped = GrayImage::new(40, 30);
+        text.draw_colored_mut(&mut clamped, &[Luma([255u8])], 0, 0);
+        assert_eq!(single, clamped);
+
+        // The space is the second glyph, so the final 'l' uses the last color
+        let mut colored = GrayImage::new(40, 30);
+        text.draw_colored_mut(&mut colored, &[Luma([255u8]), Luma([100u8])], 0, 0);
+        let values: Vec<u8> = colored.pixels().map(|p| p[0]).filter(|&v| v > 0).collect();
+        assert!(values.contains(&255));
+        assert!(values.contains(&100));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_glyph_string_draw_colored_mut_no_colors() {
+        let font = font();
+        let text = GlyphString::new(Scale::uniform(20.0), &font, "l");
+        text.draw_colored_mut(&mut GrayImage::new(10, 10), &[], 0, 0);
+    }
+
+    #[test]
+    fn test_draw_text_clipped_mut() {
+        let font = font();
+        let scale = Scale::uniform(20.0);
+        let mut full = GrayImage::new(60, 30);
+        draw_text_mut(&mut full, Luma([255u8]), 2, 2, scale, &font, "Hello");
+
+        let clip = IpRect::at(10, 5).of_size(20, 10);
+        let mut clipped = GrayImage::new(60, 30);
+        draw_text_clipped_mut(
+            &mut clipped,
+            Luma([255u8]),
+            2,
+            2,
+            scale,
+            &font,
+            "Hello",
+            clip,
+        );
+
+        for (x, y, p) in clipped.enumerate_pixels() {
+            if clip.contains(x as i32, y as i32) {
+                assert_eq!(p, full.get_pixel(x, y));
+            } else {
+                assert_eq!(p[0], 0);
+            }
+        }
+        assert!(clipped.pixels().any(|p| p[0] > 0));
+    }
+
+    #[test]
+    fn test_wrap_text() {
+        let font = font();
+        let scale = Scale::uniform(20.0);
+        let max_width = text_size(scale, &font, "the quick")
+            .0
+            .max(text_size(scale, &font, "brown fox").0) as u32;
+
+        let lines = wrap_text(scale, &font, "the quick brown fox  jumps", max_width);
+        assert_eq!(lines, vec!["the quick", "brown fox", "jumps"]);
+        for line in &lines {
+            assert!(text_size(scale, &font, line).0 as u32 <= max_width);
+        }
+    }
+
+    #[test]
+    fn test_wrap_text_hard_breaks_and_long_words() {
+        let font = font();
+        let scale = Scale::uniform(20.0);
+        let lines = wrap_text(scale, &font, "a\n\nextraordinarily b", 30);
+        assert_eq!(lines, vec!["a", "", "extraordinarily", "b"]);
+        assert!(wrap_text(scale, &font, "", 30).is_empty());
+    }
+
+    #[test]
+    fn test_wrap_text_hyphens() {
+        let font = font();
+        let scale = Scale::uniform(20.0);
+        let max_width = text_size(scale, &font, "a well-").0 as u32;
+
+        // Breaks after an existing hyphen, keeping it on the first line
+        let lines = wrap_text(scale, &font, "a well-known", max_width);
+        assert_eq!(lines, vec!["a well-", "known"]);
+
+        // Soft hyphens are only rendered where the line is broken
+        let lines = wrap_text(scale, &font, "a well\u{AD}known", max_width);
+        assert_eq!(lines, vec!["a well-", "known"]);
+        let lines = wrap_text(scale, &font, "well\u{AD}known", 1000);
+        assert_eq!(lines, vec!["wellknown"]);
+
+        // A long word is broken repeatedly, at the last soft hyphen which fits
+        let max_width = text_size(scale, &font, "extra-").0 as u32;
+        let word = "ex\u{AD}tra\u{AD}or\u{AD}di\u{AD}nar\u{AD}i\u{AD}ly";
+        let lines = wrap_text(scale, &font, word, max_width);
+        assert_eq!(lines.concat().replace('-', ""), "extraordinarily");
+        assert_eq!(lines[0], "extra-");
+        for line in &lines {
+            assert!(text_size(scale, &font, line).0 as u32 <= max_width);
+        }
+
+        // Leading and trailing hyphens are not break opportunities
+        assert_eq!(wrap_text(scale, &font, "-abc-", 1), vec!["-abc-"]);
+    }
+
+    #[test]
+    fn test_wrap_text_around() {
+        let font = font();
+        let scale = Scale::uniform(16.0);
+        let advance = line_advance(&font.v_metrics(scale));
+        let text = "the quick brown fox jumps over the lazy dog and keeps running far away";
+
+        // Without exclusions lines match wrap_text
+        let plain: Vec<(i32, String)> = wrap_text(scale, &font, text, 150)
+            .into_iter()
+            .map(|line| (0, line))
+            .collect();
+        assert_eq!(wrap_text_around(scale, &font, text, 150, &[]), plain);
+
+        // An inset on the right narrows the lines beside it
+        let inset = IpRect::at(80, 0).of_size(70, (2.5 * advance) as u32);
+        let lines = wrap_text_around(scale, &font, text, 150, &[inset]);
+        for (i, (offset, line)) in lines.iter().enumerate() {
+            assert_eq!(*offset, 0);
+            let width = text_size(scale, &font, line).0;
+            let limit = if i < 3 { 80 } else { 150 };
+            assert!(width <= limit, "{:?} is too wide", line);
+        }
+        assert!(lines[3..]
+            .iter()
+            .any(|(_, line)| text_size(scale, &font, line).0 > 80));
+
+        // An inset on the left moves the lines beside it to the right
+        let inset = IpRect::at(0, 0).of_size(40, advance as u32);
+        let lines = wrap_text_around(scale, &font, text, 150, &[inset]);
+        assert_eq!(lines[0].0, 40);
+        assert!(text_size(scale, &font, &lines[0].1).0 <= 110);
+        assert_eq!(lines[1].0, 0);
+
+        // Lines too narrow for any word are left empty
+        let inset = IpRect::at(5, 0).of_size(140, advance as u32);
+        let lines = wrap_text_around(scale, &font, text, 150, &[inset]);
+        assert_eq!(lines[0].1, "");
+        assert_eq!(lines[1], (0, "the quick brown fox".to_string()));
+    }
+
+    #[test]
+    fn test_calculate_center_oversized_content() {
+        assert_eq!(calculate_center(10, 4, &EdgePosition::center()), 3);
+        assert_eq!(calculate_center(10, 20, &EdgePosition::center()), 0);
+        assert_eq!(calculate_center(10, 20, &EdgePosition::right()), 0);
+        assert_eq!(calculate_center(10, 4, &EdgePosition(150.0)), 6);
+        assert_eq!(calculate_center(10, 4, &EdgePosition(-10.0)), 0);
+    }
+
+    #[test]
+    fn test_edge_position_clamps() {
+        let position: EdgePosition = 200.0.into();
+        assert_eq!(position.0, EdgePosition::right().0);
+        let position: EdgePosition = 200u32.into();
+        assert_eq!(position.0, 100.0);
+        let position: EdgePosition = (-10.0).into();
+        assert_eq!(position.0, 0.0);
+        let position: EdgePosition = f32::NAN.into();
+        assert_eq!(position.0, 0.0);
+
+        assert_eq!(EdgePosition::try_from(25.0).unwrap().0, 25.0);
+        assert_eq!(EdgePosition::try_from(100.0).unwrap().0, 100.0);
+        assert_eq!(
+            EdgePosition::try_from(150.0).err(),
+            Some(InvalidEdgePosition(150.0))
+        );
+        assert!(EdgePosition::try_from(-0.5).is_err());
+        assert!(EdgePosition::try_from(f32::NAN).is_err());
+    }
+
+    #[test]
+    fn test_find_text_area_coordinates_oversized_text() {
+        let rect = IpRect::at(5, 7).of_size(10, 10);
+        let positions = vec![
+            Position::HorizontalTop(EdgePosition::right()),
+            Position::HorizontalCenter(EdgePosition::right()),
+            Position::HorizontalBottom(EdgePosition::right()),
+            Position::VerticalLeft(EdgePosition::bottom()),
+            Position::VerticalCenter(EdgePosition::bottom()),
+            Position::VerticalRight(EdgePosition::bottom()),
+            Position::Any(EdgePosition::right(), EdgePosition::bottom()),
+        ];
+        // Text which is too large in both dimensions is pinned to the top-left corner
+        for position in &positions {
+            assert_eq!(find_text_area_coordinates(position, &rect, 50, 50), (5, 7));
+        }
+        // Text which is too wide is pinned to the left, but still positioned vertically
+        assert_eq!(
+            find_text_area_coordinates(
+                &Position::HorizontalBottom(EdgePosition::left()),
+                &rect,
+                50,
+                4
+            ),
+            (5, 12)
+        );
+        assert_eq!(
+            find_text_area_coordinates(&Position::VerticalRight(EdgePosition::top()), &rect, 4, 50),
+            (10, 7)
+        );
+    }
+
+    #[test]
+    fn test_draw_positioned_mut_oversized_text() {
+        let font = font();
+        let text = GlyphString::new(Scale::uniform(40.0), &font, "Much too long");
+        let rect = IpRect::at(5, 5).of_size(4, 4);
+        let mut image = GrayImage::new(20, 20);
+
+        for position in &[
+            Position::HorizontalCenter(EdgePosition::center()),
+            Position::VerticalCenter(EdgePosition::center()),
+            Position::Any(EdgePosition::right(), EdgePosition::bottom()),
+        ] {
+            text.draw_positioned_mut(&mut image, Luma([255u8]), position, &rect);
+        }
+    }
+
+    #[test]
+    fn test_srgb_round_trip() {
+        for i in 0..=255 {
+            let value = i as f32 / 255.0;
+            assert_approx_eq!(linear_to_srgb(srgb_to_linear(value)), value, 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_blend_linear() {
+        use image::{Rgb, Rgba};
+
+        let black = Rgb([0u8, 0, 0]);
+        let white = Rgb([255u8, 255, 255]);
+        assert_eq!(blend_linear(black, white, 0.0), black);
+        assert_eq!(blend_linear(black, white, 1.0), white);
+        // Half coverage is half the light, which is brighter than half the sRGB value
+        assert_eq!(blend_linear(black, white, 0.5), Rgb([188, 188, 188]));
+
+        let transparent = Rgba([0u8, 0, 0, 0]);
+        let opaque = Rgba([255u8, 255, 255, 255]);
+        assert_eq!(blend_linear(transparent, opaque, 0.5)[3], 128);
+    }
+
+    #[test]
+    fn test_draw_text_linear_mut() {
+        let font = font();
+        let scale = Scale::uniform(20.0);
+        let mut plain = GrayImage::new(60, 30);
+        draw_text_mut(&mut plain, Luma([255u8]), 2, 2, scale, &font, "Hi");
+        let mut linear = GrayImage::new(60, 30);
+        draw_text_linear_mut(&mut linear, Luma([255u8]), 2, 2, scale, &font, "Hi");
+
+        // The same pixels are covered, but partially covered pixels are brighter
+        for (p, q) in plain.pixels().zip(linear.pixels()) {
+            assert_eq!(p[0] == 0, q[0] == 0);
+            assert!(q[0] >= p[0]);
+        }
+        assert!(plain
+            .pixels()
+            .zip(linear.pixels())
+            .any(|(p, q)| q[0] > p[0]));
+    }
+
+    #[test]
+    fn test_draw_text_directed_mut() {
+        let font = font();
+        let scale = Scale::uniform(20.0);
+
+        let mut ltr = GrayImage::new(60, 60);
+        draw_text_directed_mut(
+            &mut ltr,
+            Luma([255u8]),
+            0,
+            0,
+            TextDirection::Ltr,
+            scale,
+            &font,
+            "ab",
+        );
+        let mut expected = GrayImage::new(60, 60);
+        draw_text_mut(&mut expected, Luma([255u8]), 0, 0, scale, &font, "ab");
+        assert_eq!(ltr, expected);
+
+        let mut rtl = GrayImage::new(60, 60);
+        draw_text_directed_mut(
+            &mut rtl,
+            Luma([255u8]),
+            0,
+            0,
+            TextDirection::Rtl,
+            scale,
+            &font,
+            "ab",
+        );
+        let mut expected = GrayImage::new(60, 60);
+        draw_text_mut(&mut expected, Luma([255u8]), 0, 0, scale, &font, "ba");
+        assert_eq!(rtl, expected);
+    }
+
+    #[test]
+    fn test_draw_text_directed_mut_rtl_lines_end_together() {
+        let font = font();
+        let scale = Scale::uniform(20.0);
+        let advance = line_advance(&font.v_metrics(scale)) as u32;
+        let mut image = GrayImage::new(100, 60);
+        draw_text_directed_mut(
+            &mut image,
+            Luma([255u8]),
+            0,
+            0,
+            TextDirection::Rtl,
+            scale,
+            &font,
+            "abcdef\nab",
+        );
+        let (_, first_right) = ink_columns(&image, 0..advance).unwrap();
+        let (_, second_right) = ink_columns(&image, advance..60).unwrap();
+        assert!((first_right as i32 - second_right as i32).abs() <= 1);
+    }
+
+    #[test]
+    fn test_draw_text_cached_mut() {
+        let font = font();
+        let scale = Scale::uniform(20.0);
+        let text = "Hello, world!\nHello";
+        let mut expected = GrayImage::new(150, 60);
+        draw_text_mut(&mut expected, Luma([255u8]), 3, 4, scale, &font, text);
+
+        let mut cache = GlyphCache::new();
+        let mut sizes = vec![];
+        for _ in 0..2 {
+            let mut actual = GrayImage::new(150, 60);
+            draw_text_cached_mut(
+                &mut actual,
+                Luma([255u8]),
+                3,
+                4,
+                scale,
+                &font,
+                text,
+                &mut cache,
+            );
+            assert_eq!(actual, expected);
+            sizes.push(cache.len());
+        }
+        // Every glyph was rasterized on the first call
+        assert!(sizes[0] > 0);
+        assert_eq!(sizes[0], sizes[1]);
+    }
+
+    const BENCH_TEXT: &str = "The quick brown fox.";
+
+    const BENCH_WORDS: [&str; 8] = ["a", "to", "fox", "jump", "over", "lazy", "dog", "ok"];
+
+    #[bench]
+    fn bench_text_size_short_strings(b: &mut Bencher) {
+        let font = font();
+        let scale = Scale::uniform(20.0);
+        b.iter(|| {
+            for i in 0..10_000 {
+                black_box(text_size(scale, &font, BENCH_WORDS[i % BENCH_WORDS.len()]));
+            }
+        });
+    }
+
+    #[bench]
+    fn bench_text_measurer_short_strings(b: &mut Bencher) {
+        let font = font();
+        let scale = Scale::uniform(20.0);
+        let mut measurer = TextMeasurer::new(&font);
+        b.iter(|| {
+            for i in 0..10_000 {
+                black_box(measurer.text_size(scale, BENCH_WORDS[i % BENCH_WORDS.len()]));
+            }
+        });
+    }
+
+    #[bench]
+    fn bench_draw_text_mut(b: &mut Bencher) {
+        let font = font();
+        let scale = Scale::uniform(20.0);
+        let mut image = GrayImage::new(300, 50);
+        b.iter(|| {
+            draw_text_mut(&mut image, Luma([255u8]), 0, 0, scale, &font, BENCH_TEXT);
+            black_box(&image);
+        });
+    }
+
+    const BENCH_PARAGRAPH: &str =
+        "Sphinx of black quartz, judge my vow. Pack my box with five dozen liquor jugs.";
+
+    #[bench]
+    fn bench_draw_text_mut_large_gray(b: &mut Bencher) {
+        let font = font();
+        let scale = Scale::uniform(60.0);
+        let mut image = GrayImage::new(2000, 200);
+        b.iter(|| {
+            draw_text_mut(
+                &mut image,
+                Luma([255u8]),
+                0,
+                0,
+                scale,
+                &font,
+                BENCH_PARAGRAPH,
+            );
+            black_box(&image);
+        });
+    }
+
+    #[bench]
+    fn bench_draw_text_gray_mut_large_gray(b: &mut Bencher) {
+        let font = font();
+        let scale = Scale::uniform(60.0);
+        let mut image = GrayImage::new(2000, 200);
+        b.iter(|| {
+            draw_text_gray_mut(
+                &mut image,
+                Luma([255u8]),
+                0,
+                0,
+                scale,
+                &font,
+                BENCH_PARAGRAPH,
+            );
+            black_box(&image);
+        });
+    }
+
+    fn bench_labels<'a>(font: &'a Font<'a>) -> Vec<Label<'a, Luma<u8>>> {
+        let style = TextStyle::new(font, Scale::uniform(12.0), Luma([255u8]));
+        (0..1000)
+            .map(|i| Label {
+                text: BENCH_WORDS[i % BENCH_WORDS.len()],
+                x: (i * 37 % 480) as i32,
+                y: (i * 53 % 480) as i32,
+                style,
+            })
+            .collect()
+    }
+
+    #[bench]
+    fn bench_draw_labels_separately(b: &mut Bencher) {
+        let font = font();
+        let labels = bench_labels(&font);
+        let mut image = GrayImage::new(500, 500);
+        b.iter(|| {
+            for label in &labels {
+                label
+                    .style
+                    .draw_mut(&mut image, label.x, label.y, label.text);
+            }
+            black_box(&image);
+        });
+    }
+
+    #[bench]
+    fn bench_draw_labels_mut(b: &mut Bencher) {
+        let font = font();
+        let labels = bench_labels(&font);
+        let mut image = GrayImage::new(500, 500);
+        b.iter(|| {
+            black_box(draw_labels_mut(&mut image, &labels));
+        });
+    }
+
+    #[bench]
+    fn bench_draw_text_cached_mut(b: &mut Bencher) {
+        let font = font();
+        let scale = Scale::uniform(20.0);
+        let mut image = GrayImage::new(300, 50);
+        let mut cache = GlyphCache::new();
+        b.iter(|| {
+            draw_text_cached_mut(
+                &mut image,
+                Luma([255u8]),
+                0,
+                0,
+                scale,
+                &font,
+                BENCH_TEXT,
+                &mut cache,
+            );
+            black_box(&image);
+        });
+    }
+
+    #[test]
+    fn test_glyph_strings_orientation() {
+        let font = font();
+        let small = GlyphString::new(Scale::uniform(10.0), &font, "ab");
+        let large = GlyphString::new(Scale::uniform(20.0), &font, "a");
+        let strings = [&small, &large];
+
+        let horizontal = GlyphStrings::new(&strings);
+        assert_eq!(horizontal.0.len(), 2);
+        assert_eq!(horizontal.width(), small.width() + large.width());
+        assert_eq!(horizontal.height(), large.height());
+        let explicit = horizontal.with_orientation(Orientation::Horizontal);
+        assert_eq!(explicit.width(), horizontal.width());
+        assert_eq!(explicit.height(), horizontal.height());
+
+        let vertical = GlyphStrings::new(&strings).with_orientation(Orientation::Vertical);
+        assert_eq!(vertical.width(), small.width().max(large.width()));
+        assert_eq!(vertical.height(), small.height() + large.height());
+
+        let rect = IpRect::at(0, 0).of_size(100, 100);
+        let position = Position::Any(EdgePosition::left(), EdgePosition::top());
+        let mut image = GrayImage::new(100, 100);
+        vertical.draw_positioned_mut(&mut image, &[Luma([255u8]); 2], &position, &rect);
+        let mut expected = GrayImage::new(100, 100);
+        small.draw_mut(&mut expected, Luma([255u8]), 0, 0);
+        large.draw_mut(&mut expected, Luma([255u8]), 0, small.height());
+        assert_eq!(image, expected);
+    }
+
+    #[test]
+    fn test_glyph_strings_colors_clamp_to_last() {
+        let font = font();
+        let first = GlyphString::new(Scale::uniform(20.0), &font, "ab");
+        let second = GlyphString::new(Scale::uniform(20.0), &font, "cd");
+        let third = GlyphString::new(Scale::uniform(20.0), &font, "ef");
+        let strings = [&first, &second, &third];
+        let rect = IpRect::at(0, 0).of_size(100, 40);
+        let position = Position::Any(EdgePosition::left(), EdgePosition::top());
+
+        let mut image = GrayImage::new(100, 40);
+        GlyphStrings::new(&strings).draw_positioned_mut(
+            &mut image,
+            &[Luma([100u8]), Luma([255u8])],
+            &position,
+            &rect,
+        );
+        let mut expected = GrayImage::new(100, 40);
+        first.draw_mut(&mut expected, Luma([100u8]), 0, 0);
+        second.draw_mut(&mut expected, Luma([255u8]), first.width(), 0);
+        third.draw_mut(
+            &mut expected,
+            Luma([255u8]),
+            first.width() + second.width(),
+            0,
+        );
+        assert_eq!(image, expected);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_glyph_strings_empty_colors_panics() {
+        let font = font();
+        let string = GlyphString::new(Scale::uniform(20.0), &font, "ab");
+        let strings = [&string];
+        let rect = IpRect::at(0, 0).of_size(100, 40);
+        let position = Position::Any(EdgePosition::left(), EdgePosition::top());
+        let mut image = GrayImage::new(100, 40);
+        GlyphStrings::new(&strings).draw_positioned_mut(&mut image, &[], &position, &rect);
+    }
+
+    #[test]
+    fn test_glyph_strings_baseline_alignment() {
+        let font = font();
+        let small = GlyphString::new(Scale::uniform(10.0), &font, "H");
+        let large = GlyphString::new(Scale::uniform(30.0), &font, "H");
+        let strings = [&small, &large];
+        let offset = (large.v_metrics.ascent - small.v_metrics.ascent).round() as u32;
+
+        let top_aligned = GlyphStrings::new(&strings);
+        let aligned = GlyphStrings::new(&strings).with_baseline_alignment(true);
+        assert_eq!(top_aligned.height(), large.height());
+        assert_eq!(
+            aligned.height(),
+            large.height().max(small.height() + offset)
+        );
+
+        let rect = IpRect::at(0, 0).of_size(100, 100);
+        let position = Position::Any(EdgePosition::left(), EdgePosition::top());
+        let mut image = GrayImage::new(100, 100);
+        aligned.draw_positioned_mut(&mut image, &[Luma([255u8]); 2], &position, &rect);
+
+        // Both 'H's end on the same row
+        let bottom = |columns: std::ops::Range<u32>| {
+            (0..100)
+                .rev()
+                .find(|&y| columns.clone().any(|x| image.get_pixel(x, y)[0] > 0))
+                .unwrap()
+        };
+        let split = small.width();
+        assert!((bottom(0..split) as i32 - bottom(split..100) as i32).abs() <= 1);
+    }
+
+    #[test]
+    fn test_fit_scale() {
+        let font = font();
+        let rect = IpRect::at(0, 0).of_size(100, 40);
+
+        let scale = fit_scale(&font, "Hello", rect, 2, 200.0);
+        let (w, h) = text_size(scale, &font, "Hello");
+        assert!(w <= 96 && h <= 36);
+        let larger = Scale::uniform(scale.y + 0.5);
+        let (w, h) = text_size(larger, &font, "Hello");
+        assert!(w > 96 || h > 36);
+
+        // Single glyphs are limited by height
+        let scale = fit_scale(&font, "l", rect, 0, 200.0);
+        assert!(text_size(scale, &font, "l").1 <= 40);
+        assert!(text_size(Scale::uniform(scale.y + 0.5), &font, "l").1 > 40);
+    }
+
+    #[test]
+    fn test_fit_scale_edge_cases() {
+        let font = font();
+        let rect = IpRect::at(0, 0).of_size(100, 40);
+        assert_eq!(fit_scale(&font, "", rect, 0, 50.0), Scale::uniform(50.0));
+        assert_eq!(fit_scale(&font, "a", rect, 0, 5.0), Scale::uniform(5.0));
+        assert_eq!(fit_scale(&font, "a", rect, 50, 100.0), Scale::uniform(0.0));
+    }
+
+    #[test]
+    fn test_draw_text_fitted_mut() {
+        let font = font();
+        let rect = IpRect::at(10, 10).of_size(50, 20);
+        let mut image = GrayImage::new(100, 100);
+        let scale = draw_text_fitted_mut(&mut image, Luma([255u8]), rect, 1, 100.0, &font, "Hi");
+        assert_eq!(scale, fit_scale(&font, "Hi", rect, 1, 100.0));
+        for (x, y, p) in image.enumerate_pixels() {
+            if p[0] > 0 {
+                assert!(rect.contains(x as i32, y as i32));
+            }
+        }
+    }
+
+    #[test]
+    fn test_truncate_with_ellipsis() {
+        let font = font();
+        let scale = Scale::uniform(20.0);
+        let (full, _) = text_size(scale, &font, "Hello world");
+
+        assert_eq!(
+            truncate_with_ellipsis(scale, &font, "Hello world", full as u32),
+            "Hello world"
+        );
+
+        let max_width = text_size(scale, &font, "Hello…").0 as u32;
+        assert_eq!(
+            truncate_with_ellipsis(scale, &font, "Hello world", max_width),
+            "Hello…"
+        );
+        assert_eq!(
+            truncate_with_ellipsis(scale, &font, "Hello world", max_width + 3),
+            "Hello…"
+        );
+
+        let ellipsis = text_size(scale, &font, "…").0 as u32;
+        assert_eq!(truncate_with_ellipsis(scale, &font, "Hello", ellipsis), "…");
+        assert_eq!(
+            truncate_with_ellipsis(scale, &font, "Hello", ellipsis - 1),
+            ""
+        );
+    }
+
+    #[test]
+    fn test_tab_stops() {
+        let font = font();
+        let scale = Scale::uniform(20.0);
+        let right_edge = |style: &TextStyle<Luma<u8>>, text: &str| {
+            let mut image = GrayImage::new(300, 30);
+            style.draw_mut(&mut image, 0, 0, text);
+            ink_columns(&image, 0..30).unwrap().1
+        };
+
+        let pixels = TextStyle::new(&font, scale, Luma([255u8])).tab_width(TabWidth::Pixels(50.0));
+        // Both tabs advance the pen to the first tab stop
+        assert_eq!(right_edge(&pixels, "\tl"), right_edge(&pixels, "a\tl"));
+        assert_eq!(
+            right_edge(&pixels, "\t\tl"),
+            right_edge(
+                &TextStyle::new(&font, scale, Luma([255u8])).tab_width(TabWidth::Pixels(100.0)),
+                "\tl"
+            )
+        );
+
+        let default = TextStyle::new(&font, scale, Luma([255u8]));
+        let spaces = TextStyle::new(&font, scale, Luma([255u8])).tab_width(TabWidth::Spaces(4.0));
+        assert_eq!(right_edge(&default, "\tl"), right_edge(&default, "    l"));
+        assert_eq!(right_edge(&spaces, "\tl"), right_edge(&default, "\tl"));
+        assert_eq!(default.size("a\tb").1, text_size(scale, &font, "ab").1);
+    }
+
+    #[test]
+    fn test_synthetic_bold() {
+        let font = font();
+        let scale = Scale::uniform(20.0);
+        let text = "Hello";
+        let regular = TextStyle::new(&font, scale, Luma([255u8]));
+        let bold = regular.synthetic_bold(1.5);
+
+        let (regular_width, regular_height) = regular.size(text);
+        let (bold_width, bold_height) = bold.size(text);
+        assert_eq!(bold_height, regular_height);
+        // Four widened advances plus the smear of the last glyph
+        assert!((bold_width - regular_width - 8).abs() <= 1);
+
+        let ink = |style: &TextStyle<Luma<u8>>| {
+            let mut image = GrayImage::new(100, 30);
+            style.draw_mut(&mut image, 0, 0, text);
+            image.pixels().map(|p| p[0] as u32).sum::<u32>()
+        };
+        assert!(ink(&bold) > ink(&regular));
+        assert_eq!(regular.synthetic_bold(0.0).size(text), regular.size(text));
+    }
+
+    #[test]
+    fn test_shear() {
+        let font = font();
+        let scale = Scale::uniform(40.0);
+        let upright = TextStyle::new(&font, scale, Luma([255u8]));
+        let slanted = upright.shear(0.25);
+
+        // The top of the 'l' moves right by roughly a quarter of its height
+        let (width, height) = upright.size("l");
+        let (slanted_width, slanted_height) = slanted.size("l");
+        let ink_height = ink_bounds(scale, &font, "l").unwrap().bottom() + 1;
+        assert_eq!(slanted_height, height);
+        assert!(slanted_width > width + ink_height / 5);
+
+        let draw = |style: &TextStyle<Luma<u8>>| {
+            let mut image = GrayImage::new(60, 60);
+            style.draw_mut(&mut image, 10, 10, "l");
+            image
+        };
+        let (upright, slanted) = (draw(&upright), draw(&slanted));
+        let rows = |image: &GrayImage| {
+            (0..60)
+                .filter(|&y| (0..60).any(|x| image.get_pixel(x, y)[0] > 0))
+                .collect::<Vec<_>>()
+        };
+        let ink_rows = rows(&upright);
+        assert_eq!(rows(&slanted), ink_rows);
+
+        let (first, last) = (ink_rows[0], ink_rows[ink_rows.len() - 1]);
+        let top = ink_columns(&slanted, first..first + 2).unwrap();
+        let bottom = ink_columns(&slanted, last - 1..last + 1).unwrap();
+        assert!(top.0 > bottom.0 + 4);
+        assert_eq!(
+            ink_columns(&upright, first..first + 2),
+            ink_columns(&upright, last - 1..last + 1)
+        );
+    }
+
+    #[test]
+    fn test_draw_text_subpixel_mut() {
+        let font = font();
+        let scale = Scale::uniform(20.0);
+        let draw = |x: f32| {
+            let mut image = GrayImage::new(80, 30);
+            draw_text_subpixel_mut(
+                &mut image,
+                Luma([255u8]),
+                x,
+                2.0,
+                false,
+                scale,
+                &font,
+                "Hello",
+            );
+            image
+        };
+        let total = |image: &GrayImage| image.pixels().map(|p| p[0] as i32).sum::<i32>();
+
+        let mut expected = GrayImage::new(80, 30);
+        draw_text_mut(&mut expected, Luma([255u8]), 5, 2, scale, &font, "Hello");
+        assert_pixels_eq!(draw(5.0), expected);
+
+        // Moving by half a pixel keeps the amount of ink roughly constant, and lies between the two neighbouring
+        // whole pixel positions
+        let (left, middle, right) = (draw(5.0), draw(5.5), draw(6.0));
+        let tolerance = total(&left) / 20;
+        assert!((total(&middle) - total(&left)).abs() < tolerance);
+        assert_ne!(middle, left);
+        assert_ne!(middle, right);
+        let first_column = |image: &GrayImage| ink_columns(image, 0..30).unwrap().0;
+        assert_eq!(first_column(&middle), first_column(&left));
+        assert_eq!(
+            ink_columns(&middle, 0..30).unwrap().1,
+            ink_columns(&right, 0..30).unwrap().1
+        );
+    }
+
+    #[test]
+    fn test_draw_text_subpixel_mut_snap_baseline() {
+        let font = font();
+        let scale = Scale::uniform(20.0);
+        let draw = |x: f32, y: f32, snap: bool| {
+            let mut image = GrayImage::new(80, 30);
+            draw_text_subpixel_mut(&mut image, Luma([255u8]), x, y, snap, scale, &font, "Hello");
+            image
+        };
+
+        // Only the vertical position is snapped
+        assert_pixels_eq!(draw(5.5, 2.4, true), draw(5.5, 2.0, false));
+        assert_pixels_eq!(draw(5.5, 2.6, true), draw(5.5, 3.0, false));
+        assert_ne!(draw(5.5, 2.4, true), draw(5.0, 2.0, false));
+        assert_ne!(draw(5.5, 2.4, false), draw(5.5, 2.0, false));
+    }
+
+    #[test]
+    fn test_text_metrics() {
+        let font = font();
+        let scale = Scale::uniform(20.0);
+        let metrics = text_metrics(scale, &font, "Hello");
+        let v_metrics = font.v_metrics(scale);
+
+        assert_eq!(
+            (metrics.width, metrics.height),
+            text_size(scale, &font, "Hello")
+        );
+        assert_eq!(metrics.ascent, v_metrics.ascent);
+        assert_eq!(metrics.descent, v_metrics.descent);
+        assert_eq!(metrics.line_gap, v_metrics.line_gap);
+
+        // The bottom of the 'H' lies on the baseline
+        let mut image = GrayImage::new(80, 30);
+        draw_text_mut(&mut image, Luma([255u8]), 0, 0, scale, &font, "H");
+        let bottom = (0..30)
+            .rev()
+            .find(|&y| (0..80).any(|x| image.get_pixel(x, y)[0] > 127))
+            .unwrap();
+        assert_eq!(bottom + 1, metrics.baseline.round() as u32);
+    }
+
+    #[test]
+    fn test_draw_text_blended_mut() {
+        let font = font();
+        let scale = Scale::uniform(20.0);
+        let draw = |background: Luma<u8>, color: Luma<u8>, mode: BlendMode| {
+            let mut image = GrayImage::from_pixel(80, 30, background);
+            draw_text_blended_mut(&mut image, color, 2, 2, mode, scale, &font, "Hello");
+            image
+        };
+
+        let mut expected = GrayImage::from_pixel(80, 30, Luma([100u8]));
+        draw_text_mut(&mut expected, Luma([200u8]), 2, 2, scale, &font, "Hello");
+        let normal = draw(Luma([100u8]), Luma([200u8]), BlendMode::Normal);
+        assert_pixels_eq!(normal, expected);
+
+        // Identity colors for each mode leave the canvas unchanged
+        let background = GrayImage::from_pixel(80, 30, Luma([100u8]));
+        assert_pixels_eq!(
+            draw(Luma([100u8]), Luma([255u8]), BlendMode::Multiply),
+            background
+        );
+        assert_pixels_eq!(
+            draw(Luma([100u8]), Luma([0u8]), BlendMode::Screen),
+            background
+        );
+        assert_pixels_eq!(draw(Luma([100u8]), Luma([0u8]), BlendMode::Add), background);
+
+        let (x, y) = (0..80)
+            .flat_map(|x| (0..30).map(move |y| (x, y)))
+            .find(|&(x, y)| expected.get_pixel(x, y)[0] == 200)
+            .unwrap();
+        let at = |image: GrayImage| image.get_pixel(x, y)[0];
+        assert_eq!(
+            at(draw(Luma([102u8]), Luma([128u8]), BlendMode::Multiply)),
+            51
+        );
+        assert_eq!(
+            at(draw(Luma([102u8]), Luma([153u8]), BlendMode::Screen)),
+            194
+        );
+        assert_eq!(at(draw(Luma([200u8]), Luma([100u8]), BlendMode::Add)), 255);
+    }
+
+    #[test]
+    fn test_draw_text_blended_mut_alpha() {
+        use image::{Rgba, RgbaImage};
+        let font = font();
+        let scale = Scale::uniform(20.0);
+        let mut image = RgbaImage::from_pixel(80, 30, Rgba([255u8, 255u8, 255u8, 0u8]));
+        let color = Rgba([0u8, 0u8, 0u8, 255u8]);
+        draw_text_blended_mut(
+            &mut image,
+            color,
+            2,
+            2,
+            BlendMode::Screen,
+            scale,
+            &font,
+            "Hello",
+        );
+
+        // Screening black leaves the color channels unchanged but the text is still made opaque
+        assert!(image
+            .pixels()
+            .all(|p| p[0] == 255 && p[1] == 255 && p[2] == 255));
+        assert!(image.pixels().any(|p| p[3] == 255));
+    }
+
+    #[test]
+    fn test_draw_text_on_path_mut() {
+        let font = font();
+        let scale = Scale::uniform(20.0);
+        let draw = |path: &[(f32, f32)], width: u32, height: u32| {
+            let mut image = GrayImage::new(width, height);
+            draw_text_on_path_mut(&mut image, Luma([255u8]), path, scale, &font, "Hello");
+            image
+        };
+        let total = |image: &GrayImage| image.pixels().map(|p| p[0] as u32).sum::<u32>();
+        let (width, height) = text_size(scale, &font, "Hello");
+        let baseline = font.v_metrics(scale).ascent;
+
+        // Along a horizontal path the text sits on the path, starting at its first point
+        let horizontal = draw(&[(10.0, 30.0), (70.0, 30.0), (200.0, 30.0)], 200, 40);
+        let mut expected = GrayImage::new(200, 40);
+        draw_text_mut(
+            &mut expected,
+            Luma([255u8]),
+            10,
+            30 - baseline.round() as i32,
+            scale,
+            &font,
+            "Hello",
+        );
+        let (left, right) = ink_columns(&horizontal, 0..40).unwrap();
+        assert!((left as i32 - 10).abs() <= 1);
+        assert!((right as i32 - (10 + width)).abs() <= 2);
+        assert!(ink_columns(&horizontal, 31..40).is_none());
+        assert!((total(&horizontal) as f32 / total(&expected) as f32 - 1.0).abs() < 0.05);
+
+        // Along a downwards path the text is rotated clockwise by a quarter turn, so the tops of the
+        // glyphs face right
+        let vertical = draw(&[(30.0, 10.0), (30.0, 200.0)], 80, 200);
+        let (left, right) = ink_columns(&vertical, 0..200).unwrap();
+        assert!(left >= 29 && (right - left) as i32 <= height + 1);
+
+        // Glyphs which don't fit on the path are dropped
+        let short = draw(&[(10.0, 30.0), (10.0 + width as f32 / 2.0, 30.0)], 200, 40);
+        assert!(total(&short) > 0 && total(&short) < total(&horizontal) * 3 / 4);
+        assert_eq!(total(&draw(&[(10.0, 30.0)], 200, 40)), 0);
+    }
+
+    #[test]
+    fn test_font_stack_matches_single_font() {
+        let scale = Scale::uniform(20.0);
+        let text = "Hello,\nWorld!";
+        let fonts = FontStack::new(font()).with_fallback(font());
+
+        assert_eq!(
+            text_size_with_fallback(scale, &fonts, text),
+            text_size(scale, &font(), text)
+        );
+
+        let mut expected = GrayImage::new(100, 60);
+        draw_text_mut(&mut expected, Luma([255u8]), 3, 4, scale, &font(), text);
+        let mut actual = GrayImage::new(100, 60);
+        draw_text_with_fallback_mut(&mut actual, Luma([255u8]), 3, 4, scale, &fonts, text);
+        assert_pixels_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_font_stack_glyph_selection() {
+        let fonts = FontStack::new(font()).with_fallback(font());
+        assert_eq!(fonts.fonts().len(), 2);
+
+        let (index, glyph) = fonts.glyph('a');
+        assert_eq!((index, glyph.id()), (0, font().glyph('a').id()));
+
+        // Characters missing from every font use the primary font's replacement glyph
+        let (index, glyph) = fonts.glyph('\u{10FFFD}');
+        assert_eq!((index, glyph.id()), (0, GlyphId(0)));
+    }
+
+    #[test]
+    fn test_tabular_figures() {
+        let font = font();
+        let scale = Scale::uniform(30.0);
+        let style = TextStyle::new(&font, scale, Luma([255u8])).tabular_figures(true);
+
+        // Measured to a trailing character, numbers with the same number of digits have the same width
+        let widths: Vec<i32> = ["1111.5|", "8888.5|", "1080.4|"]
+            .iter()
+            .map(|text| style.size(text).0)
+            .collect();
+        assert!(widths.iter().all(|&w| w == widths[0]));
+
+        // Each digit is centered in the widest digit advance, and other glyphs follow unchanged
+        let widest = ('0'..='9')
+            .map(|d| font.glyph(d).scaled(scale).h_metrics().advance_width)
+            .fold(0.0, f32::max);
+        let mut xs = vec![];
+        layout_line(
+            scale,
+            &font,
+            "1a",
+            point(0.0, 0.0),
+            &style.options,
+            |_, g| xs.push(g.position().x),
+        );
+        let one = font.glyph('1').scaled(scale).h_metrics().advance_width;
+        assert!((xs[0] - (widest - one) / 2.0).abs() < 1e-3);
+        assert!((xs[1] - widest).abs() < 1e-3);
+
+        // Text without digits is unaffected
+        assert_eq!(style.size("abc"), text_size(scale, &font, "abc"));
+    }
+
+    #[test]
+    fn test_outline_join() {
+        let font = font();
+        let scale = Scale::uniform(40.0);
+        let draw = |join| {
+            let mut image = GrayImage::new(60, 60);
+            TextStyle::new(&font, scale, Luma([0u8]))
+                .outline(Luma([255u8]), 6)
+                .outline_join(join)
+                .draw_mut(&mut image, 10, 5, "L");
+            image
+        };
+        let total = |image: &GrayImage| image.pixels().map(|p| p[0] as u32).sum::<u32>();
+
+        // Round joins are the default and match draw_text_outlined_mut
+        let round = draw(StrokeJoin::Round);
+        let mut expected = GrayImage::new(60, 60);
+        draw_text_outlined_mut(
+            &mut expected,
+            Luma([0u8]),
+            Luma([255u8]),
+            6,
+            10,
+            5,
+            scale,
+            &font,
+            "L",
+        );
+        assert_pixels_eq!(round, expected);
+
+        // The outer corners of an 'L' are cut off by bevels, and filled out to a point by miters
+        let bevel = draw(StrokeJoin::Bevel);
+        let miter = draw(StrokeJoin::Miter);
+        assert!(total(&bevel) < total(&round));
+        assert!(total(&round) < total(&miter));
+
+        // The bottom-left corner of the outline is square with miter joins
+        let (ink_left, ink_bottom) = {
+            let mut plain = GrayImage::new(60, 60);
+            draw_text_mut(&mut plain, Luma([255u8]), 10, 5, scale, &font, "L");
+            let ink: Vec<(u32, u32)> = plain
+                .enumerate_pixels()
+                .filter(|(_, _, p)| p[0] > 128)
+                .map(|(x, y, _)| (x, y))
+                .collect();
+            (
+                ink.iter().map(|p| p.0).min().unwrap(),
+                ink.iter().map(|p| p.1).max().unwrap(),
+            )
+        };
+        let corner = (ink_left - 5, ink_bottom + 5);
+        assert!(miter.get_pixel(corner.0, corner.1)[0] > 200);
+        assert!(round.get_pixel(corner.0, corner.1)[0] < 100);
+        assert!(bevel.get_pixel(corner.0, corner.1)[0] < 100);
+    }
+
+    #[test]
+    fn test_kerning() {
+        let font = font();
+        let scale = Scale::uniform(40.0);
+        let kerned = TextStyle::new(&font, scale, Luma([255u8]));
+        let unkerned = kerned.kerning(false);
+
+        let pair_kerning = font.pair_kerning(scale, font.glyph('A').id(), font.glyph('V').id());
+        assert!(pair_kerning < -1.0);
+        assert_eq!(kerned.size("AV"), text_size(scale, &font, "AV"));
+        let difference = unkerned.size("AV").0 - kerned.size("AV").0;
+        assert!((difference as f32 + pair_kerning).abs() <= 1.0);
+
+        // Without kerning, each glyph lies at the sum of the preceding advances
+        let mut image = GrayImage::new(100, 50);
+        unkerned.draw_mut(&mut image, 0, 0, "AV");
+        let advance = font.glyph('A').scaled(scale).h_metrics().advance_width;
+        let mut expected = GrayImage::new(100, 50);
+        kerned.draw_mut(&mut expected, 0, 0, "A");
+        kerned.draw_mut(&mut expected, advance as i32, 0, "V");
+        let right = |image: &GrayImage| ink_columns(image, 0..50).unwrap().1 as i32;
+        assert!((right(&image) - right(&expected)).abs() <= 1);
+    }
+
+    #[test]
+    fn test_draw_text_opacity_mut() {
+        let font = font();
+        let scale = Scale::uniform(20.0);
+        let draw = |opacity: f32| {
+            let mut image = GrayImage::from_pixel(80, 30, Luma([100u8]));
+            draw_text_opacity_mut(
+                &mut image,
+                Luma([200u8]),
+                2,
+                2,
+                opacity,
+                scale,
+                &font,
+                "Hello",
+            );
+            image
+        };
+
+        let mut opaque = GrayImage::from_pixel(80, 30, Luma([100u8]));
+        draw_text_mut(&mut opaque, Luma([200u8]), 2, 2, scale, &font, "Hello");
+        assert_pixels_eq!(draw(1.0), opaque);
+        assert_pixels_eq!(draw(3.0), opaque);
+        assert_pixels_eq!(draw(-1.0), GrayImage::from_pixel(80, 30, Luma([100u8])));
+
+        // Fully covered pixels move 40% of the way towards the text color
+        let faint = draw(0.4);
+        let (x, y) = (0..80)
+            .flat_map(|x| (0..30).map(move |y| (x, y)))
+            .find(|&(x, y)| opaque.get_pixel(x, y)[0] == 200)
+            .unwrap();
+        assert_eq!(faint.get_pixel(x, y)[0], 140);
+    }
+
+    #[test]
+    fn test_draw_justified_text_mut() {
+        let font = font();
+        let scale = Scale::uniform(20.0);
+        let lines = [
+            "the quick brown",
+            "fox",
+            "jumps over",
+            "",
+            "the lazy dog",
+            "and cat",
+        ];
+        let target_width = 200;
+        let mut image = GrayImage::new(260, 140);
+        draw_justified_text_mut(
+            &mut image,
+            Luma([255u8]),
+            10,
+            0,
+            target_width,
+            scale,
+            &font,
+            &lines,
+        );
+
+        let line_height = line_advance(&font.v_metrics(scale)).round() as u32;
+        let line_ink = |i: u32| ink_columns(&image, i * line_height + 2..(i + 1) * line_height - 4);
+        let left_aligned = |i: u32| {
+            let mut expected = GrayImage::new(260, 140);
+            draw_text_mut(
+                &mut expected,
+                Luma([255u8]),
+                10,
+                0,
+                scale,
+                &font,
+                &lines[..i as usize + 1].join("\n"),
+            );
+            ink_columns(&expected, i * line_height + 2..(i + 1) * line_height - 4)
+        };
+
+        // Justified lines end at the target width
+        for &i in &[0, 4] {
+            let (left, right) = line_ink(i).unwrap();
+            assert_eq!(Some(left), left_aligned(i).map(|c| c.0));
+            assert!((right as i32 - (10 + target_width as i32 - 1)).abs() <= 1);
+        }
+        // Single words and the last line of each paragraph are left-aligned
+        for &i in &[1, 2, 5] {
+            assert_eq!(line_ink(i), left_aligned(i));
+        }
+        assert_eq!(line_ink(3), None);
+    }
+
+    #[test]
+    fn test_glyph_string_width() {
+        let font = font();
+        let scale = Scale::uniform(20.0);
+        let advance = |c: char| font.glyph(c).scaled(scale).h_metrics().advance_width;
+
+        assert_eq!(GlyphString::new(scale, &font, "").width(), 0);
+        assert_eq!(
+            GlyphString::new(scale, &font, "l").width(),
+            advance('l').ceil() as u32
+        );
+        let pair_kerning = font.pair_kerning(scale, font.glyph('A').id(), font.glyph('V').id());
+        assert_eq!(
+            GlyphString::new(scale, &font, "AV").width(),
+            (advance('A') + advance('V') + pair_kerning).ceil() as u32
+        );
+    }
+
+    #[test]
+    fn test_glyph_string_draw_positioned_mut_centers_text() {
+        let font = font();
+        let scale = Scale::uniform(20.0);
+        let mut image = GrayImage::new(100, 40);
+        let rect = IpRect::at(0, 0).of_size(100, 40);
+        let position = Position::HorizontalCenter(EdgePosition::center());
+        GlyphString::new(scale, &font, "HH").draw_positioned_mut(
+            &mut image,
+            Luma([255u8]),
+            &position,
+            &rect,
+        );
+
+        // 'H' has equal side bearings, so the ink is centered
+        let (left, right) = ink_columns(&image, 0..40).unwrap();
+        assert!((left as i32 - (99 - right as i32)).abs() <= 1);
+    }
+
+    #[test]
+    fn test_glyph_string_draw_copies_image() {
+        let font = font();
+        let scale = Scale::uniform(20.0);
+        let string = GlyphString::new(scale, &font, "Hi");
+        let image = GrayImage::from_fn(70, 30, |x, y| Luma([(x + y) as u8]));
+
+        let mut expected = image.clone();
+        string.draw_mut(&mut expected, Luma([255u8]), 3, 4);
+        assert_pixels_eq!(string.draw(&image, Luma([255u8]), 3, 4), expected);
+
+        let rect = IpRect::at(0, 0).of_size(70, 30);
+        let position = Position::Any(EdgePosition::center(), EdgePosition::center());
+        let mut expected = image.clone();
+        string.draw_positioned_mut(&mut expected, Luma([255u8]), &position, &rect);
+        assert_pixels_eq!(
+            string.draw_positioned(&image, Luma([255u8]), &position, &rect),
+            expected
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "color-glyphs")]
+    fn test_draw_text_with_color_glyphs_mut() {
+        use image::{Rgba, RgbaImage};
+        let scale = Scale::uniform(20.0);
+        let white = Rgba([255u8, 255, 255, 255]);
+        let red = Rgba([255u8, 0, 0, 255]);
+        let background = Rgba([0u8, 0, 0, 255]);
+
+        // A square with a transparent right half, whose bottom lies 2 pixels below the baseline
+        let emoji = RgbaImage::from_fn(8, 8, |x, _| if x < 4 { red } else { Rgba([0, 0, 0, 0]) });
+        let data = font_data_with_color_glyph('*', &emoji, 20, (1, -2));
+        let font = Font::try_from_vec(data).unwrap();
+        let ascent = font.v_metrics(scale).ascent;
+
+        let mut image = RgbaImage::from_pixel(100, 40, background);
+        draw_text_with_color_glyphs_mut(&mut image, white, 10, 5, scale, &font, "*l");
+        let top = 5 + (ascent - 6.0).round() as u32;
+        let is_red = |x: u32, y: u32| *image.get_pixel(x, y) == red;
+        assert!(is_red(11, top) && is_red(14, top + 7));
+        assert!(!is_red(11, top - 1) && !is_red(11, top + 8) && !is_red(10, top));
+        assert_eq!(*image.get_pixel(15, top + 3), background);
+
+        // Other glyphs are drawn from their outlines, after the advance of the color glyph
+        let mut expected = RgbaImage::from_pixel(100, 40, background);
+        draw_text_mut(&mut expected, white, 10, 5, scale, &font, "*l");
+        let advance = font.glyph('*').scaled(scale).h_metrics().advance_width;
+        for (x, y, p) in expected.enumerate_pixels() {
+            if x >= 10 + advance.ceil() as u32 {
+                assert_eq!(image.get_pixel(x, y), p);
+            }
+        }
+
+        // Images are resized from the size of their strike, and converted to the canvas pixel type
+        let mut gray = GrayImage::new(100, 60);
+        let large = Scale::uniform(40.0);
+        draw_text_with_color_glyphs_mut(&mut gray, Luma([255u8]), 10, 5, large, &font, "*");
+        let red_luma = red.to_luma()[0];
+        let top = 5 + (font.v_metrics(large).ascent - 12.0).round() as u32;
+        assert_eq!(gray.get_pixel(12, top)[0], red_luma);
+        assert_eq!(gray.get_pixel(18, top + 15)[0], red_luma);
+        assert_eq!(gray.get_pixel(11, top + 8)[0], 0);
+        assert_eq!(gray.get_pixel(22, top + 8)[0], 0);
+        assert_eq!(gray.get_pixel(12, top + 16)[0], 0);
+
+        // A negative scale mirrors both color and outline glyphs within the text box
+        let size = text_size(scale, &font, "*l");
+        let draw = |scale| {
+            let mut image = GrayImage::new(size.0 as u32 + 20, size.1 as u32 + 20);
+            draw_text_with_color_glyphs_mut(&mut image, Luma([255u8]), 10, 10, scale, &font, "*l");
+            image
+        };
+        let expected = mirror_text_box(&draw(scale), 10, size, Flip::Horizontal);
+        assert_pixels_eq!(draw(Scale { x: -20.0, y: 20.0 }), expected);
+
+        // Fonts without color glyphs are drawn as by draw_text_mut
+        let plain = self::font();
+        let mut image = RgbaImage::from_pixel(100, 40, background);
+        draw_text_with_color_glyphs_mut(&mut image, white, 10, 5, scale, &plain, "*l");
+        let mut expected = RgbaImage::from_pixel(100, 40, background);
+        draw_text_mut(&mut expected, white, 10, 5, scale, &plain, "*l");
+        assert_pixels_eq!(image, expected);
+    }
+
+    #[test]
+    fn test_draw_text_flipped_mut() {
+        let font = font();
+        let scale = Scale::uniform(20.0);
+        // Text without descenders is still mirrored within the box reaching down to the descent
+        for &text in &["Fly", "ACE"] {
+            test_draw_text_flipped_mut_text(&font, scale, text);
+        }
+    }
+
+    fn test_draw_text_flipped_mut_text(font: &Font, scale: Scale, text: &str) {
+        let (w, h) = text_size(scale, font, text);
+        let draw = |flip: Flip| {
+            let mut image = GrayImage::new(w as u32 + 10, h as u32 + 10);
+            draw_text_flipped_mut(&mut image, Luma([255u8]), 5, 5, flip, scale, font, text);
+            image
+        };
+
+        let normal = draw(Flip::None);
+        let mut expected = GrayImage::new(w as u32 + 10, h as u32 + 10);
+        draw_text_mut(&mut expected, Luma([255u8]), 5, 5, scale, font, text);
+        assert_pixels_eq!(normal, expected);
+
+        let mirror = |image: &GrayImage, flip_x: bool, flip_y: bool| {
+            GrayImage::from_fn(image.width(), image.height(), |x, y| {
+                let (tx, ty) = (x as i32 - 5, y as i32 - 5);
+                if tx < 0 || ty < 0 || tx >= w || ty >= h {
+                    return Luma([0u8]);
+                }
+                let sx = if flip_x { w - 1 - tx } else { tx };
+                let sy = if flip_y { h - 1 - ty } else { ty };
+                *image.get_pixel(sx as u32 + 5, sy as u32 + 5)
+            })
+        };
+        assert_pixels_eq!(draw(Flip::Horizontal), mirror(&normal, true, false));
+        assert_pixels_eq!(draw(Flip::Vertical), mirror(&normal, false, true));
+        assert_pixels_eq!(draw(Flip::Both), mirror(&normal, true, true));
+    }
+
+    #[test]
+    fn test_glyph_rects() {
+        let font = font();
+        let scale = Scale::uniform(20.0);
+        let text = "Hi there\nyou";
+        let rects = glyph_rects(scale, &font, text);
+
+        let chars: String = rects.iter().map(|(c, _)| c).collect();
+        assert_eq!(chars, "Hithereyou");
+
+        // Together the rectangles cover exactly the drawn pixels
+        let (w, h) = text_size(scale, &font, text);
+        let mut image = GrayImage::new(w as u32, h as u32);
+        draw_text_mut(&mut image, Luma([255u8]), 0, 0, scale, &font, text);
+        for (x, y, p) in image.enumerate_pixels() {
+            if p[0] > 0 {
+                assert!(rects.iter().any(|(_, r)| r.contains(x as i32, y as i32)));
+            }
+        }
+        let right = rects.iter().map(|(_, r)| r.right() + 1).max().unwrap();
+        let bottom = rects.iter().map(|(_, r)| r.bottom() + 1).max().unwrap();
+        assert_eq!((right, bottom), (w, h));
+
+        // Characters on the second line lie below those on the first
+        let (_, h_rect) = rects[0];
+        let (_, y_rect) = rects[rects.len() - 3];
+        assert!(y_rect.top() > h_rect.bottom());
+    }
+
+    #[test]
+    fn test_glyph_string_multiple_lines() {
+        let font = font();
+        let scale = Scale::uniform(20.0);
+        let single = GlyphString::new(scale, &font, "Hello");
+        let short = GlyphString::new(scale, &font, "Hi");
+        let multi = GlyphString::new(scale, &font, "Hi\nHello");
+
+        assert_eq!(multi.width(), single.width());
+        assert_eq!(multi.height(), 2 * single.height());
+        assert_eq!(single.height(), single.line_height());
+        assert_eq!(GlyphString::new(scale, &font, "").height(), 0);
+
+        // Each line is drawn one line height below the previous one
+        let mut actual = GrayImage::new(80, 60);
+        multi.draw_mut(&mut actual, Luma([255u8]), 2, 3);
+        let mut expected = GrayImage::new(80, 60);
+        short.draw_mut(&mut expected, Luma([255u8]), 2, 3);
+        single.draw_mut(&mut expected, Luma([255u8]), 2, 3 + single.line_height());
+        assert_pixels_eq!(actual, expected);
+
+        let spaced = GlyphString::new(scale, &font, "Hi\nHello")
+            .with_line_spacing(LineSpacing::Pixels(30.0));
+        assert_eq!(spaced.line_height(), 30);
+        assert_eq!(spaced.height(), 60);
+    }
+
+    #[test]
+    fn test_aliased_text() {
+        let font = font();
+        let scale = Scale::uniform(20.0);
+        let style = TextStyle::new(&font, scale, Luma([200u8]));
+        let draw = |style: TextStyle<Luma<u8>>| {
+            let mut image = GrayImage::from_pixel(80, 30, Luma([10u8]));
+            let bounds = style.draw_mut(&mut image, 2, 2, "Hello");
+            (image, bounds)
+        };
+
+        let (smooth, _) = draw(style);
+        let (crisp, bounds) = draw(style.aliasing(Aliasing::Aliased));
+        assert!(smooth.pixels().any(|p| p[0] != 10 && p[0] != 200));
+        assert!(crisp.pixels().all(|p| p[0] == 10 || p[0] == 200));
+
+        // Pixels are set exactly when they are at least half covered
+        for (x, y, p) in crisp.enumerate_pixels() {
+            let coverage = (smooth.get_pixel(x, y)[0] as f32 - 10.0) / 190.0;
+            if (coverage - 0.5).abs() > 0.01 {
+                assert_eq!(p[0] == 200, coverage > 0.5);
+            }
+        }
+        let bounds = bounds.unwrap();
+        for (x, y, p) in crisp.enumerate_pixels() {
+            if p[0] == 200 {
+                assert!(bounds.contains(x as i32, y as i32));
+            }
+        }
+    }
+
+    #[test]
+    fn test_text_style_matches_free_functions() {
+        let font = font();
+        let scale = Scale::uniform(20.0);
+        let text = "Hello\nWorld!";
+        let style = TextStyle::new(&font, Scale::uniform(10.0), Luma([1u8]))
+            .scale(scale)
+            .color(Luma([255u8]));
+        let draw = |f: &dyn Fn(&mut GrayImage)| {
+            let mut image = GrayImage::new(120, 80);
+            f(&mut image);
+            image
+        };
+
+        assert_pixels_eq!(
+            draw(&|image| {
+                style.tracking(2.0).draw_mut(image, 5, 5, text);
+            }),
+            draw(&|image| draw_text_spaced_mut(
+                image,
+                Luma([255u8]),
+                5,
+                5,
+                2.0,
+                scale,
+                &font,
+                text
+            ))
+        );
+        assert_eq!(
+            style.tracking(2.0).size(text),
+            spaced_text_size(scale, &font, text, 2.0)
+        );
+
+        let spacing = LineSpacing::Multiplier(1.5);
+        assert_pixels_eq!(
+            draw(&|image| {
+                style
+                    .align(HorizontalAlign::Center)
+                    .line_spacing(spacing)
+                    .draw_mut(image, 5, 5, text);
+            }),
+            draw(&|image| draw_text_aligned_mut(
+                image,
+                Luma([255u8]),
+                5,
+                5,
+                HorizontalAlign::Center,
+                spacing,
+                scale,
+                &font,
+                text
+            ))
+        );
+
+        let decoration = TextDecoration::UNDERLINE | TextDecoration::STRIKETHROUGH;
+        assert_pixels_eq!(
+            draw(&|image| {
+                style
+                    .decoration(decoration, Some(2.0))
+                    .draw_mut(image, 5, 5, text);
+            }),
+            draw(&|image| draw_text_decorated_mut(
+                image,
+                Luma([255u8]),
+                5,
+                5,
+                decoration,
+                Some(2.0),
+                scale,
+                &font,
+                text
+            ))
+        );
+    }
+
+    #[test]
+    fn test_text_style_outline() {
+        let font = font();
+        let scale = Scale::uniform(20.0);
+        let style = TextStyle::new(&font, scale, Luma([255u8])).outline(Luma([100u8]), 2);
+
+        let mut image = GrayImage::new(80, 40);
+        let bounds = style.draw_mut(&mut image, 5, 5, "Hi").unwrap();
+        let mut expected = GrayImage::new(80, 40);
+        draw_text_outlined_mut(
+            &mut expected,
+            Luma([255u8]),
+            Luma([100u8]),
+            2,
+            5,
+            5,
+            scale,
+            &font,
+            "Hi",
+        );
+
+        // The fill is blended directly rather than through a rounded mask, so may differ by one
+        for (x, y, p) in image.enumerate_pixels() {
+            assert!((p[0] as i32 - expected.get_pixel(x, y)[0] as i32).abs() <= 1);
+            if p[0] > 0 {
+                assert!(bounds.contains(x as i32, y as i32));
+            }
+        }
+        assert!(image.pixels().any(|p| p[0] == 100));
+    }
+
+    #[test]
+    fn test_text_style_decorations_follow_alignment() {
+        let font = font();
+        let scale = Scale::uniform(20.0);
+        let style = TextStyle::new(&font, scale, Luma([255u8]))
+            .align(HorizontalAlign::Right)
+            .decoration(TextDecoration::UNDERLINE, Some(2.0));
+        let mut image = GrayImage::new(120, 60);
+        style.draw_mut(&mut image, 0, 0, "Hello there\nHi");
+
+        let (w, _) = style.size("Hello there\nHi");
+        let short = text_size(scale, &font, "Hi").0;
+        let v_metrics = font.v_metrics(scale);
+        let baseline = v_metrics.ascent + line_advance(&v_metrics);
+        let underline = (baseline - v_metrics.descent / 2.0 - 1.0).round() as u32;
+        assert_eq!(
+            ink_columns(&image, underline..underline + 1),
+            Some(((w - short) as u32, w as u32 - 1))
+        );
+    }
+
+    #[test]
+    fn test_layout_matches_draw_text_mut() {
+        let font = font();
+        let scale = Scale::uniform(20.0);
+        let text = "Hello,\nWorld!";
+        let layout = Layout::new(scale, &font, text);
+        assert_eq!(layout.size(), text_size(scale, &font, text));
+        assert_eq!(layout.scale(), scale);
+
+        let mut expected = GrayImage::new(100, 60);
+        let expected_bounds =
+            draw_text_bounded_mut(&mut expected, Luma([255u8]), 3, 4, scale, &font, text);
+        let mut actual = GrayImage::new(100, 60);
+        let bounds = layout.draw_mut(&mut actual, Luma([255u8]), 3, 4);
+        assert_pixels_eq!(actual, expected);
+        assert_eq!(bounds, expected_bounds);
+
+        // Layouts made from a style keep its arrangement
+        let style = TextStyle::new(&font, scale, Luma([255u8]))
+            .align(HorizontalAlign::Right)
+            .synthetic_bold(1.0);
+        let layout = style.layout(text);
+        assert_eq!(layout.size(), style.size(text));
+        let mut expected = GrayImage::new(100, 60);
+        style.draw_mut(&mut expected, 3, 4, text);
+        let mut actual = GrayImage::new(100, 60);
+        layout.draw_mut(&mut actual, Luma([255u8]), 3, 4);
+        assert_pixels_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_draw_wrapped_positioned_mut_centers_block() {
+        let font = font();
+        let scale = Scale::uniform(20.0);
+        let text = "HHH HHH HHH";
+        let rect = IpRect::at(10, 0).of_size(60, 200);
+        let lines = wrap_text(scale, &font, text, rect.width());
+        assert_eq!(lines.len(), 3);
+
+        let mut image = GrayImage::new(80, 200);
+        let position = Position::Any(EdgePosition::center(), EdgePosition::center());
+        draw_wrapped_positioned_mut(
+            &mut image,
+            Luma([255u8]),
+            &position,
+            &rect,
+            scale,
+            &font,
+            text,
+        );
+
+        let wrapped = lines.join("\n");
+        let string = GlyphString::new(scale, &font, &wrapped);
+        assert_eq!(string.height(), 3 * string.line_height());
+        let mut expected = GrayImage::new(80, 200);
+        string.draw_positioned_mut(&mut expected, Luma([255u8]), &position, &rect);
+        assert_pixels_eq!(image, expected);
+
+        let top = (0..200)
+            .find(|&y| (0..80).any(|x| image.get_pixel(x, y)[0] > 0))
+            .unwrap();
+        let bottom = (0..200)
+            .rev()
+            .find(|&y| (0..80).any(|x| image.get_pixel(x, y)[0] > 0))
+            .unwrap();
+        let block_top = (200 - string.height()) / 2;
+        assert!(top >= block_top && bottom < block_top + string.height());
+    }
+
+    #[test]
+    fn test_draw_text_mut_transparent_canvas_has_no_dark_fringes() {
+        use image::{Rgba, RgbaImage};
+        let font = font();
+        let scale = Scale::uniform(20.0);
+        let color = Rgba([255u8, 200, 100, 255]);
+        let mut image = RgbaImage::from_pixel(60, 30, Rgba([0u8, 0, 0, 0]));
+        draw_text_mut(&mut image, color, 2, 2, scale, &font, "Hello");
+
+        // Every touched pixel has the text color, with alpha given by coverage
+        let touched: Vec<_> = image.pixels().filter(|p| p[3] > 0).collect();
+        assert!(touched.iter().any(|p| p[3] < 255));
+        assert!(touched
+            .iter()
+            .all(|p| p[0] == 255 && p[1] == 200 && p[2] == 100));
+    }
+
+    #[test]
+    fn test_draw_text_mut_translucent_color_over_opaque_canvas() {
+        use image::{Rgba, RgbaImage};
+        let font = font();
+        let scale = Scale::uniform(40.0);
+        let mut image = RgbaImage::from_pixel(40, 50, Rgba([0u8, 0, 255, 255]));
+        draw_text_mut(
+            &mut image,
+            Rgba([255u8, 0, 0, 128]),
+            2,
+            2,
+            scale,
+            &font,
+            "l",
+        );
+
+        // The canvas stays opaque, and fully covered pixels are an even mix of both colors
+        assert!(image.pixels().all(|p| p[3] == 255));
+        assert!(image
+            .pixels()
+            .any(|p| (p[0] as i32 - 128).abs() <= 1 && (p[2] as i32 - 127).abs() <= 1));
+    }
+
+    #[test]
+    fn test_draw_text_mut_opaque_canvas_matches_weighted_sum() {
+        use image::{Rgb, RgbImage, Rgba, RgbaImage};
+        let font = font();
+        let scale = Scale::uniform(20.0);
+        let mut rgba = RgbaImage::from_pixel(60, 30, Rgba([10u8, 20, 30, 255]));
+        let mut rgb = RgbImage::from_pixel(60, 30, Rgb([10u8, 20, 30]));
+        draw_text_mut(
+            &mut rgba,
+            Rgba([200u8, 150, 100, 255]),
+            2,
+            2,
+            scale,
+            &font,
+            "Hello",
+        );
+        draw_text_mut(
+            &mut rgb,
+            Rgb([200u8, 150, 100]),
+            2,
+            2,
+            scale,
+            &font,
+            "Hello",
+        );
+
+        for (a, b) in rgba.pixels().zip(rgb.pixels()) {
+            assert_eq!(a[3], 255);
+            for c in 0..3 {
+                assert!((a[c] as i32 - b[c] as i32).abs() <= 1);
+            }
+        }
+    }
+
+    #[test]
+    fn test_draw_text_scaled_ramp_mut() {
+        let font = font();
+        let (start, end) = (Scale::uniform(10.0), Scale::uniform(40.0));
+        let (w, h) = scaled_ramp_text_size(start, end, &font, "IIII");
+        let mut image = GrayImage::new(100, 60);
+        draw_text_scaled_ramp_mut(&mut image, Luma([255u8]), 0, 0, start, end, &font, "IIII");
+
+        // Each 'I' is a separate column of ink, taller than the one before, resting on the same baseline
+        let inked = |x: u32| {
+            (0..60)
+                .filter(|&y| image.get_pixel(x, y)[0] > 0)
+                .collect::<Vec<_>>()
+        };
+        let mut glyphs: Vec<(u32, u32)> = vec![];
+        for x in 0..100 {
+            let column = inked(x);
+            if !column.is_empty() && (x == 0 || inked(x - 1).is_empty()) {
+                glyphs.push((column[0], column[column.len() - 1]));
+            }
+        }
+        assert_eq!(glyphs.len(), 4);
+        for pair in glyphs.windows(2) {
+            assert!(pair[1].0 < pair[0].0);
+            assert!((pair[1].1 as i32 - pair[0].1 as i32).abs() <= 1);
+        }
+
+        let (left, right) = ink_columns(&image, 0..60).unwrap();
+        assert!(left < 2 && right as i32 == w - 1);
+        assert_eq!(glyphs.last().unwrap().1 as i32, h - 1);
+    }
+
+    #[test]
+    fn test_break_opportunities() {
+        assert_eq!(break_opportunities("hello world"), vec![6]);
+        assert_eq!(break_opportunities("a  b\nc"), vec![3, 5]);
+        assert_eq!(break_opportunities("well-known -x"), vec![5, 11]);
+        assert_eq!(break_opportunities("trailing "), Vec::<usize>::new());
+        assert_eq!(break_opportunities(""), Vec::<usize>::new());
+
+        // Each ideograph is three bytes long in UTF-8, and no break is allowed before the space
+        assert_eq!(break_opportunities("日本語 ok"), vec![3, 6, 10]);
+        assert_eq!(break_opportunities("abc日本"), vec![6]);
+    }
+
+    #[test]
+    fn test_ink_bounds() {
+        let font = font();
+        let scale = Scale::uniform(30.0);
+        assert_eq!(ink_bounds(scale, &font, ""), None);
+        assert_eq!(ink_bounds(scale, &font, "   "), None);
+
+        let mut image = GrayImage::new(200, 40);
+        draw_text_mut(&mut image, Luma([255u8]), 0, 0, scale, &font, "ace");
+        let ink = ink_bounds(scale, &font, "ace").unwrap();
+        let (w, h) = text_size(scale, &font, "ace");
+        assert_eq!(ink.right() + 1, w);
+        assert!(ink.bottom() < h);
+        let (left, right) = ink_columns(&image, 0..40).unwrap();
+        assert!(ink.left() <= left as i32 && right as i32 <= ink.right());
+
+        // Lowercase letters start lower than capitals
+        let upper = ink_bounds(scale, &font, "ACE").unwrap();
+        assert!(ink.top() > upper.top());
+    }
+
+    #[test]
+    fn test_logical_bounds() {
+        let font = font();
+        let scale = Scale::uniform(30.0);
+        assert_eq!(logical_bounds(scale, &font, ""), None);
+
+        let lower = logical_bounds(scale, &font, "ace").unwrap();
+        let upper = logical_bounds(scale, &font, "ACE").unwrap();
+        assert_eq!((lower.left(), lower.top()), (0, 0));
+        assert_eq!(lower.height(), upper.height());
+        assert_eq!(lower.width(), GlyphString::new(scale, &font, "ace").width());
+
+        let v_metrics = font.v_metrics(scale);
+        let two_lines = logical_bounds(scale, &font, "ace\nACE").unwrap();
+        let expected = v_metrics.ascent - v_metrics.descent + line_advance(&v_metrics);
+        assert_eq!(two_lines.height(), expected.ceil() as u32);
+        assert_eq!(two_lines.width(), max(lower.width(), upper.width()));
+    }
+
+    #[test]
+    fn test_draw_text_mut_luma16() {
+        let font = font();
+        let scale = Scale::uniform(20.0);
+        let mut narrow = GrayImage::new(80, 30);
+        let mut deep = Image::<Luma<u16>>::new(80, 30);
+        draw_text_mut(&mut narrow, Luma([255u8]), 2, 2, scale, &font, "Hello");
+        draw_text_mut(&mut deep, Luma([u16::MAX]), 2, 2, scale, &font, "Hello");
+
+        // The same coverage is used at both depths, but edges are not quantized to 8 bits
+        for (n, d) in narrow.pixels().zip(deep.pixels()) {
+            assert!((n[0] as i32 * 257 - d[0] as i32).abs() <= 257);
+        }
+        assert!(deep.pixels().any(|p| p[0] % 257 != 0));
+        assert!(deep.pixels().any(|p| p[0] == u16::MAX));
+    }
+
+    #[test]
+    fn test_draw_text_outlined_mut_luma16_is_not_quantized() {
+        let font = font();
+        let scale = Scale::uniform(20.0);
+        let mut image = Image::<Luma<u16>>::new(90, 40);
+        let (fill, outline) = (Luma([u16::MAX]), Luma([u16::MAX / 2]));
+        draw_text_outlined_mut(&mut image, fill, outline, 2, 4, 4, scale, &font, "Hello");
+
+        assert!(image.pixels().any(|p| p[0] % 257 != 0 && p[0] % 257 != 128));
+        assert!(image.pixels().any(|p| p[0] == u16::MAX));
+    }
+
+    #[test]
+    fn test_draw_text_mut_rgb16_matches_luma16() {
+        use image::Rgb;
+        let font = font();
+        let scale = Scale::uniform(20.0);
+        let mut luma = Image::<Luma<u16>>::new(80, 30);
+        let mut rgb = Image::<Rgb<u16>>::new(80, 30);
+        draw_text_mut(&mut luma, Luma([u16::MAX]), 2, 2, scale, &font, "Hello");
+        draw_text_mut(&mut rgb, Rgb([u16::MAX; 3]), 2, 2, scale, &font, "Hello");
+
+        for (l, c) in luma.pixels().zip(rgb.pixels()) {
+            assert_eq!(*c, Rgb([l[0]; 3]));
+        }
+    }
+
+    #[test]
+    fn test_caret_position() {
+        let font = font();
+        let scale = Scale::uniform(20.0);
+        let ascent = font.v_metrics(scale).ascent.round() as i32;
+        assert_eq!(caret_position(scale, &font, "", 0), (0, ascent));
+        assert_eq!(caret_position(scale, &font, "AV", 0), (0, ascent));
+
+        // The caret between 'A' and 'V' includes their kerning
+        let (x, _) = caret_position(scale, &font, "AV", 1);
+        let a = font.glyph('A').scaled(scale);
+        let kerning = font.pair_kerning(scale, a.id(), font.glyph('V').id());
+        let expected = a.h_metrics().advance_width + kerning;
+        assert!(kerning < 0.0);
+        assert_eq!(x, expected.round() as i32);
+
+        let end = GlyphString::new(scale, &font, "AV").width() as i32;
+        assert!((caret_position(scale, &font, "AV", 2).0 - end).abs() <= 1);
+        assert_eq!(
+            caret_position(scale, &font, "AV", 10),
+            caret_position(scale, &font, "AV", 2)
+        );
+
+        // After a newline the caret is at the start of the next line
+        let advance = line_advance(&font.v_metrics(scale));
+        let second = (font.v_metrics(scale).ascent + advance).round() as i32;
+        assert_eq!(caret_position(scale, &font, "AV\nW", 3), (0, second));
+    }
+
+    #[test]
+    fn test_caret_position_includes_tracking() {
+        let font = font();
+        let scale = Scale::uniform(20.0);
+        let style = TextStyle::new(&font, scale, Luma([255u8])).tracking(5.0);
+        let (plain, _) = caret_position(scale, &font, "Hello", 3);
+        let (tracked, _) = style.caret_position("Hello", 3);
+        assert_eq!(tracked - plain, 15);
+    }
+
+    #[test]
+    fn test_draw_caret_mut() {
+        let font = font();
+        let scale = Scale::uniform(20.0);
+        let mut image = GrayImage::new(80, 40);
+        draw_caret_mut(&mut image, Luma([255u8]), 5, 3, scale, &font, "Hello", 2);
+
+        let (cx, _) = caret_position(scale, &font, "Hello", 2);
+        let v_metrics = font.v_metrics(scale);
+        let height = (v_metrics.ascent - v_metrics.descent).round() as usize;
+        let inked: Vec<_> = image.enumerate_pixels().filter(|p| p.2[0] > 0).collect();
+        assert!(inked.iter().all(|&(x, _, _)| x as i32 == 5 + cx));
+        assert!((inked.len() as i32 - height as i32).abs() <= 1);
+        assert_eq!(inked[0].1, 3);
+    }
+
+    #[test]
+    fn test_variable_font_ignores_missing_axes() {
+        let data = include_bytes!("../../examples/DejaVuSans.ttf");
+        assert!(variable_font(&[0u8; 4], &[]).is_none());
+
+        // DejaVu Sans is not a variable font, so only the default instance is available
+        let font = font();
+        let varied = variable_font(data, &[FontVariation::weight(700.0)]).unwrap();
+        let scale = Scale::uniform(20.0);
+        let mut expected = GrayImage::new(80, 30);
+        let mut image = GrayImage::new(80, 30);
+        draw_text_mut(&mut expected, Luma([255u8]), 2, 2, scale, &font, "Hello");
+        draw_text_mut(&mut image, Luma([255u8]), 2, 2, scale, &varied, "Hello");
+        assert_pixels_eq!(image, expected);
+    }
+
+    #[test]
+    fn test_variable_font_weight() {
+        let data = &variable_font_data();
+        let scale = Scale::uniform(24.0);
+        let at_weight = |weight| variable_font(data, &[FontVariation::weight(weight)]).unwrap();
+        let draw = |font: &Font| {
+            let mut image = GrayImage::new(120, 40);
+            draw_text_mut(&mut image, Luma([255u8]), 2, 2, scale, font, "III");
+            image
+        };
+        let ink = |image: &GrayImage| image.pixels().map(|p| p[0] as u32).sum::<u32>();
+        let advance = |font: &Font| font.glyph('I').scaled(scale).h_metrics().advance_width;
+
+        let (thin, bold) = (at_weight(100.0), at_weight(800.0));
+        let default = variable_font(data, &[]).unwrap();
+        assert_pixels_eq!(draw(&at_weight(400.0)), draw(&default));
+
+        // Heavier weights have thicker outlines and wider advances
+        assert!(ink(&draw(&thin)) < ink(&draw(&default)));
+        assert!(ink(&draw(&default)) < ink(&draw(&bold)));
+        assert!(advance(&thin) < advance(&bold));
+        assert!(text_size(scale, &thin, "III").0 < text_size(scale, &bold, "III").0);
+
+        // Values outside the axis are clamped to it
+        assert_pixels_eq!(draw(&at_weight(2000.0)), draw(&bold));
+
+        // The font has no width axis, so only the weight applies
+        let variations = [FontVariation::width(50.0), FontVariation::weight(800.0)];
+        assert_pixels_eq!(
+            draw(&variable_font(data, &variations).unwrap()),
+            draw(&bold)
+        );
+    }
+
+    #[test]
+    fn test_draw_text_report_mut() {
+        let font = font();
+        let scale = Scale::uniform(20.0);
+        let mut image = GrayImage::new(100, 30);
+        let report =
+            draw_text_report_mut(&mut image, Luma([255u8]), 2, 2, scale, &font, "Hi there");
+        assert_eq!(
+            report,
+            TextDrawReport {
+                glyphs_drawn: 7,
+                glyphs_clipped: 0,
+                contained: true
+            }
+        );
+
+        let mut expected = GrayImage::new(100, 30);
+        draw_text_mut(&mut expected, Luma([255u8]), 2, 2, scale, &font, "Hi there");
+        assert_pixels_eq!(image, expected);
+
+        // Only the first 'H' is cut in half
+        let (w, _) = text_size(scale, &font, "H");
+        let mut image = GrayImage::new(100, 30);
+        let report =
+            draw_text_report_mut(&mut image, Luma([255u8]), -w / 2, 2, scale, &font, "HHH");
+        assert_eq!(
+            report,
+            TextDrawReport {
+                glyphs_drawn: 3,
+                glyphs_clipped: 1,
+                contained: false
+            }
+        );
+
+        let report = draw_text_report_mut(&mut image, Luma([255u8]), 0, 40, scale, &font, "HHH");
+        assert_eq!((report.glyphs_drawn, report.glyphs_clipped), (0, 3));
+    }
+
+    #[test]
+    fn test_draw_vertical_text_mut() {
+        let font = font();
+        let scale = Scale::uniform(20.0);
+        let v_metrics = font.v_metrics(scale);
+        let advance = v_metrics.ascent - v_metrics.descent;
+
+        // DejaVu Sans has no vertical metrics, so every glyph advances by the line height
+        let (w, h) = vertical_text_size(scale, &font, "HHH");
+        assert_eq!((w, h), (20, (3.0 * advance).ceil() as i32));
+        assert!(h > w);
+        assert_eq!(
+            vertical_text_size(scale, &font, "HH\nH"),
+            (40, (2.0 * advance).ceil() as i32)
+        );
+
+        let mut image = GrayImage::new(40, 80);
+        draw_vertical_text_mut(&mut image, Luma([255u8]), 10, 5, scale, &font, "HHH");
+
+        // Each 'H' is centered in the column, and the three are stacked one advance apart
+        let (left, right) = ink_columns(&image, 0..80).unwrap();
+        assert!((left as i32 - 10 - (30 - 1 - right as i32)).abs() <= 1);
+        let rows: Vec<u32> = (0..80)
+            .filter(|&y| (0..40).any(|x| image.get_pixel(x, y)[0] > 0))
+            .collect();
+        let tops: Vec<u32> = rows
+            .iter()
+            .copied()
+            .filter(|&y| y == rows[0] || !rows.contains(&(y - 1)))
+            .collect();
+        assert_eq!(tops.len(), 3);
+        assert!((tops[1] as f32 - tops[0] as f32 - advance).abs() <= 1.0);
+        assert!((tops[2] as f32 - tops[1] as f32 - advance).abs() <= 1.0);
+    }
+
+    #[test]
+    fn test_draw_text_hollow_mut() {
+        let font = font();
+        let scale = Scale::uniform(60.0);
+        let mut filled = GrayImage::new(60, 70);
+        let mut hollow = GrayImage::new(60, 70);
+        draw_text_mut(&mut filled, Luma([255u8]), 5, 0, scale, &font, "O");
+        draw_text_hollow_mut(&mut hollow, Luma([255u8]), 1, 5, 0, scale, &font, "O");
+
+        // The stroke lies within the glyph, and is thinner than it
+        let count = |image: &GrayImage| image.pixels().filter(|p| p[0] > 0).count();
+        assert!(hollow
+            .pixels()
+            .zip(filled.pixels())
+            .all(|(h, f)| h[0] <= f[0]));
+        assert!(count(&hollow) > 0 && count(&hollow) < count(&filled));
+
+        // Each side of the ring of the 'O' has a fully covered pixel which the stroke leaves empty
+        let rows: Vec<u32> = (0..70)
+            .filter(|&y| (0..60).any(|x| filled.get_pixel(x, y)[0] > 0))
+            .collect();
+        let middle = (rows[0] + rows[rows.len() - 1]) / 2;
+        let solid: Vec<u32> = (0..60)
+            .filter(|&x| filled.get_pixel(x, middle)[0] == 255)
+            .collect();
+        assert!(solid.iter().any(|&x| hollow.get_pixel(x, middle)[0] == 0));
+
+        let mut untouched = GrayImage::new(60, 70);
+        draw_text_hollow_mut(&mut untouched, Luma([255u8]), 0, 5, 0, scale, &font, "O");
+        assert_eq!(count(&untouched), 0);
+    }
+
+    #[test]
+    fn test_rasterize_text() {
+        let font = font();
+        let scale = Scale::uniform(20.0);
+        let (mask, (left, top)) = rasterize_text(scale, &font, "jolly");
+
+        // The mask is tight around the ink
+        let ink = ink_bounds(scale, &font, "jolly").unwrap();
+        assert_eq!((left, top), (ink.left(), ink.top()));
+        assert_eq!(mask.dimensions(), (ink.width(), ink.height()));
+
+        let mut image = GrayImage::new(100, 50);
+        draw_text_mut(&mut image, Luma([255u8]), 10, 10, scale, &font, "jolly");
+        for (x, y, p) in mask.enumerate_pixels() {
+            let (ix, iy) = ((10 + left + x as i32) as u32, (10 + top + y as i32) as u32);
+            assert!((p[0] as i32 - image.get_pixel(ix, iy)[0] as i32).abs() <= 1);
+        }
+
+        let (empty, offset) = rasterize_text(scale, &font, "");
+        assert_eq!((empty.dimensions(), offset), ((0, 0), (0, 0)));
+    }
+
+    #[test]
+    fn test_text_size_reserves_descent() {
+        let font = font();
+        let scale = Scale::uniform(30.0);
+        let (_, tops) = text_size(scale, &font, "Tops");
+        let (_, descenders) = text_size(scale, &font, "gjpqy");
+        assert_eq!(tops, descenders);
+
+        // Every descender fits inside the box
+        let ink = ink_bounds(scale, &font, "gjpqy").unwrap();
+        assert!(ink.bottom() < descenders);
+        let v_metrics = font.v_metrics(scale);
+        assert!(tops as f32 >= v_metrics.ascent - v_metrics.descent);
+    }
+
+    #[test]
+    fn test_draw_text_at_origin_mut() {
+        let font = font();
+        let scale = Scale::uniform(30.0);
+        let draw = |x, y, origin| {
+            let mut image = GrayImage::new(100, 60);
+            draw_text_at_origin_mut(&mut image, Luma([255u8]), x, y, origin, scale, &font, "Hx");
+            image
+        };
+        let ink_rows = |image: &GrayImage| {
+            let rows: Vec<u32> = (0..60)
+                .filter(|&y| (0..100).any(|x| image.get_pixel(x, y)[0] > 0))
+                .collect();
+            (rows[0], rows[rows.len() - 1])
+        };
+
+        let mut expected = GrayImage::new(100, 60);
+        draw_text_mut(&mut expected, Luma([255u8]), 10, 5, scale, &font, "Hx");
+        assert_pixels_eq!(draw(10, 5, Origin::TopLeft), expected);
+
+        // Glyphs without descenders rest on the row above the baseline
+        let baseline = draw(10, 40, Origin::Baseline);
+        assert_eq!(ink_rows(&baseline).1, 39);
+
+        // The ink box is centered on the point
+        let centered = draw(50, 30, Origin::Center);
+        let (top, bottom) = ink_rows(&centered);
+        let (left, right) = ink_columns(&centered, 0..60).unwrap();
+        assert_eq!(left + (right - left + 1) / 2, 50);
+        assert_eq!(top + (bottom - top + 1) / 2, 30);
+    }
+
+    #[test]
+    fn test_substring_x_range() {
+        let font = font();
+        let scale = Scale::uniform(40.0);
+        let advance = |c| font.glyph(c).scaled(scale).h_metrics().advance_width;
+        let kerning = font.pair_kerning(scale, 'A', 'V');
+
+        // The range of "V" starts after the kerned "A", not after its natural advance
+        let (start, end) = substring_x_range(scale, &font, "xAVx", 2, 3);
+        let expected = advance('x') + font.pair_kerning(scale, 'x', 'A') + advance('A') + kerning;
+        assert!((start - expected).abs() < 1e-3);
+        assert!((start - (advance('x') + advance('A'))).abs() > 1.0);
+        assert!(end > start);
+
+        // Adjacent ranges meet, and the whole range spans the line
+        let (_, middle) = substring_x_range(scale, &font, "xAVx", 0, 2);
+        assert_eq!(middle, start);
+        let (left, right) = substring_x_range(scale, &font, "xAVx", 0, 4);
+        assert_eq!(left, 0.0);
+        let options = LayoutOptions::default();
+        let pen = layout_line(scale, &font, "xAVx", point(0.0, 0.0), &options, |_, _| {});
+        assert_eq!(right, pen);
+
+        // Indices count characters and are clamped to the text
+        let text = "héllo";
+        assert_eq!(
+            substring_x_range(scale, &font, text, 1, 2).1,
+            substring_x_range(scale, &font, text, 2, 5).0
+        );
+        assert_eq!(
+            substring_x_range(scale, &font, text, 0, 100),
+            substring_x_range(scale, &font, text, 0, 5)
+        );
+        let (a, b) = substring_x_range(scale, &font, text, 3, 1);
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn test_selection_rect() {
+        let font = font();
+        let scale = Scale::uniform(20.0);
+        let v_metrics = font.v_metrics(scale);
+        let text = "héllo\nwörld!";
+        assert_eq!(selection_rect(scale, &font, text, 2, 2), None);
+        assert_eq!(selection_rect(scale, &font, text, 3, 1), None);
+
+        // Indices count characters, so the accented characters take one index each
+        let rect = selection_rect(scale, &font, text, 1, 3).unwrap();
+        let (start, baseline) = caret_position(scale, &font, text, 1);
+        let (end, _) = caret_position(scale, &font, text, 3);
+        assert_eq!((rect.left(), rect.right() + 1), (start, end));
+        assert_eq!(
+            rect.top(),
+            (baseline as f32 - v_metrics.ascent).round() as i32
+        );
+        let height = (v_metrics.ascent - v_metrics.descent).round() as i32;
+        assert!((rect.height() as i32 - height).abs() <= 1);
+
+        // A selection over two lines covers both
+        let rect = selection_rect(scale, &font, text, 3, 8).unwrap();
+        assert_eq!(rect.left(), 0);
+        assert!(rect.height() as i32 > height);
+        assert_eq!(
+            selection_rect(scale, &font, text, 0, 100),
+            selection_rect(scale, &font, text, 0, 12)
+        );
+
+        let mut image = GrayImage::new(100, 60);
+        draw_selection_mut(&mut image, Luma([255u8]), 5, 5, scale, &font, text, 1, 3);
+        let inked = image.pixels().filter(|p| p[0] > 0).count() as u32;
+        let rect = selection_rect(scale, &font, text, 1, 3).unwrap();
+        assert_eq!(inked, rect.width() * rect.height());
+    }
+
+    #[test]
+    fn test_text_measurer_matches_text_size() {
+        let font = font();
+        let mut measurer = TextMeasurer::new(&font);
+        for &size in &[10.0, 20.0, 10.0] {
+            let scale = Scale::uniform(size);
+            assert_eq!(measurer.v_metrics(scale), font.v_metrics(scale));
+            for &text in &["", "Hello", "gjpqy", "two\nlines", " "] {
+                assert_eq!(
+                    measurer.text_size(scale, text),
+                    text_size(scale, &font, text)
+                );
+            }
+        }
+        assert_eq!(measurer.v_metrics.len(), 2);
+    }
+
+    #[test]
+    fn test_rich_line() {
+        let font = font();
+        let small = TextStyle::new(&font, Scale::uniform(16.0), Luma([255u8]));
+        let large = TextStyle::new(&font, Scale::uniform(30.0), Luma([100u8]));
+        let line = RichLine::new()
+            .with_run("Error: ", large)
+            .with_run("HH", small);
+
+        let mut image = GrayImage::new(200, 60);
+        let drawn = line.draw_mut(&mut image, 5, 5).unwrap();
+        let (width, height) = line.size();
+        assert!(drawn.right() < 5 + width && drawn.bottom() < 5 + height);
+
+        // The small run starts where the advance of the large one ends, and shares its baseline
+        let pen = advance_width(
+            Scale::uniform(30.0),
+            &font,
+            "Error: ",
+            &LayoutOptions::default(),
+        );
+        let small_columns: Vec<u32> = (0..200)
+            .filter(|&x| (0..60).any(|y| image.get_pixel(x, y)[0] > 100))
+            .collect();
+        assert!((small_columns[0] as i32 - 5 - pen.round() as i32).abs() <= 2);
+
+        let small_bottom = (0..60)
+            .rev()
+            .find(|&y| (0..200).any(|x| image.get_pixel(x, y)[0] > 100))
+            .unwrap();
+        let mut large_only = GrayImage::new(200, 60);
+        RichLine::new()
+            .with_run("E", large)
+            .draw_mut(&mut large_only, 5, 5);
+        let large_bottom = (0..60)
+            .rev()
+            .find(|&y| (0..200).any(|x| large_only.get_pixel(x, y)[0] > 0))
+            .unwrap();
+        assert!((small_bottom as i32 - large_bottom as i32).abs() <= 1);
+
+        assert_eq!(RichLine::<Luma<u8>>::new().size(), (0, 0));
+    }
+
+    #[test]
+    fn test_draw_text_mut_negative_scale_mirrors() {
+        let font = font();
+        let text = "Fig";
+        for &(sx, sy, flip) in &[
+            (-20.0, 20.0, Flip::Horizontal),
+            (20.0, -20.0, Flip::Vertical),
+            (-20.0, -20.0, Flip::Both),
+        ] {
+            let scale = Scale { x: sx, y: sy };
+            assert_eq!(
+                text_size(scale, &font, text),
+                text_size(Scale::uniform(20.0), &font, text)
+            );
+
+            let mut image = GrayImage::new(60, 40);
+            let mut expected = GrayImage::new(60, 40);
+            draw_text_mut(&mut image, Luma([255u8]), 5, 5, scale, &font, text);
+            let uniform = Scale::uniform(20.0);
+            draw_text_flipped_mut(
+                &mut expected,
+                Luma([255u8]),
+                5,
+                5,
+                flip,
+                uniform,
+                &font,
+                text,
+            );
+            assert!(image.pixels().any(|p| p[0] > 0));
+            assert_pixels_eq!(image, expected);
+        }
+    }
+
+    /// Mirrors the area of `image` within `margin` pixels of the text box of size `size` at `(margin, margin)`.
+    fn mirror_text_box(image: &GrayImage, margin: i32, size: (i32, i32), flip: Flip) -> GrayImage {
+        let (w, h) = size;
+        let (flip_x, flip_y) = match flip {
+            Flip::None => (false, false),
+            Flip::Horizontal => (true, false),
+            Flip::Vertical => (false, true),
+            Flip::Both => (true, true),
+        };
+        GrayImage::from_fn(image.width(), image.height(), |x, y| {
+            let (x, y) = (x as i32, y as i32);
+            let sx = if flip_x { 2 * margin + w - 1 - x } else { x };
+            let sy = if flip_y { 2 * margin + h - 1 - y } else { y };
+            if sx < 0 || sy < 0 || sx >= image.width() as i32 || sy >= image.height() as i32 {
+                return Luma([0u8]);
+            }
+            *image.get_pixel(sx as u32, sy as u32)
+        })
+    }
+
+    #[test]
+    fn test_negative_scale_mirrors_within_text_box() {
+        let font = font();
+        let uniform = Scale::uniform(20.0);
+        let margin = 10;
+        for &(sx, sy, flip) in &[
+            (-20.0, 20.0, Flip::Horizontal),
+            (20.0, -20.0, Flip::Vertical),
+            (-20.0, -20.0, Flip::Both),
+        ] {
+            let scale = Scale { x: sx, y: sy };
+            // Text with and without descenders, so its ink box may be smaller than its text box
+            for &text in &["ace", "jolly", "ACE\nfig"] {
+                let size = text_size(uniform, &font, text);
+                let blank = GrayImage::new(size.0 as u32 + 20, size.1 as u32 + 20);
+                // Draws at both scales into an image with a margin around a text box of the given size
+                let check = |size: (i32, i32), tolerance: u8, f: &dyn Fn(&mut GrayImage, Scale)| {
+                    let blank = GrayImage::new(size.0 as u32 + 20, size.1 as u32 + 20);
+                    let mut expected = blank.clone();
+                    f(&mut expected, uniform);
+                    let mut image = blank;
+                    f(&mut image, scale);
+                    assert!(image.pixels().any(|p| p[0] > 0));
+                    let expected = mirror_text_box(&expected, margin, size, flip);
+                    assert_pixels_eq_within!(image, expected, tolerance);
+                };
+                let draw = |f: &dyn Fn(&mut GrayImage, Scale)| check(size, 0, f);
+
+                let white = Luma([255u8]);
+                draw(&|image, scale| {
+                    draw_text_mut(image, white, margin, margin, scale, &font, text)
+                });
+                draw(&|image, scale| {
+                    let (mask, (left, top)) = rasterize_text(scale, &font, text);
+                    image
+                        .copy_from(&mask, (margin + left) as u32, (margin + top) as u32)
+                        .unwrap();
+                });
+                draw(&|image, scale| {
+                    draw_text_outlined_mut(
+                        image,
+                        white,
+                        Luma([128u8]),
+                        2,
+                        margin,
+                        margin,
+                        scale,
+                        &font,
+                        text,
+                    )
+                });
+                draw(&|image, scale| {
+                    TextStyle::new(&font, scale, white)
+                        .outline(Luma([128u8]), 2)
+                        .outline_join(StrokeJoin::Miter)
+                        .draw_mut(image, margin, margin, text);
+                });
+                draw(&|image, scale| {
+                    let (x, y) = (margin as f32, margin as f32);
+                    draw_text_subpixel_mut(image, white, x, y, false, scale, &font, text)
+                });
+                draw(&|image, scale| {
+                    let mut cache = GlyphCache::new();
+                    draw_text_cached_mut(
+                        image, white, margin, margin, scale, &font, text, &mut cache,
+                    )
+                });
+                draw(&|image, scale| {
+                    Layout::new(scale, &font, text).draw_mut(image, white, margin, margin);
+                });
+                assert_eq!(Layout::new(scale, &font, text).size(), size);
+                assert_eq!(TextMeasurer::new(&font).text_size(scale, text), size);
+
+                // Carets, selections and metrics are mirrored along with the text
+                let (w, h) = (size.0 as f32, size.1 as f32);
+                let options = LayoutOptions::default();
+                let (cx, cy) = caret_offset(uniform, &font, text, 2, &options);
+                let (mx, my) = caret_offset(scale, &font, text, 2, &options);
+                assert_eq!(mx, if sx < 0.0 { w - cx } else { cx });
+                assert_eq!(my, if sy < 0.0 { h - cy } else { cy });
+                let (start, end) = substring_x_range(uniform, &font, text, 1, 3);
+                let expected = if sx < 0.0 {
+                    (w - end, w - start)
+                } else {
+                    (start, end)
+                };
+                assert_eq!(substring_x_range(scale, &font, text, 1, 3), expected);
+                let baseline = text_metrics(uniform, &font, text).baseline;
+                let expected = if sy < 0.0 { h - baseline } else { baseline };
+                assert_eq!(text_metrics(scale, &font, text).baseline, expected);
+                draw(&|image, scale| {
+                    draw_selection_mut(image, white, margin, margin, scale, &font, text, 1, 3)
+                });
+
+                // Scale ramps are mirrored when both ends share signs, and draw nothing otherwise
+                let grow = |scale: Scale| Scale {
+                    x: scale.x * 1.5,
+                    y: scale.y * 1.5,
+                };
+                let ramp_size = scaled_ramp_text_size(uniform, grow(uniform), &font, text);
+                assert_eq!(
+                    scaled_ramp_text_size(scale, grow(scale), &font, text),
+                    ramp_size
+                );
+                check(ramp_size, 0, &|image, scale| {
+                    draw_text_scaled_ramp_mut(
+                        image,
+                        white,
+                        margin,
+                        margin,
+                        scale,
+                        grow(scale),
+                        &font,
+                        text,
+                    )
+                });
+                assert_eq!(scaled_ramp_text_size(scale, uniform, &font, text), (0, 0));
+                let mut image = blank.clone();
+                draw_text_scaled_ramp_mut(
+                    &mut image, white, margin, margin, scale, uniform, &font, text,
+                );
+                assert!(image.pixels().all(|p| p[0] == 0));
+
+                check(
+                    vertical_text_size(uniform, &font, text),
+                    0,
+                    &|image, scale| {
+                        draw_vertical_text_mut(image, white, margin, margin, scale, &font, text)
+                    },
+                );
+
+                // Wrapped lines are mirrored within the width they are wrapped to
+                let max_width = size.0 as u32 + 10;
+                let style = ParagraphStyle {
+                    first_line_indent: 3,
+                    align: HorizontalAlign::Right,
+                };
+                check((max_width as i32, size.1), 0, &|image, scale| {
+                    draw_paragraphs_mut(
+                        image,
+                        white,
+                        margin,
+                        margin,
+                        max_width,
+                        &[style],
+                        scale,
+                        &font,
+                        text,
+                    )
+                });
+                let lines: Vec<&str> = text.lines().collect();
+                check((max_width as i32, size.1), 0, &|image, scale| {
+                    draw_justified_text_mut(
+                        image, white, margin, margin, max_width, scale, &font, &lines,
+                    )
+                });
+
+                // Exclusions are positioned on the canvas, so text around them is not mirrored
+                let wrapped = wrap_text_around(uniform, &font, text, max_width, &[]);
+                assert_eq!(
+                    wrap_text_around(scale, &font, text, max_width, &[]),
+                    wrapped
+                );
+                let mut image = blank.clone();
+                draw_text_around_mut(
+                    &mut image,
+                    white,
+                    margin,
+                    margin,
+                    max_width,
+                    &[],
+                    scale,
+                    &font,
+                    text,
+                );
+                assert!(image.pixels().all(|p| p[0] == 0));
+
+                let fonts = FontStack::new(font.clone());
+                check(
+                    text_size_with_fallback(uniform, &fonts, text),
+                    0,
+                    &|image, scale| {
+                        draw_text_with_fallback_mut(
+                            image, white, margin, margin, scale, &fonts, text,
+                        )
+                    },
+                );
+
+                let glyphs = GlyphString::new(uniform, &font, text);
+                let glyphs_size = (glyphs.width() as i32, glyphs.height() as i32);
+                check(glyphs_size, 0, &|image, scale| {
+                    let m = margin as u32;
+                    GlyphString::new(scale, &font, text).draw_mut(image, white, m, m)
+                });
+
+                // Text on a path is splatted at fractional positions, so is compared approximately
+                if !text.contains('\n') {
+                    let baseline = margin as f32 + font.v_metrics(uniform).ascent;
+                    let path = [(margin as f32, baseline), (margin as f32 + 200.0, baseline)];
+                    check(size, 1, &|image, scale| {
+                        draw_text_on_path_mut(image, white, &path, scale, &font, text)
+                    });
+                }
+
+                // The shadow is blurred, so is only compared approximately
+                let shadowed = |scale| {
+                    let mut image = blank.clone();
+                    let gray = Luma([100u8]);
+                    draw_text_with_shadow_mut(
+                        &mut image,
+                        white,
+                        gray,
+                        (0, 0),
+                        1.5,
+                        margin,
+                        margin,
+                        scale,
+                        &font,
+                        text,
+                    );
+                    image
+                };
+                let expected = mirror_text_box(&shadowed(uniform), margin, size, flip);
+                assert_pixels_eq_within!(shadowed(scale), expected, 1);
+            }
+        }
+    }
+
+    #[test]
+    fn test_for_each_dash_wraps_pattern() {
+        let square = [
+            (0.0, 0.0),
+            (10.0, 0.0),
+            (10.0, 10.0),
+            (0.0, 10.0),
+            (0.0, 0.0),
+        ];
+        let mut dashes = vec![];
+        for_each_dash(&square, &[4.0, 2.0], |a, b| dashes.push((a, b)));
+
+        // The pattern continues around corners: 0-4, 6-10, 12-16, ...
+        let total: f32 = dashes.iter().map(|&(a, b)| distance(a, b)).sum();
+        assert_eq!(dashes[0], ((0.0, 0.0), (4.0, 0.0)));
+        assert_eq!(dashes[1], ((6.0, 0.0), (10.0, 0.0)));
+        assert_eq!(dashes[2], ((10.0, 2.0), (10.0, 6.0)));
+        assert!((total - 28.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_draw_text_dashed_outline_mut() {
+        let font = font();
+        let scale = Scale::uniform(40.0);
+        let draw = |dashes: &[f32]| {
+            let mut image = GrayImage::new(80, 60);
+            draw_text_dashed_outline_mut(
+                &mut image,
+                Luma([255u8]),
+                10,
+                10,
+                1.0,
+                dashes,
+                scale,
+                &font,
+                "O",
+            );
+            image
+        };
+        let count = |image: &GrayImage| image.pixels().filter(|p| p[0] > 0).count();
+
+        let solid = draw(&[]);
+        let dashed = draw(&[3.0, 3.0]);
+        assert!(count(&dashed) > 0 && count(&dashed) < count(&solid));
+        assert!(dashed
+            .pixels()
+            .zip(solid.pixels())
+            .all(|(d, s)| d[0] <= s[0]));
+
+        // The outline is hollow: the center of the 'O' is not drawn
+        let mut filled = GrayImage::new(80, 60);
+        draw_text_mut(&mut filled, Luma([255u8]), 10, 10, scale, &font, "O");
+        let interior = filled.pixels().filter(|p| p[0] == 255).count();
+        assert!(
+            solid
+                .pixels()
+                .zip(filled.pixels())
+                .filter(|(s, f)| f[0] == 255 && s[0] == 0)
+                .count()
+                > interior / 4
+        );
+        assert_eq!(draw(&[0.0, 0.0]), solid);
+    }
+
+    #[test]
+    fn test_draw_text_checked_mut() {
+        let font = font();
+        let scale = Scale::uniform(20.0);
+        let ink = ink_bounds(scale, &font, "Hi").unwrap();
+
+        let mut image = GrayImage::new(40, 30);
+        let drawn = draw_text_checked_mut(&mut image, Luma([255u8]), 5, 3, scale, &font, "Hi");
+        assert_eq!(
+            drawn,
+            Ok(IpRect::at(ink.left() + 5, ink.top() + 3).of_size(ink.width(), ink.height()))
+        );
+        assert!(image.pixels().any(|p| p[0] > 0));
+
+        // Partly visible text is clipped to the canvas
+        let mut image = GrayImage::new(40, 30);
+        let drawn = draw_text_checked_mut(&mut image, Luma([255u8]), -5, 0, scale, &font, "Hi");
+        assert_eq!(drawn.unwrap().left(), 0);
+
+        let mut image = GrayImage::new(40, 30);
+        let drawn = draw_text_checked_mut(&mut image, Luma([255u8]), 100, 0, scale, &font, "Hi");
+        assert_eq!(
+            drawn,
+            Err(TextNotDrawn::OffCanvas(
+                IpRect::at(ink.left() + 100, ink.top()).of_size(ink.width(), ink.height())
+            ))
+        );
+        assert!(image.pixels().all(|p| p[0] == 0));
+
+        let drawn = draw_text_checked_mut(&mut image, Luma([255u8]), 0, 0, scale, &font, " ");
+        assert_eq!(drawn, Err(TextNotDrawn::NoInk));
+    }
+
+    #[test]
+    fn test_scale_from_pt() {
+        assert_eq!(scale_from_pt(12.0, 96.0), Scale::uniform(16.0));
+        assert_eq!(scale_from_pt(12.0, 72.0), Scale::uniform(12.0));
+        assert_eq!(scale_from_pt(10.0, 300.0).y, 10.0 * 300.0 / 72.0);
+    }
+
+    #[test]
+    fn test_draw_text_opacity_ramp_mut() {
+        let font = font();
+        let scale = Scale::uniform(20.0);
+        let draw = |opacity: &dyn Fn(usize) -> f32| {
+            let mut image = GrayImage::from_pixel(60, 50, Luma([10]));
+            let color = Luma([200u8]);
+            draw_text_opacity_ramp_mut(&mut image, color, 3, 4, opacity, scale, &font, "Ab\nc");
+            image
+        };
+
+        let mut expected = GrayImage::from_pixel(60, 50, Luma([10]));
+        let color = Luma([200u8]);
+        draw_text_opacity_mut(&mut expected, color, 3, 4, 0.5, scale, &font, "Ab\nc");
+        assert_pixels_eq!(draw(&|_| 0.5), expected);
+
+        // Only the first glyph of the second line, which follows the line break, is drawn
+        let mut expected = GrayImage::from_pixel(60, 50, Luma([10]));
+        draw_text_mut(&mut expected, color, 3, 4, scale, &font, "  \nc");
+        assert_pixels_eq!(draw(&|i| if i == 2 { 3.0 } else { -1.0 }), expected);
+    }
+
+    #[test]
+    fn test_draw_ruby_mut() {
+        let font = font();
+        let base = TextStyle::new(&font, Scale::uniform(24.0), Luma([255u8]));
+        let ruby = TextStyle::new(&font, Scale::uniform(10.0), Luma([255u8]));
+        let (base_w, _) = base.size("Kanji");
+        let (ruby_w, ruby_h) = ruby.size("ka");
+        assert!(ruby_w < base_w);
+
+        let mut image = GrayImage::new(120, 60);
+        draw_ruby_mut(&mut image, &base, &ruby, "Kanji", "ka", 5, 2);
+        let mut expected = GrayImage::new(120, 60);
+        ruby.draw_mut(&mut expected, 5 + (base_w - ruby_w) / 2, 2, "ka");
+        base.draw_mut(&mut expected, 5, 2 + ruby_h, "Kanji");
+        assert_pixels_eq!(image, expected);
+
+        // Ruby wider than its base overhangs it, and the base is centered below
+        let (ruby_w, ruby_h) = ruby.size("a long annotation");
+        let (base_w, _) = base.size("K");
+        let mut image = GrayImage::new(120, 60);
+        draw_ruby_mut(&mut image, &base, &ruby, "K", "a long annotation", 5, 2);
+        let mut expected = GrayImage::new(120, 60);
+        ruby.draw_mut(&mut expected, 5, 2, "a long annotation");
+        base.draw_mut(&mut expected, 5 + (ruby_w - base_w) / 2, 2 + ruby_h, "K");
+        assert_pixels_eq!(image, expected);
+    }
+
+    #[test]
+    fn test_draw_fitted_positioned_mut() {
+        let font = font();
+        let rect = IpRect::at(10, 5).of_size(60, 30);
+        let position = Position::HorizontalCenter(EdgePosition::center());
+
+        // Text which fits is drawn at the maximum scale
+        let mut image = GrayImage::new(80, 40);
+        let scale = draw_fitted_positioned_mut(
+            &mut image,
+            Luma([255u8]),
+            &position,
+            &rect,
+            12.0,
+            &font,
+            "Hi",
+        );
+        assert_eq!(scale, Scale::uniform(12.0));
+
+        // Text which is too large is shrunk until its glyph string fits, then positioned
+        let mut image = GrayImage::new(80, 40);
+        let scale = draw_fitted_positioned_mut(
+            &mut image,
+            Luma([255u8]),
+            &position,
+            &rect,
+            100.0,
+            &font,
+            "Hello",
+        );
+        assert!(scale.y < 100.0 && scale.y > 0.0);
+        let glyphs = GlyphString::new(scale, &font, "Hello");
+        assert!(glyphs.width() <= rect.width() && glyphs.height() <= rect.height());
+        let larger = GlyphString::new(Scale::uniform(scale.y + 0.2), &font, "Hello");
+        assert!(larger.width() > rect.width() || larger.height() > rect.height());
+
+        let mut expected = GrayImage::new(80, 40);
+        glyphs.draw_positioned_mut(&mut expected, Luma([255u8]), &position, &rect);
+        assert_pixels_eq!(image, expected);
+    }
+
+    #[test]
+    fn test_glyph_string_draw_mut_custom_canvas() {
+        use crate::drawing::Blend;
+        use image::{Rgba, RgbaImage};
+
+        let font = font();
+        let scale = Scale::uniform(20.0);
+        let color = Rgba([255u8, 0, 0, 128]);
+
+        let mut glyphs = Blend(RgbaImage::from_pixel(60, 30, Rgba([0, 0, 255, 255])));
+        GlyphString::new(scale, &font, "Blend").draw_mut(&mut glyphs, color, 2, 3);
+        let mut text = Blend(RgbaImage::from_pixel(60, 30, Rgba([0, 0, 255, 255])));
+        draw_text_mut(&mut text, color, 2, 3, scale, &font, "Blend");
+
+        assert_pixels_eq!(glyphs.0, text.0);
+    }
+
+    #[test]
+    fn test_trimmed_text_size() {
+        let font = font();
+        let scale = Scale::uniform(20.0);
+        let ink = ink_bounds(scale, &font, "Hi").unwrap();
+        let (_, height) = text_size(scale, &font, "Hi");
+        let trimmed = trimmed_text_size(scale, &font, "Hi");
+        assert_eq!(trimmed, ((ink.width() as i32, height), ink.left()));
+
+        // Leading and trailing whitespace only moves the offset, up to the rasterization of the glyphs
+        // at a different subpixel position
+        let ((w, h), offset) = trimmed_text_size(scale, &font, "   Hi  ");
+        assert!((w - trimmed.0 .0).abs() <= 1);
+        assert_eq!(h, trimmed.0 .1);
+        assert!(offset > trimmed.1 + 10);
+        let (advance_width, _) = text_size(scale, &font, "   Hi  ");
+        assert!(w < advance_width);
+
+        assert_eq!(trimmed_text_size(scale, &font, "  "), ((0, 0), 0));
+        assert_eq!(trimmed_text_size(scale, &font, ""), ((0, 0), 0));
+    }
+
+    #[test]
+    fn test_draw_text_in_quad_mut() {
+        let font = font();
+        let scale = Scale::uniform(20.0);
+        let (w, h) = text_size(scale, &font, "Quad");
+
+        // An axis-aligned quad the size of the text box matches drawing the text normally
+        let (x, y) = (6.0, 4.0);
+        let quad = [
+            (x, y),
+            (x + w as f32, y),
+            (x + w as f32, y + h as f32),
+            (x, y + h as f32),
+        ];
+        let mut image = GrayImage::new(80, 40);
+        draw_text_in_quad_mut(&mut image, Luma([255u8]), quad, scale, &font, "Quad");
+        let mut expected = GrayImage::new(80, 40);
+        draw_text_mut(&mut expected, Luma([255u8]), 6, 4, scale, &font, "Quad");
+        assert_pixels_eq_within!(image, expected, 1);
+
+        // Ink stays inside a perspective quad
+        let quad = [(10.0, 5.0), (70.0, 12.0), (70.0, 28.0), (10.0, 35.0)];
+        let mut image = GrayImage::new(80, 40);
+        draw_text_in_quad_mut(&mut image, Luma([255u8]), quad, scale, &font, "Quad");
+        assert!(image.pixels().any(|p| p[0] > 0));
+        for (px, py, p) in image.enumerate_pixels() {
+            if p[0] > 0 {
+                assert!((9..=70).contains(&px) && (4..=35).contains(&py));
+            }
+        }
+
+        // Degenerate quads draw nothing
+        let mut image = GrayImage::new(80, 40);
+        let line = [(0.0, 0.0), (10.0, 10.0), (20.0, 20.0), (30.0, 30.0)];
+        draw_text_in_quad_mut(&mut image, Luma([255u8]), line, scale, &font, "Quad");
+        assert!(image.pixels().all(|p| p[0] == 0));
+    }
+
+    #[test]
+    fn test_draw_text_decorated_mut_fractional_thickness() {
+        let font = font();
+        let scale = Scale::uniform(32.0);
+        let v_metrics = font.v_metrics(scale);
+        let (w, _) = text_size(scale, &font, "ab");
+
+        for &thickness in &[0.5f32, 1.0, 1.5, 2.25] {
+            let mut image = GrayImage::new(100, 50);
+            let underline = TextDecoration::UNDERLINE;
+            draw_text_decorated_mut(
+                &mut image,
+                Luma([255u8]),
+                0,
+                0,
+                underline,
+                Some(thickness),
+                scale,
+                &font,
+                "ab",
+            );
+
+            // Neither glyph reaches below the baseline, so each column below it only has the underline,
+            // whose coverage sums to its thickness
+            let below = v_metrics.ascent.ceil() as u32 + 1..50;
+            for x in 0..w as u32 {
+                let sum: u32 = below.clone().map(|y| image.get_pixel(x, y)[0] as u32).sum();
+                assert!((sum as f32 - 255.0 * thickness).abs() <= 2.0);
+            }
+
+            // Aliased decorations only use whole pixels
+            let mut aliased = GrayImage::new(100, 50);
+            TextStyle::new(&font, scale, Luma([255u8]))
+                .aliasing(Aliasing::Aliased)
+                .decoration(underline, Some(thickness))
+                .draw_mut(&mut aliased, 0, 0, "ab");
+            assert!(below
+                .clone()
+                .all(|y| (0..w as u32).all(|x| [0, 255].contains(&aliased.get_pixel(x, y)[0]))));
+        }
+    }
+
+    #[test]
+    fn test_wrap_text_indented() {
+        let font = font();
+        let scale = Scale::uniform(20.0);
+        let text = "the quick brown fox jumps\nover";
+        let max_width = text_size(scale, &font, "the quick brown")
+            .0
+            .max(text_size(scale, &font, "brown fox jumps").0) as u32;
+        assert_eq!(
+            wrap_text_indented(scale, &font, text, max_width, 0),
+            wrap_text(scale, &font, text, max_width)
+        );
+
+        // The indent is taken from the first line of each paragraph only
+        let indent = max_width as i32 - text_size(scale, &font, "the quick").0;
+        let lines = wrap_text_indented(scale, &font, text, max_width, indent);
+        assert_eq!(lines, vec!["the quick", "brown fox jumps", "over"]);
+        assert!(text_size(scale, &font, &lines[0]).0 + indent <= max_width as i32);
+
+        // A hanging indent lets the first line grow
+        let lines = wrap_text_indented(scale, &font, "the quick brown fox", max_width, -40);
+        assert_eq!(lines[0], "the quick brown fox");
+    }
+
+    #[test]
+    fn test_draw_paragraphs_mut() {
+        let font = font();
+        let scale = Scale::uniform(20.0);
+        let advance = line_advance(&font.v_metrics(scale)).round() as i32;
+        let max_width = 150;
+        let text = "the quick brown fox jumps\nover";
+        let indent = 20;
+        let styles = [
+            ParagraphStyle {
+                first_line_indent: indent,
+                align: HorizontalAlign::Left,
+            },
+            ParagraphStyle {
+                first_line_indent: 0,
+                align: HorizontalAlign::Right,
+            },
+        ];
+
+        let mut image = GrayImage::new(200, 100);
+        draw_paragraphs_mut(
+            &mut image,
+            Luma([255u8]),
+            5,
+            5,
+            max_width,
+            &styles,
+            scale,
+            &font,
+            text,
+        );
+
+        let mut expected = GrayImage::new(200, 100);
+        let lines =
+            wrap_text_indented(scale, &font, "the quick brown fox jumps", max_width, indent);
+        assert_eq!(lines.len(), 2);
+        draw_text_mut(
+            &mut expected,
+            Luma([255u8]),
+            5 + indent,
+            5,
+            scale,
+            &font,
+            &lines[0],
+        );
+        draw_text_mut(
+            &mut expected,
+            Luma([255u8]),
+            5,
+            5 + advance,
+            scale,
+            &font,
+            &lines[1],
+        );
+        let right = 5 + max_width as i32 - text_size(scale, &font, "over").0;
+        draw_text_mut(
+            &mut expected,
+            Luma([255u8]),
+            right,
+            5 + 2 * advance,
+            scale,
+            &font,
+            "over",
+        );
+        assert_pixels_eq!(image, expected);
+
+        // Without styles every paragraph uses the default style
+        let mut image = GrayImage::new(200, 100);
+        draw_paragraphs_mut(
+            &mut image,
+            Luma([255u8]),
+            5,
+            5,
+            max_width,
+            &[],
+            scale,
+            &font,
+            text,
+        );
+        let mut expected = GrayImage::new(200, 100);
+        draw_wrapped_text_mut(
+            &mut expected,
+            Luma([255u8]),
+            5,
+            5,
+            max_width,
+            scale,
+            &font,
+            text,
+        );
+        assert_pixels_eq!(image, expected);
+    }
+
+    #[test]
+    fn test_advances() {
+        let font = font();
+        let scale = Scale::uniform(20.0);
+        let options = LayoutOptions::default();
+
+        let line = advances(scale, &font, "AVA b");
+        assert_eq!(line.len(), 5);
+        let total: f32 = line.iter().sum();
+        assert!((total - advance_width(scale, &font, "AVA b", &options)).abs() < 1e-3);
+
+        // Kerning is included in the advance of the first glyph of a pair
+        let unkerned = font.glyph('A').scaled(scale).h_metrics().advance_width;
+        let kerning = font.pair_kerning(scale, 'A', 'V');
+        assert!(kerning < 0.0);
+        assert!((line[0] - (unkerned + kerning)).abs() < 1e-3);
+
+        // Lines are measured separately, without line breaks
+        let lines = advances(scale, &font, "AV\nb");
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[2], line[4]);
+        assert!(advances(scale, &font, "").is_empty());
+    }
+
+    #[test]
+    fn test_draw_text_textured_mut() {
+        let font = font();
+        let scale = Scale::uniform(24.0);
+
+        // A uniform texture is the same as a solid color
+        let texture = GrayImage::from_pixel(3, 3, Luma([180u8]));
+        let mut image = GrayImage::from_pixel(60, 40, Luma([20]));
+        draw_text_textured_mut(&mut image, &texture, 4, 2, scale, &font, "Tile");
+        let mut expected = GrayImage::from_pixel(60, 40, Luma([20]));
+        draw_text_mut(&mut expected, Luma([180u8]), 4, 2, scale, &font, "Tile");
+        assert_pixels_eq!(image, expected);
+
+        // A texture of stripes repeats from the top of the text box
+        let stripes = GrayImage::from_fn(1, 4, |_, y| Luma([if y < 2 { 255 } else { 100 }]));
+        let mut image = GrayImage::new(60, 40);
+        draw_text_textured_mut(&mut image, &stripes, 4, 2, scale, &font, "Tile");
+        for (_, y, p) in image.enumerate_pixels() {
+            if y >= 2 && p[0] > 100 {
+                assert!((y - 2) % 4 < 2);
+            }
+        }
+        assert!(image.pixels().any(|p| p[0] == 255));
+
+        let mut image = GrayImage::new(60, 40);
+        draw_text_textured_mut(
+            &mut image,
+            &GrayImage::new(0, 0),
+            4,
+            2,
+            scale,
+            &font,
+            "Tile",
+        );
+        assert!(image.pixels().all(|p| p[0] == 0));
+    }
+
+    #[test]
+    fn test_glyph_string_vertical_metric() {
+        let font = font();
+        let scale = Scale::uniform(30.0);
+        let rect = IpRect::at(0, 0).of_size(100, 60);
+        let position = Position::HorizontalCenter(EdgePosition::center());
+        let ink_rows = |metric| {
+            let mut image = GrayImage::new(100, 60);
+            GlyphString::new(scale, &font, "HELLO")
+                .with_vertical_metric(metric)
+                .draw_positioned_mut(&mut image, Luma([255u8]), &position, &rect);
+            let rows: Vec<u32> = (0..60)
+                .filter(|&y| (0..100).any(|x| image.get_pixel(x, y)[0] > 127))
+                .collect();
+            (rows[0], *rows.last().unwrap())
+        };
+
+        // The whole line height is centered by default
+        let mut image = GrayImage::new(100, 60);
+        let string = GlyphString::new(scale, &font, "HELLO");
+        string.draw_positioned_mut(&mut image, Luma([255u8]), &position, &rect);
+        let mut expected = GrayImage::new(100, 60);
+        let (x, y) = ((100 - string.width()) / 2, (60 - string.height()) / 2);
+        string.draw_mut(&mut expected, Luma([255u8]), x, y);
+        assert_pixels_eq!(image, expected);
+
+        // Centering on the cap height centers capital letters
+        let (top, bottom) = ink_rows(VerticalMetric::CapHeight);
+        assert!(((60 - 1 - bottom) as i32 - top as i32).abs() <= 1);
+
+        // Centering on the x-height places capitals higher still
+        let (x_top, _) = ink_rows(VerticalMetric::XHeight);
+        assert!(x_top < top);
+    }
+
+    #[test]
+    fn test_draw_text_with_progress_mut() {
+        let font = font();
+        let scale = Scale::uniform(16.0);
+        let text = "one\ntwo\nthree";
+
+        let mut calls = vec![];
+        let mut image = GrayImage::new(60, 60);
+        let result = draw_text_with_progress_mut(
+            &mut image,
+            Luma([255u8]),
+            2,
+            3,
+            scale,
+            &font,
+            text,
+            |done, total| {
+                calls.push((done, total));
+                ControlFlow::Continue(())
+            },
+        );
+        assert_eq!(result, ControlFlow::Continue(()));
+        assert_eq!(calls, vec![(1, 3), (2, 3), (3, 3)]);
+        let mut expected = GrayImage::new(60, 60);
+        draw_text_mut(&mut expected, Luma([255u8]), 2, 3, scale, &font, text);
+        assert_pixels_eq!(image, expected);
+
+        // Cancelling leaves exactly the lines drawn so far
+        let mut image = GrayImage::new(60, 60);
+        let result = draw_text_with_progress_mut(
+            &mut image,
+            Luma([255u8]),
+            2,
+            3,
+            scale,
+            &font,
+            text,
+            |done, _| {
+                if done == 2 {
+                    ControlFlow::Break(())
+                } else {
+                    ControlFlow::Continue(())
+                }
+            },
+        );
+        assert_eq!(result, ControlFlow::Break(()));
+        let mut expected = GrayImage::new(60, 60);
+        draw_text_mut(&mut expected, Luma([255u8]), 2, 3, scale, &font, "one\ntwo");
+        assert_pixels_eq!(image, expected);
+    }
+
+    #[test]
+    fn test_text_style_coverage_map() {
+        let font = font();
+        let scale = Scale::uniform(20.0);
+        let style = TextStyle::new(&font, scale, Luma([255u8]));
+        let draw = |style: TextStyle<Luma<u8>>| {
+            let mut image = GrayImage::new(60, 30);
+            style.draw_mut(&mut image, 2, 2, "Map");
+            image
+        };
+
+        let mut expected = GrayImage::new(60, 30);
+        draw_text_mut(&mut expected, Luma([255u8]), 2, 2, scale, &font, "Map");
+        assert_pixels_eq!(draw(style), expected);
+
+        // A threshold at one half matches aliased drawing
+        let cutoff = 0.5;
+        let threshold = move |c: f32| if c >= cutoff { 1.0 } else { 0.0 };
+        let threshold = style.coverage_map(&threshold);
+        assert_pixels_eq!(draw(threshold), draw(style.aliasing(Aliasing::Aliased)));
+
+        // A gamma chosen at runtime is applied to every pixel
+        for &gamma in &[0.5f32, 2.0] {
+            let curve = move |c: f32| c.powf(gamma);
+            let image = draw(style.coverage_map(&curve));
+            let mut seen = 0;
+            for (a, e) in image.pixels().zip(expected.pixels()) {
+                // The expected coverage was truncated or rounded to a whole pixel value
+                let curve = |v: f32| (v.clamp(0.0, 255.0) / 255.0).powf(gamma) * 255.0;
+                let (low, high) = (curve(e[0] as f32 - 0.5), curve(e[0] as f32 + 1.0));
+                assert!(a[0] as f32 >= low - 1.0 && a[0] as f32 <= high + 1.0);
+                seen += (a[0] != e[0]) as usize;
+            }
+            assert!(seen > 0);
+        }
+
+        // A map given for a single draw replaces the style's map, and may mutate what it captures
+        let (mut samples, mut darkest) = (0, 0.0f32);
+        let mut image = GrayImage::new(60, 30);
+        threshold.draw_mut_with(&mut image, 2, 2, "Map", |c| {
+            samples += 1;
+            darkest = darkest.max(c);
+            c.powf(2.0)
+        });
+        assert_pixels_eq!(image, draw(style.coverage_map(&|c| c.powf(2.0))));
+        assert!(samples >= expected.pixels().filter(|p| p[0] > 0).count());
+        assert!(darkest > 0.9 && darkest <= 1.0);
+
+        // Results are clamped, so scaling coverage up saturates more pixels without overflowing
+        let boosted = draw(style.coverage_map(&|c| c * 4.0));
+        assert!(boosted
+            .pixels()
+            .zip(expected.pixels())
+            .all(|(b, e)| b[0] >= e[0]));
+        assert!(
+            boosted.pixels().filter(|p| p[0] == 255).count()
+                > expected.pixels().filter(|p| p[0] == 255).count()
+        );
+    }
+
+    #[test]
+    fn test_draw_label_value_mut() {
+        let font = font();
+        let scale = Scale::uniform(16.0);
+        let style = TextStyle::new(&font, scale, Luma([255u8]));
+        let rect = IpRect::at(5, 3).of_size(150, 20);
+
+        let mut image = GrayImage::new(160, 30);
+        draw_label_value_mut(&mut image, &style, "Total", "$12.50", rect, None);
+        let mut expected = GrayImage::new(160, 30);
+        style.draw_mut(&mut expected, 5, 3, "Total");
+        let value_x = 5 + 150 - style.size("$12.50").0;
+        style.draw_mut(&mut expected, value_x, 3, "$12.50");
+        assert_pixels_eq!(image, expected);
+
+        // A leader fills the gap between the two without touching either
+        let mut image = GrayImage::new(160, 30);
+        draw_label_value_mut(&mut image, &style, "Total", "$12.50", rect, Some('.'));
+        let label_end = 5 + style.size("Total").0;
+        let extra: Vec<u32> = image
+            .enumerate_pixels()
+            .filter(|(x, y, p)| p[0] != expected.get_pixel(*x, *y)[0])
+            .map(|(x, _, _)| x)
+            .collect();
+        assert!(extra.len() > 10);
+        assert!(extra
+            .iter()
+            .all(|&x| x as i32 > label_end && (x as i32) < value_x));
+
+        // Labels which do not fit are truncated
+        let narrow = IpRect::at(5, 3).of_size(90, 20);
+        let mut image = GrayImage::new(160, 30);
+        let long = "A very long description";
+        draw_label_value_mut(&mut image, &style, long, "$12.50", narrow, Some('.'));
+        let value_x = 5 + 90 - style.size("$12.50").0;
+        let gap = advance_width(scale, &font, " ", &LayoutOptions::default()).ceil() as i32;
+        let truncated = truncate_with_ellipsis(scale, &font, long, (value_x - 5 - gap) as u32);
+        assert!(truncated.ends_with('…'));
+        let mut expected = GrayImage::new(160, 30);
+        style.draw_mut(&mut expected, 5, 3, &truncated);
+        style.draw_mut(&mut expected, value_x, 3, "$12.50");
+        assert_pixels_eq!(image, expected);
+    }
+
+    #[test]
+    fn test_draw_shaped_text_mut() {
+        let font = font();
+        let scale = Scale::uniform(20.0);
+        let text = "AVA\nshaped";
+
+        let mut image = GrayImage::new(80, 60);
+        draw_shaped_text_mut(
+            &mut image,
+            Luma([255u8]),
+            3,
+            4,
+            scale,
+            &font,
+            text,
+            &DefaultShaper,
+        );
+        let mut expected = GrayImage::new(80, 60);
+        draw_text_mut(&mut expected, Luma([255u8]), 3, 4, scale, &font, text);
+        assert_pixels_eq!(image, expected);
+
+        // A custom shaper which reverses each line and substitutes a ligature-like glyph for "fi"
+        struct Reversing;
+        impl Shaper for Reversing {
+            fn shape(&self, font: &Font, scale: Scale, text: &str) -> Vec<ShapedGlyph> {
+                let reversed: String = text.replace("fi", "ﬁ").chars().rev().collect();
+                DefaultShaper.shape(font, scale, &reversed)
+            }
+        }
+        let mut image = GrayImage::new(80, 60);
+        draw_shaped_text_mut(
+            &mut image,
+            Luma([255u8]),
+            3,
+            4,
+            scale,
+            &font,
+            "fig",
+            &Reversing,
+        );
+        let mut expected = GrayImage::new(80, 60);
+        draw_text_mut(&mut expected, Luma([255u8]), 3, 4, scale, &font, "gﬁ");
+        assert_pixels_eq!(image, expected);
+    }
+
+    #[test]
+    fn test_draw_text_with_reflection_mut() {
+        let font = font();
+        let scale = Scale::uniform(24.0);
+        let ink = ink_bounds(scale, &font, "Glass").unwrap();
+        let gap = 3;
+
+        let mut image = GrayImage::new(90, 80);
+        draw_text_with_reflection_mut(
+            &mut image,
+            Luma([255u8]),
+            0.6,
+            gap,
+            4,
+            2,
+            scale,
+            &font,
+            "Glass",
+        );
+
+        // The text itself is drawn as normal
+        let mut text = GrayImage::new(90, 80);
+        draw_text_mut(&mut text, Luma([255u8]), 4, 2, scale, &font, "Glass");
+        let text_bottom = 2 + ink.bottom() as u32;
+        for (x, y, p) in image
+            .enumerate_pixels()
+            .filter(|(_, y, _)| *y <= text_bottom)
+        {
+            assert_eq!(p[0], text.get_pixel(x, y)[0]);
+        }
+
+        // The reflection mirrors the text's rows below the gap, and is fainter further from the text
+        let row_sum = |image: &GrayImage, y: u32| -> u32 {
+            (0..90).map(|x| image.get_pixel(x, y)[0] as u32).sum()
+        };
+        for y in text_bottom + 1..=text_bottom + gap {
+            assert_eq!(row_sum(&image, y), 0);
+        }
+        let mirror = |r: u32| text_bottom + gap + 1 + r;
+        let near = row_sum(&image, mirror(1));
+        assert!(near > 0 && near < row_sum(&text, text_bottom - 1));
+        let far = ink.height() - 2;
+        assert!((row_sum(&image, mirror(far)) as f32) < near as f32 * 0.5 + 1.0);
+        assert!((mirror(ink.height())..80).all(|y| row_sum(&image, y) == 0));
     }
 
-    /// Draws this `GlyphString` onto the `image` inside a `rectangle` at a `position`.
-    /// For an out-of-place version use [`GlyphString::draw_positioned`](#method.draw_positioned).
-    ///
-    /// ##Example: drawing some text to the center and top-left corner of an image
-    /// ```no_run
-    /// use imageproc::drawing::{EdgePosition, GlyphString, Position};
-    /// use imageproc::rect::Rect;
-    /// use image::{ImageBuffer, Rgb};
-    /// use rusttype::Scale;
-    ///
-    /// let text = "Hello World";
-    /// let scale = Scale::uniform(12.0);
-    /// let font = unimplemented!(); // load your font here
-    /// let mut image = ImageBuffer::from_pixel(100, 100, Rgb([0u8, 0u8, 0u8]));
-    /// let rect = Rect::at(0, 0).of_size(image.width(), image.height());
-    ///
-    /// let position = Position::HorizontalCenter(50.0.into());
-    /// GlyphString::new(scale, &font, &text)
-    ///     .draw_positioned_mut(&mut image, Rgb([0u8, 0u8, 255u8]), &position, &rect);
-    ///
-    /// let position = Position::HorizontalTop(0.0.into());
-    /// GlyphString::new(scale, &font, &text)
-    ///     .draw_positioned_mut(&mut image, Rgb([0u8, 255u8, 0u8]), &position, &rect);
-    /// ```
-    ///
-    /// What we are doing here:
-    ///
-    /// 1. Find an x, y such that
-    ///     1.1 (x, y) lies on the edge of `position` and inside of `rectangle`
-    ///     1.2 (x, y) should be the top-left corner of the text area (the space consumed by the glyphs)
-    ///     1.3 the text area should divide the rectangle according to the given `position`
-    ///     1.4 there should be an equal padding in all directions (from the edges of the text area to the edges of rectangle)
-    /// 2. Draw the text to x, y
-    ///
-    /// <pre>
-    ///     +-----------------------------------------+  = `image` bounds
-    ///     |                                         |
-    ///     |     +-----------------------------+     |  = `rectangle` bounds
-    ///     |     |                             |     |
-    ///     |     |     +-----------------+     |     |  = text area <- y
-    ///     |     |     |Some example text|     |     |
-    ///     |     |     +-----------------+     |     |
-    ///     |     |                             |     |
-    ///     |     +-----------------------------+     |
-    ///     |                                         |
-    ///     +-----------------------------------------+
-    ///
-    ///                 ^
-    ///                 |
-    ///                 x
-    /// </pre>
-    pub fn draw_positioned_mut<'b, I>(
-        &self,
-        image: &'b mut I,
-        color: I::Pixel,
-        position: &Position,
-        rectangle: &IpRect,
-    ) where
-        I: GenericImage,
-        <I::Pixel as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
-    {
-        let width = self.width();
-        let height = self.height();
-        let (x, y) = find_text_area_coordinates(position, rectangle, width, height);
+    #[test]
+    fn test_text_size_empty_contract() {
+        let font = font();
+        let scale = Scale::uniform(20.0);
+
+        // Every way of drawing nothing measures as (0, 0)
+        for text in &["", " ", "\t\t", "\n\n", " \n "] {
+            assert_eq!(text_size(scale, &font, text), (0, 0));
+        }
+        assert_eq!(text_size(Scale::uniform(0.0), &font, "abc"), (0, 0));
 
-        self.draw_mut(image, color, x, y)
+        // But only the empty string has no size at all
+        assert_eq!(checked_text_size(scale, &font, ""), None);
+        assert_eq!(checked_text_size(scale, &font, "  "), Some((0, 0)));
+        assert_eq!(checked_text_size(scale, &font, "\n"), Some((0, 0)));
+        assert_eq!(
+            checked_text_size(Scale::uniform(0.0), &font, "abc"),
+            Some((0, 0))
+        );
+        assert_eq!(
+            checked_text_size(scale, &font, "abc"),
+            Some(text_size(scale, &font, "abc"))
+        );
     }
 
-    /// Draws this `GlyphString` onto a copy of `image` at the given coordinates `x` and `y` and return the copy.
-    /// For an in-place version use [`GlyphString::draw_positioned_mut`](#method.draw_positioned_mut).
-    pub fn draw_positioned<I>(
-        &self,
-        image: &I,
-        color: I::Pixel,
-        position: &Position,
-        rectangle: &IpRect,
-    ) -> Image<I::Pixel>
-    where
-        I: GenericImage,
-        <I::Pixel as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
-        I::Pixel: 'static,
-    {
-        let mut out = ImageBuffer::new(image.width(), image.height());
-        out.copy_from(image, 0, 0);
-        self.draw_positioned_mut(&mut out, color, position, rectangle);
-        out
+    #[test]
+    fn test_draw_text_masked_mut() {
+        let font = font();
+        let scale = Scale::uniform(24.0);
+        let mut text = GrayImage::new(80, 40);
+        draw_text_mut(&mut text, Luma([255u8]), 3, 5, scale, &font, "Mask");
+
+        // A mask which is opaque on its left half and half transparent on its right half, and
+        // smaller than the canvas
+        let mask = GrayImage::from_fn(40, 40, |x, _| Luma([if x < 20 { 255 } else { 128 }]));
+        let mut image = GrayImage::new(80, 40);
+        draw_text_masked_mut(&mut image, Luma([255u8]), 3, 5, scale, &font, "Mask", &mask);
+
+        for (x, y, p) in image.enumerate_pixels() {
+            let full = text.get_pixel(x, y)[0] as f32;
+            let expected = match x {
+                0..=19 => full,
+                20..=39 => full * 128.0 / 255.0,
+                _ => 0.0,
+            };
+            assert!((p[0] as f32 - expected).abs() <= 1.0);
+        }
+        assert!(text.enumerate_pixels().any(|(x, _, p)| x >= 40 && p[0] > 0));
     }
-}
 
-/// The relative position of a point on an edge.
-///
-/// +--------------------------------+
-/// 0%       something else          100%
-///
-/// Edge in this context does not exclusively refer to the edges
-/// which are boundaries of a rectangle but rather all lines which are parallel to two opposing boundaries.
-///
-/// <pre>
-///    +---------------+  <- "typical" horizontal edge
-///    |               |
-///    +---------------+  <- "typical" horizontal edge
-///    ^               ^
-///    |               |
-/// "typical" vertical edges
-///
-///   +---------------+
-///   |               |
-///   |---------------| <- parallel to horizontal edges (= also an edge)
-///   |               |
-///   +---------------+
-/// </pre>
-pub struct EdgePosition(pub f32);
+    #[test]
+    fn test_draw_text_gray_mut_matches_draw_text_mut() {
+        let font = font();
+        let scale = Scale::uniform(23.5);
+        for &(background, color) in &[(0u8, 255u8), (255, 0), (90, 200)] {
+            let mut expected = GrayImage::from_pixel(120, 40, Luma([background]));
+            draw_text_mut(
+                &mut expected,
+                Luma([color]),
+                -3,
+                4,
+                scale,
+                &font,
+                "Fast gray",
+            );
+            let mut actual = GrayImage::from_pixel(120, 40, Luma([background]));
+            draw_text_gray_mut(&mut actual, Luma([color]), -3, 4, scale, &font, "Fast gray");
+            assert_pixels_eq_within!(actual, expected, 1);
+            assert_ne!(actual, GrayImage::from_pixel(120, 40, Luma([background])));
+        }
+    }
 
-impl EdgePosition {
-    /// The left-most point of a horizontal edge.
-    /// Equivalent to `EdgePosition(0.into())`.
-    pub fn left() -> Self {
-        0.into()
+    #[test]
+    fn test_draw_text_positioned_mut_overflow() {
+        let font = font();
+        let scale = Scale::uniform(20.0);
+        let rect = IpRect::at(10, 5).of_size(60, 50);
+        let text = "overflowing text here";
+        let position = Position::HorizontalCenter(EdgePosition::center());
+        let draw = |overflow| {
+            let mut image = GrayImage::new(120, 70);
+            draw_text_positioned_mut(
+                &mut image,
+                Luma([255u8]),
+                &position,
+                &rect,
+                overflow,
+                scale,
+                &font,
+                text,
+            );
+            image
+        };
+        let ink_rows = |image: &GrayImage| {
+            (0..image.height())
+                .filter(|&y| (0..image.width()).any(|x| image.get_pixel(x, y)[0] > 0))
+                .count()
+        };
+
+        let clipped = draw(Overflow::Clip);
+        let shrunk = draw(Overflow::Shrink);
+        let wrapped = draw(Overflow::Wrap);
+        let truncated = draw(Overflow::Ellipsis);
+        for image in &[&clipped, &shrunk, &wrapped, &truncated] {
+            assert!(image.pixels().any(|p| p[0] > 0));
+            for (x, y, p) in image.enumerate_pixels() {
+                if !rect.contains(x as i32, y as i32) {
+                    assert_eq!(p[0], 0);
+                }
+            }
+        }
+
+        // Clipped text reaches the right edge of the rectangle, shrunk text is smaller
+        let reaches_right =
+            |image: &GrayImage| (64..70).any(|x| (0..70).any(|y| image.get_pixel(x, y)[0] > 0));
+        assert!(reaches_right(&clipped));
+        assert!(ink_rows(&shrunk) < ink_rows(&clipped));
+        assert!(ink_rows(&wrapped) > ink_rows(&clipped));
+
+        // Truncated text is positioned as a GlyphString of the shortened text
+        let shortened = truncate_with_ellipsis(scale, &font, text, rect.width());
+        let mut expected = GrayImage::new(120, 70);
+        GlyphString::new(scale, &font, &shortened).draw_positioned_mut(
+            &mut expected,
+            Luma([255u8]),
+            &position,
+            &rect,
+        );
+        assert_pixels_eq!(truncated, expected);
+
+        // Wrapped lines are placed and drawn using the line height of a GlyphString of the wrapped text,
+        // and are pinned to the top and cut off at the bottom of the rectangle when there are too many
+        let lines = wrap_text(scale, &font, text, rect.width()).join("\n");
+        assert!(lines.lines().count() > 2);
+        let mut expected = GrayImage::new(120, 70);
+        GlyphString::new(scale, &font, &lines).draw_positioned_mut(
+            &mut expected,
+            Luma([255u8]),
+            &Position::HorizontalTop(EdgePosition::center()),
+            &rect,
+        );
+        let clip = |image: &mut GrayImage, rect: &IpRect| {
+            for (x, y, p) in image.enumerate_pixels_mut() {
+                if !rect.contains(x as i32, y as i32) {
+                    *p = Luma([0u8]);
+                }
+            }
+        };
+        clip(&mut expected, &rect);
+        assert_pixels_eq!(wrapped, expected);
+
+        // The same holds with custom line spacing, which is kept when the text is wrapped, and the lines
+        // are centered vertically when they fit
+        let tall = IpRect::at(10, 5).of_size(60, 90);
+        let spacing = LineSpacing::Multiplier(1.3);
+        let mut image = GrayImage::new(120, 100);
+        GlyphString::new(scale, &font, text)
+            .with_line_spacing(spacing)
+            .with_overflow(Overflow::Wrap)
+            .draw_positioned_mut(&mut image, Luma([255u8]), &position, &tall);
+        let mut expected = GrayImage::new(120, 100);
+        let string = GlyphString::new(scale, &font, &lines).with_line_spacing(spacing);
+        assert!(string.height() <= tall.height());
+        string.draw_positioned_mut(&mut expected, Luma([255u8]), &position, &tall);
+        clip(&mut expected, &tall);
+        assert_pixels_eq!(image, expected);
+
+        // Text which fits is unaffected by the policy
+        let mut expected = GrayImage::new(120, 70);
+        GlyphString::new(scale, &font, "fits").draw_positioned_mut(
+            &mut expected,
+            Luma([255u8]),
+            &position,
+            &rect,
+        );
+        for &overflow in &[
+            Overflow::Clip,
+            Overflow::Shrink,
+            Overflow::Wrap,
+            Overflow::Ellipsis,
+        ] {
+            let mut image = GrayImage::new(120, 70);
+            draw_text_positioned_mut(
+                &mut image,
+                Luma([255u8]),
+                &position,
+                &rect,
+                overflow,
+                scale,
+                &font,
+                "fits",
+            );
+            assert_pixels_eq!(image, expected);
+        }
     }
-    /// The top-most point of a vertical edge.
-    /// Equivalent to `EdgePosition(0.into())`.
-    pub fn top() -> Self {
-        0.into()
+
+    #[test]
+    fn test_draw_text_append_mut() {
+        let font = font();
+        let scale = Scale::uniform(24.0);
+        let baseline = 20.0;
+
+        let mut expected = GrayImage::new(80, 30);
+        let end = draw_text_append_mut(
+            &mut expected,
+            Luma([255u8]),
+            3.25,
+            baseline,
+            None,
+            scale,
+            &font,
+            "AVA",
+        );
+
+        let mut actual = GrayImage::new(80, 30);
+        let mut pen = 3.25;
+        let mut previous = None;
+        for fragment in &["A", "V", "A"] {
+            pen = draw_text_append_mut(
+                &mut actual,
+                Luma([255u8]),
+                pen,
+                baseline,
+                previous,
+                scale,
+                &font,
+                fragment,
+            );
+            previous = fragment.chars().last();
+        }
+        assert_pixels_eq!(actual, expected);
+        assert_eq!(pen, end);
+        assert!(actual.pixels().any(|p| p[0] > 0));
+
+        // Without kerning between fragments the pen ends up further right
+        let mut unkerned = GrayImage::new(80, 30);
+        let pen = draw_text_append_mut(
+            &mut unkerned,
+            Luma([255u8]),
+            3.25,
+            baseline,
+            None,
+            scale,
+            &font,
+            "A",
+        );
+        let pen = draw_text_append_mut(
+            &mut unkerned,
+            Luma([255u8]),
+            pen,
+            baseline,
+            None,
+            scale,
+            &font,
+            "V",
+        );
+        let kerned = 3.25 + advance_width(scale, &font, "AV", &LayoutOptions::default());
+        assert!(pen > kerned);
     }
 
-    /// The center point of a horizontal or vertical edge.
-    /// Equivalent to `EdgePosition(50.into())`.
-    pub fn center() -> Self {
-        50.into()
+    #[test]
+    fn test_draw_text_embossed_mut() {
+        let font = font();
+        let scale = Scale::uniform(40.0);
+        let draw = |light, depth| {
+            let mut image = GrayImage::from_pixel(60, 50, Luma([200u8]));
+            draw_text_embossed_mut(
+                &mut image,
+                Luma([128u8]),
+                Luma([0u8]),
+                Luma([255u8]),
+                light,
+                depth,
+                5,
+                2,
+                scale,
+                &font,
+                "H",
+            );
+            image
+        };
+        let mean_x = |image: &GrayImage, f: fn(u8) -> bool| {
+            let xs: Vec<u32> = image
+                .enumerate_pixels()
+                .filter(|(_, _, p)| f(p[0]))
+                .map(|(x, _, _)| x)
+                .collect();
+            assert!(!xs.is_empty());
+            xs.iter().sum::<u32>() as f32 / xs.len() as f32
+        };
+
+        // Light from the left shadows the left edges of the stems and highlights their right edges
+        let image = draw((1.0, 0.0), 2);
+        assert!(mean_x(&image, |v| v < 100) < mean_x(&image, |v| v > 220));
+        let image = draw((-1.0, 0.0), 2);
+        assert!(mean_x(&image, |v| v < 100) > mean_x(&image, |v| v > 220));
+
+        // Without depth the text is drawn plain
+        let mut plain = GrayImage::from_pixel(60, 50, Luma([200u8]));
+        draw_text_mut(&mut plain, Luma([128u8]), 5, 2, scale, &font, "H");
+        assert_pixels_eq_within!(draw((1.0, 1.0), 0), plain, 1);
+        assert_pixels_eq_within!(draw((0.0, 0.0), 3), plain, 1);
     }
 
-    /// The right-most point of a horizontal edge.
-    /// Equivalent to `EdgePosition(100.into())`.
-    pub fn right() -> Self {
-        100.into()
+    #[test]
+    fn test_draw_block_cursor_mut() {
+        let font = font();
+        let scale = Scale::uniform(20.0);
+        let style = TextStyle::new(&font, scale, Luma([200u8]));
+        let (w, h) = (
+            advance_width(scale, &font, "M", &LayoutOptions::default()).ceil(),
+            scale.y,
+        );
+        let cell = IpRect::at(12, 3).of_size(w as u32, h as u32);
+
+        let mut image = GrayImage::from_pixel(50, 30, Luma([10u8]));
+        draw_block_cursor_mut(&mut image, cell, 'M', &style, Luma([10u8]));
+
+        // The character is drawn in the background color over the filled cell
+        let mut expected = GrayImage::from_pixel(50, 30, Luma([10u8]));
+        crate::drawing::draw_filled_rect_mut(&mut expected, cell, Luma([200u8]));
+        draw_text_mut(&mut expected, Luma([10u8]), 12, 3, scale, &font, "M");
+        assert_pixels_eq!(image, expected);
+        assert!(image
+            .enumerate_pixels()
+            .any(|(x, y, p)| cell.contains(x as i32, y as i32) && p[0] == 10));
     }
-    /// The bottom-most point of a vertical edge.
-    /// Equivalent to `EdgePosition(100.into())`.
-    pub fn bottom() -> Self {
-        100.into()
+
+    #[test]
+    fn test_missing_glyphs() {
+        let font = font();
+        assert!(missing_glyphs(&font, "Hello, wörld!\n\tÅ").is_empty());
+        assert_eq!(
+            missing_glyphs(&font, "a中b\u{10FFFD}中\r\n"),
+            vec!['中', '\u{10FFFD}']
+        );
+        assert!(missing_glyphs(&font, "").is_empty());
     }
-}
 
-impl From<f32> for EdgePosition {
-    fn from(from: f32) -> Self {
-        Self(from)
+    #[test]
+    fn test_draw_text_auto_contrast_mut() {
+        use image::{Rgba, RgbaImage};
+
+        let font = font();
+        let scale = Scale::uniform(20.0);
+
+        // Dark on the light left half, light on the dark right half
+        let mut image = RgbaImage::from_fn(200, 30, |x, _| {
+            if x < 100 {
+                Rgba([220, 230, 200, 255])
+            } else {
+                Rgba([30, 20, 60, 255])
+            }
+        });
+        let left = draw_text_auto_contrast_mut(&mut image, 5, 2, scale, &font, "left");
+        let right = draw_text_auto_contrast_mut(&mut image, 120, 2, scale, &font, "right");
+        assert_eq!(left, Rgba([0, 0, 0, 255]));
+        assert_eq!(right, Rgba([255, 255, 255, 255]));
+
+        // A custom pair, sampled before drawing so the result doesn't depend on earlier calls
+        let mut image = GrayImage::from_pixel(100, 30, Luma([100u8]));
+        let light = Luma([240u8]);
+        let dark = Luma([80u8]);
+        for _ in 0..2 {
+            let chosen = draw_text_contrast_mut(&mut image, light, dark, 5, 2, scale, &font, "ab");
+            assert_eq!(chosen, light);
+        }
+        let mut expected = GrayImage::from_pixel(100, 30, Luma([100u8]));
+        draw_text_mut(&mut expected, light, 5, 2, scale, &font, "ab");
+        draw_text_mut(&mut expected, light, 5, 2, scale, &font, "ab");
+        assert_pixels_eq!(image, expected);
+
+        // Off the canvas nothing is drawn
+        let mut image = GrayImage::from_pixel(100, 30, Luma([100u8]));
+        let chosen = draw_text_contrast_mut(&mut image, light, dark, 200, 2, scale, &font, "ab");
+        assert_eq!(chosen, light);
+        assert!(image.pixels().all(|p| p[0] == 100));
     }
-}
 
-impl From<u32> for EdgePosition {
-    fn from(from: u32) -> Self {
-        Self(from as _)
+    #[test]
+    fn test_draw_labels_mut() {
+        let (font, other) = (font(), font());
+        let scale = Scale::uniform(16.0);
+        let plain = TextStyle::new(&font, scale, Luma([255u8]));
+        let labels = [
+            Label {
+                text: "Paris",
+                x: 3,
+                y: 2,
+                style: plain,
+            },
+            Label {
+                text: "Lyon",
+                x: 50,
+                y: 20,
+                style: plain.color(Luma([120u8])),
+            },
+            Label {
+                text: "Nice",
+                x: 10,
+                y: 40,
+                style: plain.aliasing(Aliasing::Aliased).tracking(2.0),
+            },
+            Label {
+                text: "Brest",
+                x: 60,
+                y: 45,
+                style: plain.outline(Luma([60u8]), 2),
+            },
+            Label {
+                text: "Paris",
+                x: -10,
+                y: 60,
+                style: plain.font(&other),
+            },
+            Label {
+                text: "far away",
+                x: 500,
+                y: 500,
+                style: plain,
+            },
+        ];
+
+        let mut image = GrayImage::new(120, 80);
+        let drawn = draw_labels_mut(&mut image, &labels);
+
+        let mut expected = GrayImage::new(120, 80);
+        let expected_drawn: Vec<Option<IpRect>> = labels
+            .iter()
+            .map(|label| {
+                label
+                    .style
+                    .draw_mut(&mut expected, label.x, label.y, label.text)
+            })
+            .collect();
+        assert_pixels_eq!(image, expected);
+        assert_eq!(drawn, expected_drawn);
+        assert!(drawn[0].is_some());
+        assert!(drawn[5].is_none());
     }
-}
 
-/// A position inside a rectangle
-pub enum Position {
-    /// top edge
-    /// <pre>
-    /// +---------------+  <- this
-    /// |               |
-    /// +---------------+
-    /// </pre>
-    HorizontalTop(EdgePosition),
-    /// horizontal center edge (horizontally centered between top and bottom)
-    /// <pre>
-    /// +---------------+
-    /// |               |
-    /// |---------------| <- this
-    /// |               |
-    /// +---------------+
-    /// </pre>
-    HorizontalCenter(EdgePosition),
-    /// bottom edge
-    /// <pre>
-    /// +---------------+
-    /// |               |
-    /// +---------------+  <- this
-    /// </pre>
-    HorizontalBottom(EdgePosition),
-    /// left edge
-    /// <pre>
-    /// +---------------+
-    /// |               |
-    /// +---------------+
-    /// ^
-    /// |
-    /// this
-    /// </pre>
-    VerticalLeft(EdgePosition),
-    /// Vertical center edge (vertically centered between left and right)
-    /// <pre>
-    /// +---------------+
-    /// |       |       |
-    /// |       |       |
-    /// |       |       |
-    /// +---------------+
-    ///         ^
-    ///         |
-    ///         this
-    /// </pre>
-    VerticalCenter(EdgePosition),
-    /// left edge
-    /// <pre>
-    /// +---------------+
-    /// |               |
-    /// +---------------+
-    ///                 ^
-    ///                 |
-    ///                 this
-    /// </pre>
-    VerticalRight(EdgePosition),
-    /// fine-grained control over horizontal and vertical edges
-    Any(EdgePosition, EdgePosition),
-}
+    #[test]
+    fn test_draw_text_supersampled_mut() {
+        let font = font();
+        let scale = Scale::uniform(14.0);
+        let draw = |factor| {
+            let mut image = GrayImage::new(80, 24);
+            draw_text_supersampled_mut(
+                &mut image,
+                Luma([255u8]),
+                4,
+                3,
+                scale,
+                &font,
+                "Smooth",
+                factor,
+            );
+            image
+        };
+        let mut expected = GrayImage::new(80, 24);
+        draw_text_mut(&mut expected, Luma([255u8]), 4, 3, scale, &font, "Smooth");
+        let total = |image: &GrayImage| image.pixels().map(|p| p[0] as i32).sum::<i32>();
 
-/// An arrangement of PositionedGlyphStrings
-pub struct GlyphStrings<'a>(pub &'a [&'a GlyphString<'a>]);
+        assert_pixels_eq_within!(draw(1), expected, 1);
+        assert_eq!(draw(0), draw(1));
 
-impl<'a> GlyphStrings<'a> {
-    /// create
-    #[inline]
-    pub fn new(glyph_strings: &'a [&GlyphString<'a>]) -> Self {
-        Self(glyph_strings)
+        // Supersampled text lies in the same place with about the same amount of ink
+        for &factor in &[2, 4] {
+            let image = draw(factor);
+            assert_ne!(image, expected);
+            assert!((total(&image) - total(&expected)).abs() < total(&expected) / 10);
+            let (first, last) = ink_columns(&image, 0..24).unwrap();
+            let (expected_first, expected_last) = ink_columns(&expected, 0..24).unwrap();
+            assert!((first as i32 - expected_first as i32).abs() <= 1);
+            assert!((last as i32 - expected_last as i32).abs() <= 1);
+        }
     }
 
-    /// draw text
-    #[inline]
-    pub fn draw_positioned_mut<'b, I>(
-        &self,
-        image: &'b mut I,
-        colors: &[I::Pixel],
-        position: &Position,
-        rectangle: &IpRect,
-    ) where
-        I: GenericImage,
-        <I::Pixel as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
-    {
-        let width = self.width();
-        let height = self.height();
-        let (mut x, y) = find_text_area_coordinates(position, rectangle, width, height);
+    #[test]
+    fn test_draw_text_stamped_mut() {
+        let font = font();
+        let scale = Scale::uniform(20.0);
+        let mut image = GrayImage::from_pixel(80, 30, Luma([40u8]));
+        let (stamp, (sx, sy)) =
+            draw_text_stamped_mut(&mut image, Luma([255u8]), 6, 4, scale, &font, "Stamp");
+
+        let mut expected = GrayImage::from_pixel(80, 30, Luma([40u8]));
+        draw_text_mut(&mut expected, Luma([255u8]), 6, 4, scale, &font, "Stamp");
+        assert_pixels_eq_within!(image, expected, 1);
+
+        let (mask, (mx, my)) = rasterize_text(scale, &font, "Stamp");
+        assert_eq!(stamp, mask);
+        assert_eq!((sx, sy), (mx + 6, my + 4));
 
-        for (string, &color) in self.0.iter().zip(colors.iter()) {
-            string.draw_mut(image, color, x as _, y as _);
-            x += string.width()
+        // The stamp covers every pixel which was changed
+        for (x, y, p) in image.enumerate_pixels() {
+            let (px, py) = (x as i32 - sx, y as i32 - sy);
+            let inside =
+                px >= 0 && py >= 0 && px < stamp.width() as i32 && py < stamp.height() as i32;
+            if p[0] > 41 {
+                assert!(inside && stamp.get_pixel(px as u32, py as u32)[0] > 0);
+            }
         }
     }
 
-    /// Find out how much vertical space this `GlyphStrings` needs when drawn.
-    pub fn height(&self) -> u32 {
-        self.0
-            .iter()
-            .map(|string| string.height())
-            .max()
-            .unwrap_or(0)
+    #[test]
+    fn test_draw_text_aligned_mut_empty_line_takes_space() {
+        let font = font();
+        let scale = Scale::uniform(20.0);
+        let (_, one_gap) = text_size(scale, &font, "a\nb");
+        let (_, two_gaps) = text_size(scale, &font, "a\n\nb");
+        assert!(two_gaps > one_gap);
     }
 
-    /// Find out how much horizontal space this `GlyphStrings` needs when drawn.
-    pub fn width(&self) -> u32 {
-        self.0.iter().map(|string| string.width()).sum::<u32>()
+    #[test]
+    fn test_draw_text_bounded_mut_off_canvas() {
+        let font = font();
+        let scale = Scale::uniform(20.0);
+        let mut image = GrayImage::new(10, 10);
+        let rect = draw_text_bounded_mut(&mut image, Luma([255u8]), 50, 50, scale, &font, "Hi");
+        assert_eq!(rect, None);
+        assert_eq!(image, GrayImage::new(10, 10));
     }
 }